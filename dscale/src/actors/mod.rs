@@ -1,7 +1,19 @@
 pub(crate) mod network_actor;
 pub(crate) mod timer_actor;
 
-use crate::{event::Event, global::local_access::EventBatch, jiffy::Jiffies, step::Step};
+use std::sync::Arc;
+
+use crate::{
+    Rank,
+    event::Event,
+    factory::Factory,
+    global::{self, local_access::EventBatch},
+    jiffy::Jiffies,
+    random::Seed,
+    runners::report::Counters,
+    step::Step,
+    topology::Topology,
+};
 
 use network_actor::NetworkActor;
 use timer_actor::TimerActor;
@@ -15,9 +27,36 @@ pub(crate) trait SimulationActor {
 pub(crate) struct Actors {
     pub(crate) network: NetworkActor,
     pub(crate) timers: TimerActor,
+    pub(crate) topology: Arc<Topology>,
+}
+
+/// Process that joined or left the simulation during a step.
+/// The topology is already updated, the runner still has to apply it to its processes.
+pub(crate) enum Churn {
+    Spawned {
+        rank: Rank,
+        pool: String,
+        factory: Factory,
+        index: usize,
+        pool_size: usize,
+    },
+    Exited {
+        rank: Rank,
+    },
 }
 
 impl Actors {
+    /// Copy of the actors for a fork of the simulation, on a copy of the topology
+    /// and sampling latencies from `seed`.
+    pub(crate) fn fork(&self, seed: Seed) -> Self {
+        let topology = Arc::new(self.topology.fork());
+        Self {
+            network: self.network.fork(topology.clone(), seed),
+            timers: self.timers.clone(),
+            topology,
+        }
+    }
+
     pub(super) fn next_step(&mut self) -> Step {
         let t = self.timers.peek_next_step();
         let n = self.network.peek_next_step();
@@ -39,12 +78,66 @@ impl Actors {
         }
     }
 
-    pub(super) fn submit(&mut self, events: &mut EventBatch) {
+    pub(crate) fn counters(&self) -> Counters {
+        Counters {
+            traffic: self.network.traffic().to_vec(),
+            timers_fired: self.timers.fired(),
+        }
+    }
+
+    /// Returns the peak bandwidth queue depth since the previous call.
+    pub(crate) fn take_peak_queue_depth(&mut self) -> usize {
+        self.network.take_peak_queue_depth()
+    }
+
+    pub(crate) fn pool_names(&self) -> &[String] {
+        self.network.pool_names()
+    }
+
+    /// Returns `true` if no message is in flight and every pending timer is periodic.
+    pub(super) fn idle(&self) -> bool {
+        self.network.peek_next_step().is_none() && !self.timers.one_shot_pending()
+    }
+
+    /// Submits the events of a step in the order they were issued.
+    /// Returns the processes that joined or left, if any.
+    pub(super) fn submit(&mut self, events: &mut EventBatch) -> Vec<Churn> {
+        let mut churn = Vec::new();
         for event in events.drain(..) {
             match event {
+                // Timers scheduled after exit() of the same step never fire
+                Event::TimerEvent { rank, .. } if !self.topology.is_alive(rank) => {}
                 e @ Event::TimerEvent { .. } => self.timers.submit(e),
                 e @ Event::NetworkEvent { .. } => self.network.submit(e),
+                Event::Spawn {
+                    rank,
+                    pool,
+                    factory,
+                    receives,
+                } => {
+                    let joined = self.topology.join(rank, &pool, receives);
+                    global::configuration::setup_global_configuration(
+                        self.topology.process_number(),
+                    );
+                    churn.push(Churn::Spawned {
+                        rank,
+                        pool,
+                        factory,
+                        index: joined.index,
+                        pool_size: joined.pool_size,
+                    });
+                }
+                Event::Exit { rank } => {
+                    self.topology.leave(rank);
+                    self.timers.discard_of(rank);
+                    self.network.discard_to(rank);
+                    churn.push(Churn::Exited { rank });
+                }
+                Event::CreatePool { pool } => self.topology.create_pool(&pool),
+                Event::JoinPool { rank, pool } => self.topology.add_to_pool(&pool, rank),
+                Event::LeavePool { rank, pool } => self.topology.remove_from_pool(&pool, rank),
             }
         }
+        churn
     }
 }
@@ -1,9 +1,13 @@
-use std::collections::BinaryHeap;
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{Codecs, SavedBandwidth, SavedDelivery};
 
 use crate::{
+    MessagePtr, Rank,
     actors::network_actor::LatencyQueue,
+    event_queue::EventQueueConfig,
     jiffy::Jiffies,
     now,
+    random::{Distributions, Randomizer, Seed},
     step::{Step, StepQueue, TimedStep},
 };
 
@@ -23,13 +27,14 @@ pub(crate) struct BandwidthQueue {
     global_queue: LatencyQueue,
     total_pased: Vec<usize>,
     merged_fifo_buffers: StepQueue,
+    peak_depth: usize,
 }
 
 impl BandwidthQueue {
     pub(crate) fn new(
         bandwidth_type: BandwidthConfig,
-        proc_num: usize,
         global_queue: LatencyQueue,
+        queue: EventQueueConfig,
     ) -> Self {
         let bandwidth = match bandwidth_type {
             BandwidthConfig::Unbounded => usize::MAX,
@@ -39,19 +44,37 @@ impl BandwidthQueue {
         Self {
             bandwidth,
             global_queue,
-            total_pased: vec![0; proc_num + 1],
-            merged_fifo_buffers: BinaryHeap::new(),
+            total_pased: Vec::new(),
+            merged_fifo_buffers: StepQueue::new(queue),
+            peak_depth: 0,
         }
     }
 
-    pub(crate) fn push(&mut self, message: TimedStep) {
-        self.global_queue.push(message);
+    pub(crate) fn fork(&self, seed: Seed) -> Self {
+        Self {
+            bandwidth: self.bandwidth,
+            global_queue: self.global_queue.fork(seed),
+            total_pased: self.total_pased.clone(),
+            merged_fifo_buffers: self.merged_fifo_buffers.clone(),
+            peak_depth: self.peak_depth,
+        }
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        source: Rank,
+        message: MessagePtr,
+        base_time: Jiffies,
+        targets: impl IntoIterator<Item = (Rank, Distributions)>,
+    ) {
+        self.global_queue
+            .push(source, message, now(), base_time, targets);
     }
 
     pub(crate) fn pop(&mut self) -> Option<TimedStep> {
         loop {
             let latency_time = self.global_queue.peek();
-            let buffer_time = self.merged_fifo_buffers.peek().map(|e| e.0.invocation_time);
+            let buffer_time = self.merged_fifo_buffers.peek_time();
 
             match (latency_time, buffer_time) {
                 (None, None) => return None,
@@ -76,9 +99,88 @@ impl BandwidthQueue {
         }
     }
 
+    /// Removes and returns every message to the process.
+    pub(crate) fn discard_to(&mut self, rank: Rank) -> Vec<TimedStep> {
+        let mut discarded = self.global_queue.discard_to(rank);
+        discarded.extend(
+            self.merged_fifo_buffers
+                .extract(|step| step.step.target_rank() == rank),
+        );
+        discarded
+    }
+
+    /// Removes the deliveries of a message still in flight. Returns their targets.
+    /// Messages already waiting for bandwidth are not looked at.
+    pub(crate) fn cancel(&mut self, source: Rank, message: &MessagePtr) -> Vec<Rank> {
+        self.global_queue.cancel(source, message)
+    }
+
+    pub(crate) fn save_generator(&mut self, source: Rank) -> Randomizer {
+        self.global_queue.save_generator(source)
+    }
+
+    pub(crate) fn restore_generator(&mut self, source: Rank, generator: Randomizer) {
+        self.global_queue.restore_generator(source, generator);
+    }
+
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn save(&self, codecs: &Codecs) -> SavedBandwidth {
+        let buffered = self
+            .merged_fifo_buffers
+            .ordered()
+            .into_iter()
+            .map(|timed_step| {
+                let Step::NetworkStep {
+                    source,
+                    target,
+                    ref message,
+                    sent_at,
+                } = timed_step.step
+                else {
+                    unreachable!("BandwidthQueue only accepts NetworkSteps");
+                };
+                SavedDelivery {
+                    time: timed_step.invocation_time,
+                    source,
+                    target,
+                    message: codecs.save_message(message),
+                    sent_at,
+                }
+            })
+            .collect();
+        SavedBandwidth {
+            latency: self.global_queue.save(codecs),
+            buffered,
+            passed: self.total_pased.clone(),
+        }
+    }
+
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn load(&mut self, saved: SavedBandwidth, codecs: &Codecs) {
+        self.global_queue.load(saved.latency, codecs);
+        self.merged_fifo_buffers.retain_mut(|_| false);
+        for delivery in saved.buffered {
+            self.merged_fifo_buffers.push(TimedStep {
+                invocation_time: delivery.time,
+                step: Step::NetworkStep {
+                    source: delivery.source,
+                    target: delivery.target,
+                    message: codecs.load_message(&delivery.message),
+                    sent_at: delivery.sent_at,
+                },
+            });
+        }
+        self.total_pased = saved.passed;
+    }
+
+    /// Returns the peak number of messages waiting for bandwidth since the previous call.
+    pub(crate) fn take_peak_depth(&mut self) -> usize {
+        std::mem::replace(&mut self.peak_depth, self.merged_fifo_buffers.len())
+    }
+
     pub(crate) fn peek_closest(&self) -> Option<Jiffies> {
         let latency_time = self.global_queue.peek();
-        let buffer_time = self.merged_fifo_buffers.peek().map(|e| e.0.invocation_time);
+        let buffer_time = self.merged_fifo_buffers.peek_time();
 
         match (latency_time, buffer_time) {
             (None, None) => None,
@@ -89,6 +191,14 @@ impl BandwidthQueue {
 }
 
 impl BandwidthQueue {
+    /// Bytes that passed the NIC of the process. Grows with spawned processes.
+    fn passed(&mut self, rank: Rank) -> &mut usize {
+        if self.total_pased.len() <= rank {
+            self.total_pased.resize(rank + 1, 0);
+        }
+        &mut self.total_pased[rank]
+    }
+
     fn move_message_from_latency_queue_to_buffers(&mut self) {
         let mut message = self
             .global_queue
@@ -103,21 +213,21 @@ impl BandwidthQueue {
         else {
             unreachable!("BandwidthQueue only accepts NetworkSteps");
         };
-        let new_total = self.total_pased[target] + msg.0.virtual_size();
+        let new_total = *self.passed(target) + msg.0.virtual_size();
 
         if new_total > now().0 * self.bandwidth {
             message.invocation_time = Jiffies(new_total / self.bandwidth); // > now()
         }
 
-        self.merged_fifo_buffers.push(std::cmp::Reverse(message));
+        self.merged_fifo_buffers.push(message);
+        self.peak_depth = self.peak_depth.max(self.merged_fifo_buffers.len());
     }
 
     fn deliver_from_buffer(&mut self) -> Option<TimedStep> {
         let timed_step = self
             .merged_fifo_buffers
             .pop()
-            .expect("All buffers should not be empty")
-            .0;
+            .expect("All buffers should not be empty");
         let Step::NetworkStep {
             target,
             message: ref msg,
@@ -126,7 +236,7 @@ impl BandwidthQueue {
         else {
             unreachable!("BandwidthQueue only accepts NetworkSteps");
         };
-        self.total_pased[target] += msg.0.virtual_size();
+        *self.passed(target) += msg.0.virtual_size();
         Some(timed_step)
     }
 
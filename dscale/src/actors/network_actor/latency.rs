@@ -1,44 +1,219 @@
-use std::collections::BinaryHeap;
-use std::sync::Arc;
+use std::cmp::Reverse;
 
 use log::debug;
+use smallvec::SmallVec;
 
+use crate::Rank;
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{Codecs, SavedFanOut, SavedLatency};
+use crate::event_queue::{EventQueueConfig, FutureEvents, Timed};
 use crate::jiffy::Jiffies;
-use crate::random::Randomizer;
-use crate::step::{Step, StepQueue, TimedStep};
-use crate::topology::Topology;
+use crate::message::MessagePtr;
+use crate::random::{self, Distributions, Randomizer, Seed};
+use crate::step::{Step, TimedStep};
+
+/// One message on its way to several targets, kept as a single queue entry
+/// and expanded into deliveries one at a time as they become due.
+#[derive(Clone)]
+struct FanOut {
+    source: Rank,
+    message: MessagePtr,
+    sent_at: Jiffies,
+    // Sorted by (delivery time, target), latest first, so the next one pops off the end
+    deliveries: SmallVec<[(Jiffies, Rank); 1]>,
+}
+
+impl FanOut {
+    /// Takes the next delivery as a fan-out of its own, unless it is the last one.
+    fn split(&mut self) -> Option<FanOut> {
+        if self.deliveries.len() == 1 {
+            return None;
+        }
+        let next = self.deliveries.pop().expect("Empty fan-out");
+        Some(FanOut {
+            source: self.source,
+            message: self.message.clone(),
+            sent_at: self.sent_at,
+            deliveries: SmallVec::from_buf([next]),
+        })
+    }
+}
+
+impl Timed for FanOut {
+    fn time(&self) -> Jiffies {
+        self.deliveries.last().expect("Empty fan-out").0
+    }
+}
 
 pub(crate) struct LatencyQueue {
-    topology: Arc<Topology>,
-    randomizer: Randomizer,
-    queue: StepQueue,
+    seed: Seed,
+    // Generator of every source, its messages sample their latencies from it
+    generators: Vec<Randomizer>,
+    queue: FutureEvents<FanOut>,
 }
 
 impl LatencyQueue {
-    pub(crate) fn new(randomizer: Randomizer, topology: Arc<Topology>) -> Self {
+    pub(crate) fn new(seed: Seed, queue: EventQueueConfig) -> Self {
+        Self {
+            seed,
+            generators: Vec::new(),
+            queue: FutureEvents::new(queue),
+        }
+    }
+
+    /// Copy of the queue for a fork of the simulation, sampling latencies from `seed`.
+    pub(crate) fn fork(&self, seed: Seed) -> Self {
         Self {
-            randomizer,
-            topology,
-            queue: BinaryHeap::new(),
+            seed,
+            generators: Vec::new(),
+            queue: self.queue.clone(),
+        }
+    }
+
+    /// Generator of the source, created on its first message.
+    fn generator(&mut self, source: Rank) -> &mut Randomizer {
+        if self.generators.len() <= source {
+            let seed = self.seed;
+            let sources = self.generators.len()..=source;
+            self.generators
+                .extend(sources.map(|rank| random::process_generators(seed, rank).1));
         }
+        &mut self.generators[source]
     }
 
-    pub(crate) fn push(&mut self, mut message: TimedStep) {
-        debug!("Before latency: {}", message.invocation_time);
-        let Step::NetworkStep { source, target, .. } = &message.step else {
-            unreachable!("LatencyQueue only accepts NetworkSteps");
-        };
-        let distribution = self.topology.get_distribution(*source, *target);
-        message.invocation_time += self.randomizer.random_usize(distribution);
-        debug!("After latency: {}", message.invocation_time);
-        self.queue.push(std::cmp::Reverse(message));
+    /// Copy of the generator of the source, to go back to it with
+    /// [`LatencyQueue::restore_generator`] when its messages are taken back.
+    pub(crate) fn save_generator(&mut self, source: Rank) -> Randomizer {
+        self.generator(source).clone()
+    }
+
+    pub(crate) fn restore_generator(&mut self, source: Rank, generator: Randomizer) {
+        *self.generator(source) = generator;
+    }
+
+    /// Samples the latency to every target from its distribution and enqueues
+    /// the deliveries as one fan-out, so a broadcast costs a single heap insert.
+    pub(crate) fn push(
+        &mut self,
+        source: Rank,
+        message: MessagePtr,
+        sent_at: Jiffies,
+        base_time: Jiffies,
+        targets: impl IntoIterator<Item = (Rank, Distributions)>,
+    ) {
+        let generator = self.generator(source);
+        let mut deliveries: SmallVec<[(Jiffies, Rank); 1]> = targets
+            .into_iter()
+            .map(|(target, distribution)| {
+                let latency = generator.random_usize(distribution);
+                (base_time + Jiffies(latency), target)
+            })
+            .collect();
+        if deliveries.is_empty() {
+            return;
+        }
+        deliveries.sort_unstable_by_key(|&delivery| Reverse(delivery));
+        debug!(
+            "After latency: {} .. {}",
+            deliveries.last().unwrap().0,
+            deliveries[0].0
+        );
+        self.queue.push(FanOut {
+            source,
+            message,
+            sent_at,
+            deliveries,
+        });
+    }
+
+    /// Removes and returns every message to the process.
+    pub(crate) fn discard_to(&mut self, rank: Rank) -> Vec<TimedStep> {
+        let mut discarded = Vec::new();
+        self.queue.retain_mut(|fan_out| {
+            fan_out.deliveries.retain(|&mut (invocation_time, target)| {
+                if target != rank {
+                    return true;
+                }
+                discarded.push(TimedStep {
+                    invocation_time,
+                    step: Step::NetworkStep {
+                        source: fan_out.source,
+                        target,
+                        message: fan_out.message.clone(),
+                        sent_at: fan_out.sent_at,
+                    },
+                });
+                false
+            });
+            !fan_out.deliveries.is_empty()
+        });
+        discarded
+    }
+
+    /// Removes the deliveries of a message still in flight. Returns their targets.
+    pub(crate) fn cancel(&mut self, source: Rank, message: &MessagePtr) -> Vec<Rank> {
+        let mut targets = Vec::new();
+        self.queue.retain_mut(|fan_out| {
+            if fan_out.source != source || !fan_out.message.is_same(message) {
+                return true;
+            }
+            targets.extend(fan_out.deliveries.iter().map(|&(_, target)| target));
+            false
+        });
+        targets
+    }
+
+    /// Saves the messages in flight and the generators of their latencies.
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn save(&self, codecs: &Codecs) -> SavedLatency {
+        let in_flight = self
+            .queue
+            .ordered()
+            .into_iter()
+            .map(|fan_out| SavedFanOut {
+                source: fan_out.source,
+                message: codecs.save_message(&fan_out.message),
+                sent_at: fan_out.sent_at,
+                deliveries: fan_out.deliveries.to_vec(),
+            })
+            .collect();
+        SavedLatency {
+            generators: self.generators.clone(),
+            in_flight,
+        }
+    }
+
+    /// Replaces the messages in flight with saved ones.
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn load(&mut self, saved: SavedLatency, codecs: &Codecs) {
+        self.generators = saved.generators;
+        self.queue.retain_mut(|_| false);
+        for fan_out in saved.in_flight {
+            self.queue.push(FanOut {
+                source: fan_out.source,
+                message: codecs.load_message(&fan_out.message),
+                sent_at: fan_out.sent_at,
+                deliveries: fan_out.deliveries.into(),
+            });
+        }
     }
 
     pub(crate) fn pop(&mut self) -> Option<TimedStep> {
-        Some(self.queue.pop()?.0)
+        // The rest of the fan-out stays queued in place
+        let delivery = self.queue.pop_split(FanOut::split)?;
+        let (invocation_time, target) = delivery.deliveries[0];
+        Some(TimedStep {
+            invocation_time,
+            step: Step::NetworkStep {
+                source: delivery.source,
+                target,
+                message: delivery.message,
+                sent_at: delivery.sent_at,
+            },
+        })
     }
 
     pub(crate) fn peek(&self) -> Option<Jiffies> {
-        Some(self.queue.peek()?.0.invocation_time)
+        self.queue.peek_time()
     }
 }
@@ -8,23 +8,25 @@ pub(crate) use bandwidth::BandwidthQueue;
 pub(crate) use latency::LatencyQueue;
 use log::debug;
 
-use crate::GLOBAL_POOL;
 use crate::MessagePtr;
 use crate::Rank;
 use crate::actors::SimulationActor;
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{Codecs, SavedNetwork};
 use crate::destination::Destination;
 use crate::event::Event;
+use crate::event_queue::EventQueueConfig;
 use crate::jiffy::Jiffies;
 use crate::now;
-use crate::random::Randomizer;
-use crate::random::Seed;
+use crate::random::{Randomizer, Seed};
+use crate::runners::report::TrafficStats;
 use crate::step::Step;
-use crate::step::TimedStep;
 use crate::topology::Topology;
 
 pub(crate) struct NetworkActor {
     bandwidth_queue: BandwidthQueue,
     topology: Arc<Topology>,
+    traffic: Vec<TrafficStats>,
 }
 
 impl NetworkActor {
@@ -34,24 +36,27 @@ impl NetworkActor {
         source: Rank,
         destination: Destination,
     ) {
+        // One read of the membership for every target, even of a broadcast
+        let members = self.topology.members();
         let targets = match destination {
-            Destination::BroadcastWithinPool(pool_name) => self.topology.list_pool(pool_name),
-            Destination::Target(rank) => &[rank],
+            Destination::BroadcastWithinPool(pool_name) => members.pool(pool_name),
+            Destination::Target(ref rank) => std::slice::from_ref(rank),
+            Destination::Multicast(ref ranks) => &ranks[..],
         };
 
-        debug!("Submitting steps P{source} -> P{targets:?}");
+        debug!("Submitting {message:?} P{source} -> P{targets:?}");
         let base_time = now() + Jiffies(1);
-        for &target in targets {
-            let timed_step = TimedStep {
-                invocation_time: base_time,
-                step: Step::NetworkStep {
-                    source: source,
-                    target: target,
-                    message: message.clone(),
-                },
-            };
-            self.bandwidth_queue.push(timed_step);
-        }
+        let traffic = &mut self.traffic;
+        let alive = targets.iter().copied().filter_map(|target| {
+            let traffic = &mut traffic[members.pool_pair(source, target)];
+            traffic.sent += 1;
+            if !members.is_alive(target) {
+                traffic.dropped += 1;
+                return None;
+            }
+            Some((target, members.get_distribution(source, target)))
+        });
+        self.bandwidth_queue.push(source, message, base_time, alive);
     }
 }
 
@@ -60,24 +65,133 @@ impl NetworkActor {
         seed: Seed,
         bandwidth_type: BandwidthConfig,
         topology: Arc<Topology>,
+        queue: EventQueueConfig,
     ) -> Self {
         Self {
             bandwidth_queue: BandwidthQueue::new(
                 bandwidth_type,
-                topology.list_pool(GLOBAL_POOL).len(),
-                LatencyQueue::new(Randomizer::new(seed), topology.clone()),
+                LatencyQueue::new(seed, queue),
+                queue,
             ),
+            traffic: vec![TrafficStats::default(); topology.pool_names().len().pow(2)],
             topology,
         }
     }
+
+    /// Copy of the network for a fork of the simulation, see [`crate::actors::Actors::fork`].
+    pub(crate) fn fork(&self, topology: Arc<Topology>, seed: Seed) -> Self {
+        Self {
+            bandwidth_queue: self.bandwidth_queue.fork(seed),
+            topology,
+            traffic: self.traffic.clone(),
+        }
+    }
+
+    pub(crate) fn traffic(&self) -> &[TrafficStats] {
+        &self.traffic
+    }
+
+    pub(crate) fn take_peak_queue_depth(&mut self) -> usize {
+        self.bandwidth_queue.take_peak_depth()
+    }
+
+    pub(crate) fn pool_names(&self) -> &[String] {
+        self.topology.pool_names()
+    }
+
+    /// Drops every message in flight to the process, e.g. when it exits.
+    pub(crate) fn discard_to(&mut self, rank: Rank) {
+        for step in self.bandwidth_queue.discard_to(rank) {
+            if let Step::NetworkStep { source, target, .. } = step.step {
+                self.traffic[self.topology.pool_pair(source, target)].dropped += 1;
+            }
+        }
+    }
+
+    /// Takes back a message sent by a step that was rolled back: its deliveries
+    /// still in flight are removed and no longer count as sent. Returns their targets.
+    pub(crate) fn cancel(&mut self, source: Rank, message: &MessagePtr) -> Vec<Rank> {
+        let targets = self.bandwidth_queue.cancel(source, message);
+        for &target in &targets {
+            self.traffic[self.topology.pool_pair(source, target)].sent -= 1;
+        }
+        targets
+    }
+
+    /// Copy of the generator the latencies of messages of the source are sampled from.
+    pub(crate) fn save_generator(&mut self, source: Rank) -> Randomizer {
+        self.bandwidth_queue.save_generator(source)
+    }
+
+    /// Goes back to a generator of the source, when the messages sampled
+    /// from it since it was saved are taken back.
+    pub(crate) fn restore_generator(&mut self, source: Rank, generator: Randomizer) {
+        self.bandwidth_queue.restore_generator(source, generator);
+    }
+
+    /// Takes back a delivery of a cancelled message that was already taken for delivery.
+    pub(crate) fn retract(&mut self, source: Rank, target: Rank, message: &MessagePtr) {
+        let traffic = &mut self.traffic[self.topology.pool_pair(source, target)];
+        traffic.sent -= 1;
+        traffic.delivered -= 1;
+        traffic.bytes -= message.0.virtual_size();
+    }
+
+    /// Takes back a delivery of a cancelled message that was dropped.
+    pub(crate) fn retract_dropped(&mut self, source: Rank, target: Rank) {
+        let traffic = &mut self.traffic[self.topology.pool_pair(source, target)];
+        traffic.sent -= 1;
+        traffic.dropped -= 1;
+    }
+
+    /// Saves the messages in flight and the traffic accounted so far.
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn save(&self, codecs: &Codecs) -> SavedNetwork {
+        SavedNetwork {
+            bandwidth: self.bandwidth_queue.save(codecs),
+            traffic: self.traffic.clone(),
+        }
+    }
+
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn load(&mut self, saved: SavedNetwork, codecs: &Codecs) {
+        assert_eq!(
+            saved.traffic.len(),
+            self.traffic.len(),
+            "Checkpoint of a simulation with other pools"
+        );
+        self.bandwidth_queue.load(saved.bandwidth, codecs);
+        self.traffic = saved.traffic;
+    }
+
+    /// Accounts a message taken for delivery as dropped instead,
+    /// because its target exited before it could be handled.
+    pub(crate) fn undeliver(&mut self, source: Rank, target: Rank) {
+        let traffic = &mut self.traffic[self.topology.pool_pair(source, target)];
+        traffic.delivered -= 1;
+        traffic.dropped += 1;
+    }
 }
 
 impl SimulationActor for NetworkActor {
     fn next_step(&mut self) -> Step {
-        self.bandwidth_queue
+        let step = self
+            .bandwidth_queue
             .pop()
             .expect("Should not be empty")
-            .step
+            .step;
+        if let Step::NetworkStep {
+            source,
+            target,
+            ref message,
+            ..
+        } = step
+        {
+            let traffic = &mut self.traffic[self.topology.pool_pair(source, target)];
+            traffic.delivered += 1;
+            traffic.bytes += message.0.virtual_size();
+        }
+        step
     }
 
     fn peek_next_step(&self) -> Option<Jiffies> {
@@ -1,8 +1,12 @@
-use std::cmp::Reverse;
+use rustc_hash::FxHashSet;
 
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::SavedTimers;
 use crate::{
+    Rank,
     actors::SimulationActor,
     event::Event,
+    event_queue::EventQueueConfig,
     jiffy::Jiffies,
     now,
     step::{Step, StepQueue, TimedStep},
@@ -10,24 +14,110 @@ use crate::{
 
 pub type TimerId = usize;
 
-#[derive(Default)]
+#[derive(Clone)]
 pub(crate) struct TimerActor {
     working_timers: StepQueue,
+    // Pending timers that are not periodic, see `one_shot_pending`
+    one_shot: FxHashSet<TimerId>,
+    fired: usize,
+}
+
+impl TimerActor {
+    pub(crate) fn new(queue: EventQueueConfig) -> Self {
+        Self {
+            working_timers: StepQueue::new(queue),
+            one_shot: FxHashSet::default(),
+            fired: 0,
+        }
+    }
+
+    pub(crate) fn fired(&self) -> usize {
+        self.fired
+    }
+
+    /// Returns `true` if a pending timer is not periodic. Periodic timers, e.g.
+    /// heartbeats, never run out and so do not keep the simulation from being quiescent.
+    pub(crate) fn one_shot_pending(&self) -> bool {
+        !self.one_shot.is_empty()
+    }
+
+    /// Takes back a timer set by a step that was rolled back. Returns `false`
+    /// if it was already taken for firing, which then no longer counts as fired.
+    pub(crate) fn cancel(&mut self, id: TimerId) -> bool {
+        let pending = self.working_timers.extract(
+            |timer| matches!(timer.step, Step::TimerStep { id: timer, .. } if timer == id),
+        );
+        if pending.is_empty() {
+            self.fired -= 1;
+            return false;
+        }
+        self.one_shot.remove(&id);
+        true
+    }
+
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn save(&self) -> SavedTimers {
+        let pending = self
+            .working_timers
+            .ordered()
+            .into_iter()
+            .map(|timer| match timer.step {
+                Step::TimerStep { rank, id } => (
+                    timer.invocation_time,
+                    rank,
+                    id,
+                    !self.one_shot.contains(&id),
+                ),
+                _ => unreachable!("TimerActor only accepts TimerSteps"),
+            })
+            .collect();
+        SavedTimers {
+            pending,
+            fired: self.fired,
+        }
+    }
+
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn load(&mut self, saved: SavedTimers) {
+        self.working_timers.retain_mut(|_| false);
+        self.one_shot.clear();
+        for (invocation_time, rank, id, periodic) in saved.pending {
+            if !periodic {
+                self.one_shot.insert(id);
+            }
+            self.working_timers.push(TimedStep {
+                invocation_time,
+                step: Step::TimerStep { rank, id },
+            });
+        }
+        self.fired = saved.fired;
+    }
+
+    /// Drops every pending timer of the process, e.g. when it restarts.
+    pub(crate) fn discard_of(&mut self, rank: Rank) {
+        let one_shot = &mut self.one_shot;
+        self.working_timers.retain_mut(|timer| match timer.step {
+            Step::TimerStep { rank: r, id } if r == rank => {
+                one_shot.remove(&id);
+                false
+            }
+            _ => true,
+        });
+    }
 }
 
 impl SimulationActor for TimerActor {
     fn peek_next_step(&self) -> Option<Jiffies> {
-        self.working_timers
-            .peek()
-            .map(|entry| entry.0.invocation_time)
+        self.working_timers.peek_time()
     }
 
     fn next_step(&mut self) -> Step {
-        self.working_timers
-            .pop()
-            .expect("Should not be empty")
-            .0
-            .step
+        self.fired += 1;
+        let step = self.working_timers.pop().expect("Should not be empty").step;
+        if let Step::TimerStep { id, .. } = step {
+            self.one_shot.remove(&id);
+        }
+        step
     }
 
     fn submit(&mut self, event: Event) {
@@ -36,10 +126,16 @@ impl SimulationActor for TimerActor {
                 rank,
                 id,
                 fire_after,
-            } => self.working_timers.push(Reverse(TimedStep {
-                invocation_time: now() + fire_after,
-                step: Step::TimerStep { rank, id },
-            })),
+                periodic,
+            } => {
+                if !periodic {
+                    self.one_shot.insert(id);
+                }
+                self.working_timers.push(TimedStep {
+                    invocation_time: now() + fire_after,
+                    step: Step::TimerStep { rank, id },
+                })
+            }
             _ => unreachable!(),
         }
     }
@@ -0,0 +1,387 @@
+use std::{
+    borrow::Cow,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use crate::{
+    FactoryCtx, Jiffies, Message, MessagePtr, ProcessHandle, Rank, TimerId,
+    async_process::{Runtime, Task},
+    global, global_unique_id,
+};
+
+/// Handle through which an [`crate::AsyncProcess`] interacts with the simulation.
+///
+/// Cheap to clone, e.g. into tasks started with [`AsyncCtx::spawn`].
+#[derive(Clone)]
+pub struct AsyncCtx {
+    runtime: Arc<Mutex<Runtime>>,
+}
+
+impl AsyncCtx {
+    pub(crate) fn new(runtime: Arc<Mutex<Runtime>>) -> Self {
+        Self { runtime }
+    }
+
+    /// Rank of the process.
+    pub fn rank(&self) -> Rank {
+        global::rank()
+    }
+
+    /// Current simulation time.
+    pub fn now(&self) -> Jiffies {
+        global::now()
+    }
+
+    /// Sends a message to the process with the given rank.
+    pub fn send_to(&self, rank: Rank, message: impl Message + 'static) {
+        global::send_to(rank, message)
+    }
+
+    /// Sends one message to every process in `ranks`.
+    pub fn multicast(&self, ranks: &[Rank], message: impl Message + 'static) {
+        global::multicast(ranks, message)
+    }
+
+    /// Sends a message to all processes within the named pool.
+    pub fn broadcast_within_pool(&self, pool: &'static str, message: impl Message + 'static) {
+        global::broadcast_within_pool(pool, message)
+    }
+
+    /// Sends a message to every process.
+    pub fn broadcast(&self, message: impl Message + 'static) {
+        global::broadcast(message)
+    }
+
+    /// Sends a message to a randomly chosen process.
+    pub fn send_random(&self, message: impl Message + 'static) {
+        global::send_random(message)
+    }
+
+    /// Sends a message to a randomly chosen process from the named pool.
+    pub fn send_random_from_pool(&self, pool: &'static str, message: impl Message + 'static) {
+        global::send_random_from_pool(pool, message)
+    }
+
+    /// Starts a default-constructed process of type `P` in the named pool, see [`crate::spawn`].
+    /// Unlike [`AsyncCtx::spawn`], which starts a task of this process.
+    pub fn spawn_process<P: ProcessHandle + Default + Send + 'static>(&self, pool: &str) -> Rank {
+        global::spawn::<P>(pool)
+    }
+
+    /// Starts a process built by `factory` in the named pool, see [`crate::spawn_with`].
+    pub fn spawn_process_with<P: ProcessHandle + Send + 'static>(
+        &self,
+        pool: &str,
+        factory: impl Fn(Rank, &FactoryCtx) -> P + Send + Sync + 'static,
+    ) -> Rank {
+        global::spawn_with(pool, factory)
+    }
+
+    /// Removes the process from the simulation, see [`crate::exit`].
+    /// Its tasks stop once the current step finishes.
+    pub fn exit(&self) {
+        global::exit()
+    }
+
+    /// Creates an empty named pool, see [`crate::create_pool`].
+    pub fn create_pool(&self, pool: &str) {
+        global::create_pool(pool)
+    }
+
+    /// Adds the process to the named pool, see [`crate::join_pool`].
+    pub fn join_pool(&self, pool: &str, rank: Rank) {
+        global::join_pool(pool, rank)
+    }
+
+    /// Removes the process from the named pool, see [`crate::leave_pool`].
+    pub fn leave_pool(&self, pool: &str, rank: Rank) {
+        global::leave_pool(pool, rank)
+    }
+
+    /// Completes after `duration` of simulated time.
+    pub fn sleep(&self, duration: Jiffies) -> Sleep {
+        Sleep {
+            runtime: self.runtime.clone(),
+            duration,
+            timer: None,
+        }
+    }
+
+    /// Completes with the first unconsumed message of type `T` and its sender.
+    ///
+    /// Messages nobody waits for are kept until a matching `recv` consumes them,
+    /// and are scanned again whenever a pending `recv` is polled.
+    pub fn recv<T: Message>(&self) -> Recv<T> {
+        self.recv_where(|_, _| true)
+    }
+
+    /// Like [`AsyncCtx::recv`], but only for messages accepted by `filter`.
+    pub fn recv_where<T: Message>(
+        &self,
+        filter: impl Fn(Rank, &T) -> bool + Send + 'static,
+    ) -> Recv<T> {
+        Recv {
+            runtime: self.runtime.clone(),
+            filter: Box::new(filter),
+            message: PhantomData,
+        }
+    }
+
+    /// Runs `future` for at most `duration` of simulated time.
+    /// Returns `None` if it did not complete in time.
+    pub fn timeout<F: Future>(&self, duration: Jiffies, future: F) -> Timeout<F> {
+        Timeout {
+            future: Box::pin(future),
+            deadline: self.sleep(duration),
+        }
+    }
+
+    /// Sends `request` to `to` and waits up to `timeout` for its response of type `R`.
+    ///
+    /// The process `to` receives it with [`AsyncCtx::recv_request`] and answers
+    /// with [`AsyncCtx::reply`]. Every call is told apart, so a reply arriving
+    /// after its call timed out is dropped instead of answering a later call.
+    pub async fn rpc<R: Message>(
+        &self,
+        to: Rank,
+        request: impl Message + 'static,
+        timeout: Jiffies,
+    ) -> Option<Arc<R>> {
+        let call = global_unique_id();
+        self.runtime.lock().unwrap().calls.insert(call, None);
+        self.send_to(
+            to,
+            RpcRequest {
+                call,
+                message: MessagePtr(Arc::new(request)),
+            },
+        );
+        let reply = Reply {
+            runtime: self.runtime.clone(),
+            call,
+            response: PhantomData,
+        };
+        self.timeout(timeout, reply).await
+    }
+
+    /// Completes with the first unanswered request of type `T` sent with [`AsyncCtx::rpc`].
+    pub fn recv_request<T: Message>(&self) -> RecvRequest<T> {
+        RecvRequest {
+            runtime: self.runtime.clone(),
+            message: PhantomData,
+        }
+    }
+
+    /// Answers a request received with [`AsyncCtx::recv_request`].
+    pub fn reply<T>(&self, request: &Request<T>, response: impl Message + 'static) {
+        self.send_to(
+            request.from,
+            RpcReply {
+                call: request.call,
+                message: MessagePtr(Arc::new(response)),
+            },
+        );
+    }
+
+    /// Starts another task of this process. It first runs within the current step.
+    pub fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let task: Task = Box::pin(task);
+        self.runtime.lock().unwrap().spawned.push(task);
+    }
+}
+
+/// Future returned by [`AsyncCtx::sleep`].
+pub struct Sleep {
+    runtime: Arc<Mutex<Runtime>>,
+    duration: Jiffies,
+    timer: Option<TimerId>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        let Some(timer) = self.timer else {
+            let timer = global::schedule_timer_after(self.duration);
+            self.runtime.lock().unwrap().awaited.insert(timer);
+            self.timer = Some(timer);
+            return Poll::Pending;
+        };
+        {
+            let mut runtime = self.runtime.lock().unwrap();
+            if !runtime.fired.remove(&timer) {
+                return Poll::Pending;
+            }
+            runtime.awaited.remove(&timer);
+            runtime.progress += 1;
+        }
+        self.timer = None;
+        Poll::Ready(())
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        // Forget timers of abandoned sleeps (e.g. timeouts that did not expire)
+        if let Some(timer) = self.timer {
+            let mut runtime = self.runtime.lock().unwrap();
+            runtime.awaited.remove(&timer);
+            runtime.fired.remove(&timer);
+        }
+    }
+}
+
+type Filter<T> = Box<dyn Fn(Rank, &T) -> bool + Send>;
+
+/// Future returned by [`AsyncCtx::recv`] and [`AsyncCtx::recv_where`].
+pub struct Recv<T> {
+    runtime: Arc<Mutex<Runtime>>,
+    filter: Filter<T>,
+    message: PhantomData<fn() -> T>,
+}
+
+impl<T: Message> Future for Recv<T> {
+    type Output = (Rank, Arc<T>);
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut runtime = self.runtime.lock().unwrap();
+        let position = runtime.inbox.iter().position(|(from, message)| {
+            message
+                .try_as_type::<T>()
+                .is_some_and(|message| (self.filter)(*from, message))
+        });
+        let Some(position) = position else {
+            return Poll::Pending;
+        };
+        let (from, message) = runtime.inbox.remove(position).expect("Found above");
+        runtime.progress += 1;
+        Poll::Ready((from, message.downcast_arc::<T>().expect("Checked above")))
+    }
+}
+
+/// Request sent with [`AsyncCtx::rpc`], received with [`AsyncCtx::recv_request`].
+pub struct Request<T> {
+    /// Process that called.
+    pub from: Rank,
+    pub message: Arc<T>,
+    call: usize,
+}
+
+/// Future returned by [`AsyncCtx::recv_request`].
+pub struct RecvRequest<T> {
+    runtime: Arc<Mutex<Runtime>>,
+    message: PhantomData<fn() -> T>,
+}
+
+impl<T: Message> Future for RecvRequest<T> {
+    type Output = Request<T>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut runtime = self.runtime.lock().unwrap();
+        let position = runtime
+            .requests
+            .iter()
+            .position(|(_, _, message)| message.is::<T>());
+        let Some(position) = position else {
+            return Poll::Pending;
+        };
+        let (from, call, message) = runtime.requests.remove(position).expect("Found above");
+        runtime.progress += 1;
+        Poll::Ready(Request {
+            from,
+            message: message.downcast_arc::<T>().expect("Checked above"),
+            call,
+        })
+    }
+}
+
+/// Future of the response to an [`AsyncCtx::rpc`] call.
+pub struct Reply<R> {
+    runtime: Arc<Mutex<Runtime>>,
+    call: usize,
+    response: PhantomData<fn() -> R>,
+}
+
+impl<R: Message> Future for Reply<R> {
+    type Output = Arc<R>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut runtime = self.runtime.lock().unwrap();
+        let Some(Some(response)) = runtime.calls.get(&self.call) else {
+            return Poll::Pending;
+        };
+        let response = response.downcast_arc::<R>().unwrap_or_else(|| {
+            panic!(
+                "Expected reply {}, got {}",
+                std::any::type_name::<R>(),
+                response.0.label()
+            )
+        });
+        runtime.calls.remove(&self.call);
+        runtime.progress += 1;
+        Poll::Ready(response)
+    }
+}
+
+impl<R> Drop for Reply<R> {
+    fn drop(&mut self) {
+        // Replies to abandoned calls are dropped on arrival
+        self.runtime.lock().unwrap().calls.remove(&self.call);
+    }
+}
+
+/// Envelope of a request sent with [`AsyncCtx::rpc`].
+pub(crate) struct RpcRequest {
+    pub(crate) call: usize,
+    pub(crate) message: MessagePtr,
+}
+
+/// Envelope of a response sent with [`AsyncCtx::reply`].
+pub(crate) struct RpcReply {
+    pub(crate) call: usize,
+    pub(crate) message: MessagePtr,
+}
+
+impl Message for RpcRequest {
+    fn virtual_size(&self) -> usize {
+        self.message.0.virtual_size()
+    }
+
+    fn label(&self) -> Cow<'_, str> {
+        self.message.0.label()
+    }
+}
+
+impl Message for RpcReply {
+    fn virtual_size(&self) -> usize {
+        self.message.0.virtual_size()
+    }
+
+    fn label(&self) -> Cow<'_, str> {
+        self.message.0.label()
+    }
+}
+
+/// Future returned by [`AsyncCtx::timeout`].
+pub struct Timeout<F: Future> {
+    future: Pin<Box<F>>,
+    deadline: Sleep,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        match Pin::new(&mut self.deadline).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+mod ctx;
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Waker},
+};
+
+pub use ctx::{AsyncCtx, Recv, RecvRequest, Reply, Request, Sleep, Timeout};
+
+use crate::{MessagePtr, ProcessHandle, Rank, TimerId};
+use ctx::{RpcReply, RpcRequest};
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Process written as an async body instead of callbacks.
+///
+/// The body starts with the simulation and can wait for simulated time
+/// ([`AsyncCtx::sleep`]), for messages ([`AsyncCtx::recv`]) or for replies
+/// ([`AsyncCtx::rpc`]). Add it to a simulation wrapped in [`Async`]:
+///
+/// ```ignore
+/// SimulationBuilder::default().add_pool::<Async<MyProcess>>("Pool", 3)
+/// ```
+///
+/// The process value is moved into the body, so its state is not visible
+/// through a [`crate::View`]; publish what invariants need via
+/// [`crate::global::metrics`] or [`crate::global::kv`].
+///
+/// Messages are kept until a [`AsyncCtx::recv`] consumes them and every poll of
+/// a pending `recv` scans them, so a process should consume what it is sent.
+///
+/// The state of the futures can not be copied or saved: async processes can not
+/// run with the optimistic runner, be forked or be checkpointed.
+pub trait AsyncProcess: Default + Send + 'static {
+    /// Body of the process, started on [`ProcessHandle::on_start`].
+    fn run(self, ctx: AsyncCtx) -> impl Future<Output = ()> + Send + 'static;
+}
+
+/// State shared between the executor and the futures of one process.
+#[derive(Default)]
+pub(crate) struct Runtime {
+    inbox: VecDeque<(Rank, MessagePtr)>,
+    // Requests sent with `AsyncCtx::rpc`, by sender and call.
+    requests: VecDeque<(Rank, usize, MessagePtr)>,
+    // Calls waiting for their reply, and the reply once it arrived.
+    calls: HashMap<usize, Option<MessagePtr>>,
+    // Timers some future waits for, and those of them that already fired.
+    awaited: HashSet<TimerId>,
+    fired: HashSet<TimerId>,
+    spawned: Vec<Task>,
+    // Bumped whenever a future consumes something, so the executor knows
+    // another pass over the tasks may make further progress.
+    progress: usize,
+}
+
+/// Runs an [`AsyncProcess`] as a [`ProcessHandle`].
+///
+/// Every step (start, message, timer) polls the tasks of the process in
+/// spawn order until none of them can make progress, so execution stays
+/// deterministic under both runners.
+pub struct Async<P> {
+    process: Option<P>,
+    runtime: Arc<Mutex<Runtime>>,
+    tasks: Vec<Task>,
+}
+
+impl<P: AsyncProcess> Default for Async<P> {
+    fn default() -> Self {
+        Self {
+            process: Some(P::default()),
+            runtime: Arc::default(),
+            tasks: Vec::new(),
+        }
+    }
+}
+
+impl<P> Async<P> {
+    /// Whether every task of the process has finished.
+    pub fn finished(&self) -> bool {
+        self.process.is_none() && self.tasks.is_empty()
+    }
+
+    fn poll_tasks(&mut self) {
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            let before = self.runtime.lock().unwrap().progress;
+            self.tasks
+                .retain_mut(|task| task.as_mut().poll(&mut cx).is_pending());
+            let mut runtime = self.runtime.lock().unwrap();
+            let spawned = mem::take(&mut runtime.spawned);
+            if spawned.is_empty() && runtime.progress == before {
+                return;
+            }
+            self.tasks.extend(spawned);
+        }
+    }
+}
+
+impl<P: AsyncProcess> ProcessHandle for Async<P> {
+    fn on_start(&mut self) {
+        let process = self.process.take().expect("Started twice");
+        let ctx = AsyncCtx::new(self.runtime.clone());
+        self.tasks.push(Box::pin(process.run(ctx)));
+        self.poll_tasks();
+    }
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        {
+            let mut runtime = self.runtime.lock().unwrap();
+            if let Some(request) = message.try_as_type::<RpcRequest>() {
+                let request = (from, request.call, request.message.clone());
+                runtime.requests.push_back(request);
+            } else if let Some(reply) = message.try_as_type::<RpcReply>() {
+                // Replies to calls that timed out are dropped
+                let Some(slot) = runtime.calls.get_mut(&reply.call) else {
+                    return;
+                };
+                *slot = Some(reply.message.clone());
+            } else {
+                runtime.inbox.push_back((from, message));
+            }
+        }
+        self.poll_tasks();
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        {
+            let mut runtime = self.runtime.lock().unwrap();
+            if !runtime.awaited.contains(&id) {
+                return;
+            }
+            runtime.fired.insert(id);
+        }
+        self.poll_tasks();
+    }
+}
@@ -0,0 +1,223 @@
+//! Checkpoints of a simulation, to resume it later from the same state.
+//!
+//! [`crate::SimulationRunner::checkpoint`] writes the clock, the unique id
+//! counter, pending timers, messages in flight with the network accounting,
+//! random generator states, the key-value store and every process to bytes.
+//! [`crate::SimulationRunner::restore`] loads them into a simulation built
+//! the same way, possibly with different code or fault schedules.
+//!
+//! Processes save themselves with [`crate::ProcessHandle::save`] and
+//! [`crate::ProcessHandle::load`], usually with [`encode`] and [`decode`].
+//! Messages in flight and key-value store entries are saved by type, which
+//! must be registered under a name with [`crate::SimulationBuilder::checkpoint_message`]
+//! and [`crate::SimulationBuilder::checkpoint_value`]. The name identifies the type
+//! in the checkpoint, so it has to stay the same when the type is moved or renamed.
+//! Common value types (integers, floats, `bool` and `String`) are registered already,
+//! under their type names.
+
+use std::{
+    any::{Any, TypeId, type_name},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use crate::global::kv::Value;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{Message, MessagePtr, Rank, TimerId, TrafficStats, jiffy::Jiffies, random::Randomizer};
+
+/// Serializes a value with the format of checkpoints.
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    bincode::serialize(value).expect("Could not encode checkpoint state")
+}
+
+/// Deserializes a value written by [`encode`].
+/// Panics if the bytes do not hold a `T`.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> T {
+    bincode::deserialize(bytes)
+        .unwrap_or_else(|e| panic!("Could not decode {} from checkpoint: {e}", type_name::<T>()))
+}
+
+type Encoder = fn(&dyn Any) -> Vec<u8>;
+type Decoder<T> = fn(&[u8]) -> T;
+
+fn encode_as<T: Serialize + 'static>(value: &dyn Any) -> Vec<u8> {
+    encode(value.downcast_ref::<T>().expect("Encoder of another type"))
+}
+
+fn decode_message<M: Message + DeserializeOwned>(bytes: &[u8]) -> MessagePtr {
+    MessagePtr(Arc::new(decode::<M>(bytes)))
+}
+
+fn decode_value<T: DeserializeOwned + Send + Sync + 'static>(bytes: &[u8]) -> Box<dyn Value> {
+    Box::new(decode::<T>(bytes))
+}
+
+/// Types of messages and key-value store entries that checkpoints can hold.
+#[derive(Clone)]
+pub(crate) struct Codecs {
+    encoders: HashMap<TypeId, (&'static str, Encoder)>,
+    messages: HashMap<&'static str, Decoder<MessagePtr>>,
+    values: HashMap<&'static str, Decoder<Box<dyn Value>>>,
+}
+
+impl Default for Codecs {
+    fn default() -> Self {
+        let mut codecs = Self {
+            encoders: HashMap::new(),
+            messages: HashMap::new(),
+            values: HashMap::new(),
+        };
+        codecs.add_value::<usize>("usize");
+        codecs.add_value::<u64>("u64");
+        codecs.add_value::<u32>("u32");
+        codecs.add_value::<i64>("i64");
+        codecs.add_value::<i32>("i32");
+        codecs.add_value::<f64>("f64");
+        codecs.add_value::<bool>("bool");
+        codecs.add_value::<String>("String");
+        codecs
+    }
+}
+
+impl Codecs {
+    pub(crate) fn add_message<M: Message + Serialize + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+    ) {
+        self.add_encoder::<M>(name);
+        self.messages.insert(name, decode_message::<M>);
+    }
+
+    pub(crate) fn add_value<T>(&mut self, name: &'static str)
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        self.add_encoder::<T>(name);
+        self.values.insert(name, decode_value::<T>);
+    }
+
+    fn add_encoder<T: Serialize + 'static>(&mut self, name: &'static str) {
+        let taken = self
+            .encoders
+            .iter()
+            .any(|(id, (other, _))| *other == name && *id != TypeId::of::<T>());
+        assert!(
+            !taken,
+            "Can not register {} for checkpoints as {name}: the name is taken by another type",
+            type_name::<T>()
+        );
+        self.encoders
+            .insert(TypeId::of::<T>(), (name, encode_as::<T>));
+    }
+
+    fn encode(&self, value: &dyn Any, what: &str) -> Saved {
+        let (type_name, encoder) = self
+            .encoders
+            .get(&value.type_id())
+            .unwrap_or_else(|| panic!("Can not checkpoint {what}: register its type"));
+        Saved {
+            type_name: type_name.to_string(),
+            bytes: encoder(value),
+        }
+    }
+
+    pub(crate) fn save_message(&self, message: &MessagePtr) -> Saved {
+        let what = format!("message {message:?}");
+        self.encode(&*message.0 as &dyn Any, &what)
+    }
+
+    pub(crate) fn load_message(&self, saved: &Saved) -> MessagePtr {
+        let decode = self
+            .messages
+            .get(saved.type_name.as_str())
+            .unwrap_or_else(|| panic!("Message type {} is not registered", saved.type_name));
+        decode(&saved.bytes)
+    }
+
+    pub(crate) fn save_value(&self, key: &str, value: &dyn Any) -> Saved {
+        self.encode(value, &format!("key-value store entry {key}"))
+    }
+
+    pub(crate) fn load_value(&self, saved: &Saved) -> Box<dyn Value> {
+        let decode = self
+            .values
+            .get(saved.type_name.as_str())
+            .unwrap_or_else(|| panic!("Value type {} is not registered", saved.type_name));
+        decode(&saved.bytes)
+    }
+}
+
+/// Value of a registered type.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Saved {
+    // Name the type was registered with
+    type_name: String,
+    bytes: Vec<u8>,
+}
+
+/// Message on its way to its remaining targets.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedFanOut {
+    pub(crate) source: Rank,
+    pub(crate) message: Saved,
+    pub(crate) sent_at: Jiffies,
+    pub(crate) deliveries: Vec<(Jiffies, Rank)>,
+}
+
+/// Messages waiting for their latency, in the order they pop.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedLatency {
+    // Generators of the latencies of every source
+    pub(crate) generators: Vec<Randomizer>,
+    pub(crate) in_flight: Vec<SavedFanOut>,
+}
+
+/// Message waiting for NIC bandwidth.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedDelivery {
+    pub(crate) time: Jiffies,
+    pub(crate) source: Rank,
+    pub(crate) target: Rank,
+    pub(crate) message: Saved,
+    pub(crate) sent_at: Jiffies,
+}
+
+/// Messages waiting for bandwidth, in the order they pop, and the bytes
+/// that passed every NIC.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedBandwidth {
+    pub(crate) latency: SavedLatency,
+    pub(crate) buffered: Vec<SavedDelivery>,
+    pub(crate) passed: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedNetwork {
+    pub(crate) bandwidth: SavedBandwidth,
+    pub(crate) traffic: Vec<TrafficStats>,
+}
+
+/// Pending timers, in the order they fire.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedTimers {
+    // Time, process, timer and whether it is periodic
+    pub(crate) pending: Vec<(Jiffies, Rank, TimerId, bool)>,
+    pub(crate) fired: usize,
+}
+
+/// Everything a checkpoint holds.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Image {
+    pub(crate) time: Jiffies,
+    pub(crate) next_id: usize,
+    // Generators of the random choices of every process
+    pub(crate) generators: Vec<Randomizer>,
+    pub(crate) started: bool,
+    pub(crate) last_delivery: Jiffies,
+    pub(crate) timers: SavedTimers,
+    pub(crate) network: SavedNetwork,
+    pub(crate) kv: Vec<(String, Saved)>,
+    pub(crate) processes: Vec<Vec<u8>>,
+}
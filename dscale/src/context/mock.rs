@@ -0,0 +1,186 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    Jiffies, Message, MessagePtr, Rank, TimerId,
+    context::{Backend, Ctx},
+    random::{Randomizer, Seed},
+    topology::GLOBAL_POOL,
+};
+
+/// Where a message was sent by a process under test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    Rank(Rank),
+    Ranks(Vec<Rank>),
+    Pool(String),
+}
+
+/// Message sent through a [`MockCtx`].
+#[derive(Debug, Clone)]
+pub struct Sent {
+    pub to: Recipient,
+    pub message: MessagePtr,
+}
+
+/// Process or pool change requested through a [`MockCtx`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MembershipChange {
+    /// A process was spawned into the pool and got the rank.
+    Spawned {
+        pool: String,
+        rank: Rank,
+    },
+    /// The process under test exited.
+    Exited,
+    /// An empty pool was created.
+    Created(String),
+    Joined {
+        pool: String,
+        rank: Rank,
+    },
+    Left {
+        pool: String,
+        rank: Rank,
+    },
+}
+
+/// Stand-in for the simulation when unit testing a [`crate::ContextProcess`].
+///
+/// Records sent messages, scheduled timers and membership changes instead of
+/// applying them, so pools stay as configured.
+///
+/// ```ignore
+/// let mut mock = MockCtx::new(0, 3);
+/// process.on_start(&mut mock.ctx());
+/// assert_eq!(mock.sent()[0].to, Recipient::Rank(1));
+/// ```
+pub struct MockCtx {
+    pub(super) rank: Rank,
+    pub(super) now: Jiffies,
+    pub(super) process_number: usize,
+    pub(super) seed: Seed,
+    pools: HashMap<String, Arc<[Rank]>>,
+    random: Randomizer,
+    sent: Vec<Sent>,
+    timers: Vec<(TimerId, Jiffies)>,
+    periodic_timers: Vec<(TimerId, Jiffies)>,
+    next_timer: TimerId,
+    changes: Vec<MembershipChange>,
+    next_rank: Rank,
+}
+
+impl MockCtx {
+    /// Context of process `rank` in a simulation of `process_number` processes,
+    /// all of them in [`GLOBAL_POOL`].
+    pub fn new(rank: Rank, process_number: usize) -> Self {
+        Self {
+            rank,
+            now: Jiffies(0),
+            process_number,
+            seed: Seed::default(),
+            pools: HashMap::from([(GLOBAL_POOL.to_string(), (0..process_number).collect())]),
+            random: Randomizer::default(),
+            sent: Vec::new(),
+            timers: Vec::new(),
+            periodic_timers: Vec::new(),
+            next_timer: 0,
+            changes: Vec::new(),
+            next_rank: process_number,
+        }
+    }
+
+    /// Adds a named pool.
+    pub fn with_pool(mut self, name: &str, ranks: impl IntoIterator<Item = Rank>) -> Self {
+        self.pools
+            .insert(name.to_string(), ranks.into_iter().collect());
+        self
+    }
+
+    /// Sets the seed returned by [`Ctx::seed`] and used for random choices.
+    pub fn with_seed(mut self, seed: Seed) -> Self {
+        self.seed = seed;
+        self.random = Randomizer::new(seed);
+        self
+    }
+
+    /// Moves the mocked simulation time.
+    pub fn set_now(&mut self, now: Jiffies) {
+        self.now = now;
+    }
+
+    /// Context to pass into the process callbacks.
+    pub fn ctx(&mut self) -> Ctx<'_> {
+        Ctx {
+            backend: Backend::Mock(self),
+        }
+    }
+
+    /// Messages sent so far.
+    pub fn sent(&self) -> &[Sent] {
+        &self.sent
+    }
+
+    /// Removes and returns the messages sent so far.
+    pub fn take_sent(&mut self) -> Vec<Sent> {
+        std::mem::take(&mut self.sent)
+    }
+
+    /// Timers scheduled so far with their delays.
+    pub fn timers(&self) -> &[(TimerId, Jiffies)] {
+        &self.timers
+    }
+
+    /// Periodic timers scheduled so far with their delays.
+    pub fn periodic_timers(&self) -> &[(TimerId, Jiffies)] {
+        &self.periodic_timers
+    }
+
+    /// Processes spawned, exits and pool changes so far, in the order requested.
+    pub fn changes(&self) -> &[MembershipChange] {
+        &self.changes
+    }
+
+    pub(super) fn list_pool(&self, pool: &str) -> Arc<[Rank]> {
+        self.pools.get(pool).expect("Invalid pool name").clone()
+    }
+
+    pub(super) fn choose_from_pool(&mut self, pool: &str) -> Rank {
+        let pool = self.pools.get(pool).expect("Invalid pool name");
+        self.random.choose_from_slice(pool)
+    }
+
+    pub(super) fn record(&mut self, to: Recipient, message: impl Message + 'static) {
+        self.sent.push(Sent {
+            to,
+            message: MessagePtr(Arc::new(message)),
+        });
+    }
+
+    pub(super) fn schedule_timer_after(&mut self, after: Jiffies) -> TimerId {
+        let id = self.next_timer;
+        self.next_timer += 1;
+        self.timers.push((id, after));
+        id
+    }
+
+    pub(super) fn schedule_periodic_timer_after(&mut self, after: Jiffies) -> TimerId {
+        let id = self.next_timer;
+        self.next_timer += 1;
+        self.periodic_timers.push((id, after));
+        id
+    }
+
+    pub(super) fn spawn(&mut self, pool: &str) -> Rank {
+        let rank = self.next_rank;
+        self.next_rank += 1;
+        self.change(MembershipChange::Spawned {
+            pool: pool.to_string(),
+            rank,
+        });
+        rank
+    }
+
+    pub(super) fn change(&mut self, change: MembershipChange) {
+        self.changes.push(change);
+    }
+}
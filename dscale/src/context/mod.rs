@@ -0,0 +1,292 @@
+mod mock;
+
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+pub use mock::{MembershipChange, MockCtx, Recipient, Sent};
+
+use crate::{
+    FactoryCtx, Jiffies, Message, MessagePtr, ProcessHandle, Rank, TimerId,
+    global::{self, configuration},
+    random::Seed,
+    topology::GLOBAL_POOL,
+};
+
+enum Backend<'a> {
+    Live,
+    Mock(&'a mut MockCtx),
+}
+
+/// Handle through which a [`ContextProcess`] interacts with the simulation.
+///
+/// Offers the same operations as the free functions ([`crate::send_to`],
+/// [`crate::broadcast`], [`crate::schedule_timer_after`], ...), but only exists
+/// while a step of the process executes. Tests can create one with [`MockCtx`].
+pub struct Ctx<'a> {
+    backend: Backend<'a>,
+}
+
+impl Ctx<'_> {
+    fn live() -> Self {
+        Self {
+            backend: Backend::Live,
+        }
+    }
+
+    /// Rank of the executing process.
+    pub fn rank(&self) -> Rank {
+        match &self.backend {
+            Backend::Live => global::rank(),
+            Backend::Mock(mock) => mock.rank,
+        }
+    }
+
+    /// Current simulation time.
+    pub fn now(&self) -> Jiffies {
+        match &self.backend {
+            Backend::Live => global::now(),
+            Backend::Mock(mock) => mock.now,
+        }
+    }
+
+    /// Total number of processes in the simulation.
+    pub fn process_number(&self) -> usize {
+        match &self.backend {
+            Backend::Live => configuration::process_number(),
+            Backend::Mock(mock) => mock.process_number,
+        }
+    }
+
+    /// Deterministic seed of the executing process.
+    pub fn seed(&self) -> Seed {
+        match &self.backend {
+            Backend::Live => configuration::seed(),
+            Backend::Mock(mock) => mock.seed,
+        }
+    }
+
+    /// Ranks of all processes currently in the named pool.
+    pub fn list_pool(&self, pool: &str) -> Arc<[Rank]> {
+        match &self.backend {
+            Backend::Live => global::list_pool(pool),
+            Backend::Mock(mock) => mock.list_pool(pool),
+        }
+    }
+
+    /// Picks a random process rank from the named pool.
+    pub fn choose_from_pool(&mut self, pool: &str) -> Rank {
+        match &mut self.backend {
+            Backend::Live => global::choose_from_pool(pool),
+            Backend::Mock(mock) => mock.choose_from_pool(pool),
+        }
+    }
+
+    /// Sends a message to the process with the given rank.
+    pub fn send_to(&mut self, rank: Rank, message: impl Message + 'static) {
+        match &mut self.backend {
+            Backend::Live => global::send_to(rank, message),
+            Backend::Mock(mock) => mock.record(Recipient::Rank(rank), message),
+        }
+    }
+
+    /// Sends one message to every process in `ranks`.
+    pub fn multicast(&mut self, ranks: &[Rank], message: impl Message + 'static) {
+        match &mut self.backend {
+            Backend::Live => global::multicast(ranks, message),
+            Backend::Mock(mock) => mock.record(Recipient::Ranks(ranks.to_vec()), message),
+        }
+    }
+
+    /// Sends a message to all processes within the named pool.
+    pub fn broadcast_within_pool(&mut self, pool: &'static str, message: impl Message + 'static) {
+        match &mut self.backend {
+            Backend::Live => global::broadcast_within_pool(pool, message),
+            Backend::Mock(mock) => mock.record(Recipient::Pool(pool.to_string()), message),
+        }
+    }
+
+    /// Sends a message to every process.
+    pub fn broadcast(&mut self, message: impl Message + 'static) {
+        self.broadcast_within_pool(GLOBAL_POOL, message)
+    }
+
+    /// Sends a message to a randomly chosen process from the named pool.
+    pub fn send_random_from_pool(&mut self, pool: &'static str, message: impl Message + 'static) {
+        match &mut self.backend {
+            Backend::Live => global::send_random_from_pool(pool, message),
+            Backend::Mock(mock) => {
+                let rank = mock.choose_from_pool(pool);
+                mock.record(Recipient::Rank(rank), message)
+            }
+        }
+    }
+
+    /// Sends a message to a randomly chosen process.
+    pub fn send_random(&mut self, message: impl Message + 'static) {
+        self.send_random_from_pool(GLOBAL_POOL, message)
+    }
+
+    /// Schedules a timer for the executing process, firing after the given delay.
+    pub fn schedule_timer_after(&mut self, after: Jiffies) -> TimerId {
+        match &mut self.backend {
+            Backend::Live => global::schedule_timer_after(after),
+            Backend::Mock(mock) => mock.schedule_timer_after(after),
+        }
+    }
+
+    /// Schedules a timer of periodic background activity, see [`crate::schedule_periodic_timer_after`].
+    pub fn schedule_periodic_timer_after(&mut self, after: Jiffies) -> TimerId {
+        match &mut self.backend {
+            Backend::Live => global::schedule_periodic_timer_after(after),
+            Backend::Mock(mock) => mock.schedule_periodic_timer_after(after),
+        }
+    }
+
+    /// Starts a default-constructed process of type `P` in the named pool, see [`crate::spawn`].
+    pub fn spawn<P: ProcessHandle + Default + Send + 'static>(&mut self, pool: &str) -> Rank {
+        self.spawn_with(pool, |_, _| P::default())
+    }
+
+    /// Starts a process built by `factory` in the named pool, see [`crate::spawn_with`].
+    pub fn spawn_with<P: ProcessHandle + Send + 'static>(
+        &mut self,
+        pool: &str,
+        factory: impl Fn(Rank, &FactoryCtx) -> P + Send + Sync + 'static,
+    ) -> Rank {
+        match &mut self.backend {
+            Backend::Live => global::spawn_with(pool, factory),
+            Backend::Mock(mock) => mock.spawn(pool),
+        }
+    }
+
+    /// Removes the executing process from the simulation, see [`crate::exit`].
+    pub fn exit(&mut self) {
+        match &mut self.backend {
+            Backend::Live => global::exit(),
+            Backend::Mock(mock) => mock.change(MembershipChange::Exited),
+        }
+    }
+
+    /// Creates an empty named pool, see [`crate::create_pool`].
+    pub fn create_pool(&mut self, pool: &str) {
+        match &mut self.backend {
+            Backend::Live => global::create_pool(pool),
+            Backend::Mock(mock) => mock.change(MembershipChange::Created(pool.to_string())),
+        }
+    }
+
+    /// Adds the process to the named pool, see [`crate::join_pool`].
+    pub fn join_pool(&mut self, pool: &str, rank: Rank) {
+        match &mut self.backend {
+            Backend::Live => global::join_pool(pool, rank),
+            Backend::Mock(mock) => mock.change(MembershipChange::Joined {
+                pool: pool.to_string(),
+                rank,
+            }),
+        }
+    }
+
+    /// Removes the process from the named pool, see [`crate::leave_pool`].
+    pub fn leave_pool(&mut self, pool: &str, rank: Rank) {
+        match &mut self.backend {
+            Backend::Live => global::leave_pool(pool, rank),
+            Backend::Mock(mock) => mock.change(MembershipChange::Left {
+                pool: pool.to_string(),
+                rank,
+            }),
+        }
+    }
+}
+
+/// Process behavior receiving an explicit [`Ctx`] in every callback.
+///
+/// Alternative to [`ProcessHandle`] that does not rely on the thread-local
+/// free functions. Add it to a simulation wrapped in [`WithContext`]:
+///
+/// ```ignore
+/// SimulationBuilder::default().add_pool::<WithContext<MyProcess>>("Pool", 3)
+/// ```
+pub trait ContextProcess: 'static {
+    /// Called once when the simulation starts, before any messages are delivered.
+    fn on_start(&mut self, ctx: &mut Ctx);
+
+    /// Called when a message arrives from another process.
+    fn on_message(&mut self, ctx: &mut Ctx, from: Rank, message: MessagePtr);
+
+    /// Called when a previously scheduled timer fires.
+    fn on_timer(&mut self, ctx: &mut Ctx, id: TimerId);
+
+    /// Copy of the current state, see [`ProcessHandle::snapshot`]. `Clone`
+    /// processes implement it as `Some(self.clone())`.
+    fn snapshot(&self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// State of the process for a checkpoint, see [`ProcessHandle::save`].
+    #[cfg(feature = "checkpoint")]
+    fn save(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Takes the state written by `save` when a checkpoint is restored, see [`ProcessHandle::load`].
+    #[cfg(feature = "checkpoint")]
+    fn load(&mut self, state: &[u8]) {
+        let _ = state;
+        panic!("Process can not be restored: implement ContextProcess::load")
+    }
+}
+
+/// Runs a [`ContextProcess`] as a [`ProcessHandle`].
+///
+/// Dereferences to the wrapped process, e.g. for inspection through a [`crate::View`],
+/// and forwards its [`ContextProcess::snapshot`], `save` and `load`.
+#[derive(Default)]
+pub struct WithContext<P>(pub P);
+
+impl<P: ContextProcess + Send> ProcessHandle for WithContext<P> {
+    fn on_start(&mut self) {
+        self.0.on_start(&mut Ctx::live())
+    }
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        self.0.on_message(&mut Ctx::live(), from, message)
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        self.0.on_timer(&mut Ctx::live(), id)
+    }
+
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        let copy = self.0.snapshot()?;
+        Some(Box::new(WithContext(copy)))
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn save(&self) -> Option<Vec<u8>> {
+        self.0.save()
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn load(&mut self, state: &[u8]) {
+        self.0.load(state)
+    }
+}
+
+impl<P> Deref for WithContext<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P> DerefMut for WithContext<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.0
+    }
+}
@@ -1,7 +1,11 @@
+use std::sync::Arc;
+
 use crate::Rank;
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub(crate) enum Destination {
     BroadcastWithinPool(&'static str),
     Target(Rank),
+    // Shares one message between all the ranks, expanded by the network
+    Multicast(Arc<[Rank]>),
 }
@@ -1,6 +1,9 @@
-use crate::{Jiffies, MessagePtr, Rank, TimerId, destination::Destination};
+use crate::{
+    Jiffies, MessagePtr, Rank, TimerId, destination::Destination, factory::Factory, typed::Receives,
+};
 
 #[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum Event {
     NetworkEvent {
         source: Rank,
@@ -11,5 +14,27 @@ pub(crate) enum Event {
         rank: Rank,
         id: TimerId,
         fire_after: Jiffies,
+        // Background activity, see `schedule_periodic_timer_after`
+        periodic: bool,
+    },
+    Spawn {
+        rank: Rank,
+        pool: String,
+        factory: Factory,
+        receives: Receives,
+    },
+    Exit {
+        rank: Rank,
+    },
+    CreatePool {
+        pool: String,
+    },
+    JoinPool {
+        rank: Rank,
+        pool: String,
+    },
+    LeavePool {
+        rank: Rank,
+        pool: String,
     },
 }
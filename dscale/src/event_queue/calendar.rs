@@ -0,0 +1,240 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use crate::jiffy::Jiffies;
+
+use super::{Entry, Heap};
+
+/// Ring of one-jiffy buckets covering `[start, start + buckets)`, each one FIFO.
+/// Later entries wait in `overflow` and move to the ring as `start` advances.
+#[derive(Clone)]
+pub(super) struct CalendarQueue<T> {
+    buckets: Vec<VecDeque<Entry<T>>>,
+    // Bit per non-empty bucket, so empty ones are skipped a word at a time
+    occupied: Vec<u64>,
+    start: usize,
+    in_ring: usize,
+    // Time of the earliest entry in the ring, peeked after every step
+    earliest: Option<usize>,
+    overflow: Heap<T>,
+}
+
+impl<T> CalendarQueue<T> {
+    /// `buckets` is a power of two, at least 64.
+    pub(super) fn new(buckets: usize) -> Self {
+        debug_assert!(buckets.is_power_of_two() && buckets >= 64);
+        Self {
+            buckets: (0..buckets).map(|_| VecDeque::new()).collect(),
+            occupied: vec![0; buckets / 64],
+            start: 0,
+            in_ring: 0,
+            earliest: None,
+            overflow: BinaryHeap::new(),
+        }
+    }
+
+    pub(super) fn push(&mut self, entry: Entry<T>) {
+        let time = entry.time.0;
+        if time < self.start {
+            self.rewind(time);
+        }
+        if time < self.start + self.horizon() {
+            self.insert(entry);
+        } else {
+            self.overflow.push(Reverse(entry));
+        }
+    }
+
+    pub(super) fn pop(&mut self) -> Option<Entry<T>> {
+        let bucket = self.advance()?;
+        Some(self.take(bucket))
+    }
+
+    /// Earliest entry, left in place. If its time is changed, it must be
+    /// moved with [`CalendarQueue::reschedule_first`].
+    pub(super) fn first_mut(&mut self) -> Option<&mut Entry<T>> {
+        let bucket = self.advance()?;
+        self.buckets[bucket].front_mut()
+    }
+
+    /// Moves the earliest entry to the bucket of its new, later time.
+    pub(super) fn reschedule_first(&mut self) {
+        let earliest = self.earliest.expect("Ring should not be empty");
+        let entry = self.take(self.bucket_of(earliest));
+        self.push(entry);
+    }
+
+    pub(super) fn peek_time(&self) -> Option<Jiffies> {
+        match self.earliest {
+            Some(time) => Some(Jiffies(time)),
+            None => Some(self.overflow.peek()?.0.time),
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.in_ring + self.overflow.len()
+    }
+
+    /// Every entry, in no particular order.
+    #[cfg(feature = "checkpoint")]
+    pub(super) fn entries(&self) -> impl Iterator<Item = &Entry<T>> {
+        self.buckets
+            .iter()
+            .flatten()
+            .chain(self.overflow.iter().map(|entry| &entry.0))
+    }
+
+    /// Passes every entry through `f` in place, dropping those it returns `None` for.
+    /// Entries it moves to another time are pushed again.
+    pub(super) fn filter_map(&mut self, mut f: impl FnMut(Entry<T>) -> Option<Entry<T>>) {
+        let mut moved = Vec::new();
+        for bucket in 0..self.horizon() {
+            if self.occupied[bucket / 64] & (1 << (bucket % 64)) == 0 {
+                continue;
+            }
+            // Rotates the bucket once, so kept entries stay in order
+            for _ in 0..self.buckets[bucket].len() {
+                let entry = self.buckets[bucket].pop_front().expect("Counted");
+                let time = entry.time;
+                self.in_ring -= 1;
+                match f(entry) {
+                    Some(entry) if entry.time == time => {
+                        self.buckets[bucket].push_back(entry);
+                        self.in_ring += 1;
+                    }
+                    Some(entry) => moved.push(entry),
+                    None => {}
+                }
+            }
+            if self.buckets[bucket].is_empty() {
+                self.occupied[bucket / 64] &= !(1 << (bucket % 64));
+            }
+        }
+        self.earliest = self.first_occupied().map(|bucket| self.time_of(bucket));
+
+        let overflow: Vec<Reverse<Entry<T>>> = std::mem::take(&mut self.overflow)
+            .into_vec()
+            .into_iter()
+            .filter_map(|entry| {
+                let time = entry.0.time;
+                match f(entry.0) {
+                    Some(entry) if entry.time == time => Some(Reverse(entry)),
+                    Some(entry) => {
+                        moved.push(entry);
+                        None
+                    }
+                    None => None,
+                }
+            })
+            .collect();
+        self.overflow = overflow.into();
+        moved.into_iter().for_each(|entry| self.push(entry));
+    }
+
+    fn insert(&mut self, entry: Entry<T>) {
+        let time = entry.time.0;
+        let bucket = self.bucket_of(time);
+        self.earliest = Some(self.earliest.map_or(time, |earliest| earliest.min(time)));
+        let bucket_entries = &mut self.buckets[bucket];
+        match bucket_entries.back() {
+            // Entries pushed again, e.g. the rest of a fan-out, go before later ones
+            Some(last) if last.seq > entry.seq => {
+                let position = bucket_entries.partition_point(|queued| queued.seq < entry.seq);
+                bucket_entries.insert(position, entry);
+            }
+            _ => bucket_entries.push_back(entry),
+        }
+        self.occupied[bucket / 64] |= 1 << (bucket % 64);
+        self.in_ring += 1;
+    }
+
+    fn take(&mut self, bucket: usize) -> Entry<T> {
+        let entry = self.buckets[bucket]
+            .pop_front()
+            .expect("Bucket should not be empty");
+        self.in_ring -= 1;
+        if self.buckets[bucket].is_empty() {
+            self.occupied[bucket / 64] &= !(1 << (bucket % 64));
+            self.earliest = self.first_occupied().map(|bucket| self.time_of(bucket));
+        }
+        entry
+    }
+
+    /// Moves the horizon to the earliest entry. Returns its bucket, if any.
+    fn advance(&mut self) -> Option<usize> {
+        if self.in_ring == 0 {
+            self.start = self.overflow.peek()?.0.time.0;
+            self.refill();
+        }
+        let earliest = self.earliest.expect("Ring should not be empty");
+        if earliest > self.start {
+            self.start = earliest;
+            self.refill();
+        }
+        Some(self.bucket_of(earliest))
+    }
+
+    /// Moves entries that got within the horizon from the overflow to the ring.
+    fn refill(&mut self) {
+        while let Some(next) = self.overflow.peek()
+            && next.0.time.0 < self.start + self.horizon()
+        {
+            let entry = self.overflow.pop().expect("Just peeked").0;
+            self.insert(entry);
+        }
+    }
+
+    /// Moves the horizon back to `time`, for an entry scheduled before the earliest one.
+    fn rewind(&mut self, time: usize) {
+        let horizon = self.horizon();
+        // Buckets of [time + horizon, start + horizon) fall out of the horizon
+        for late in (time + horizon).max(self.start)..self.start + horizon {
+            let bucket = self.bucket_of(late);
+            while !self.buckets[bucket].is_empty() {
+                let entry = self.take(bucket);
+                self.overflow.push(Reverse(entry));
+            }
+        }
+        self.start = time;
+    }
+
+    fn horizon(&self) -> usize {
+        self.buckets.len()
+    }
+
+    fn bucket_of(&self, time: usize) -> usize {
+        time & (self.horizon() - 1)
+    }
+
+    fn time_of(&self, bucket: usize) -> usize {
+        self.start + self.bucket_of(bucket + self.horizon() - self.bucket_of(self.start))
+    }
+
+    fn first_occupied(&self) -> Option<usize> {
+        if self.in_ring == 0 {
+            return None;
+        }
+        let origin = self.bucket_of(self.start);
+        let (word, bit) = (origin / 64, origin % 64);
+        let from_origin = self.occupied[word] & (!0 << bit);
+        if from_origin != 0 {
+            return Some(word * 64 + from_origin.trailing_zeros() as usize);
+        }
+        // Wraps around to the bits before the origin last
+        let words = self.occupied.len();
+        for i in 1..=words {
+            let w = (word + i) % words;
+            let bits = if i == words {
+                self.occupied[w] & !(!0 << bit)
+            } else {
+                self.occupied[w]
+            };
+            if bits != 0 {
+                return Some(w * 64 + bits.trailing_zeros() as usize);
+            }
+        }
+        unreachable!("Ring should not be empty")
+    }
+}
@@ -0,0 +1,226 @@
+mod calendar;
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, binary_heap::PeekMut},
+};
+
+use crate::jiffy::Jiffies;
+
+use calendar::CalendarQueue;
+
+/// Data structure keeping pending timers and messages in flight ordered by time,
+/// selected with [`crate::SimulationBuilder::event_queue`].
+///
+/// Events due at the same jiffy are handled in the order they were scheduled
+/// with either of them, so the choice does not change the simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventQueue {
+    /// Binary heap, `O(log n)` per operation.
+    #[default]
+    BinaryHeap,
+    /// Calendar queue with a bucket per jiffy over a sliding horizon that covers
+    /// the largest configured latency. `O(1)` per operation for events due within
+    /// the horizon, later ones (e.g. long timers) wait in a binary heap until they get close.
+    Calendar,
+}
+
+/// [`EventQueue`] together with the horizon of a calendar queue.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EventQueueConfig {
+    kind: EventQueue,
+    buckets: usize,
+}
+
+impl EventQueueConfig {
+    pub(crate) fn new(kind: EventQueue, max_latency: Jiffies) -> Self {
+        // Messages are due one jiffy after they are sent plus their latency
+        let buckets = (max_latency.0 + 2).next_power_of_two().clamp(64, 1 << 16);
+        Self { kind, buckets }
+    }
+}
+
+/// Something scheduled to happen at a point of simulated time.
+pub(crate) trait Timed {
+    fn time(&self) -> Jiffies;
+}
+
+/// Queued item together with the order it was pushed in, which breaks ties in time.
+#[derive(Clone)]
+struct Entry<T> {
+    time: Jiffies,
+    seq: u64,
+    item: T,
+}
+
+impl<T> Entry<T> {
+    fn key(&self) -> (Jiffies, u64) {
+        (self.time, self.seq)
+    }
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key().eq(&other.key())
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+type Heap<T> = BinaryHeap<Reverse<Entry<T>>>;
+
+#[derive(Clone)]
+enum Queue<T> {
+    Heap(Heap<T>),
+    Calendar(CalendarQueue<T>),
+}
+
+/// Future-event list: pops items earliest first, items due at the same time in push order.
+#[derive(Clone)]
+pub(crate) struct FutureEvents<T> {
+    queue: Queue<T>,
+    next_seq: u64,
+}
+
+impl<T: Timed> FutureEvents<T> {
+    pub(crate) fn new(config: EventQueueConfig) -> Self {
+        let queue = match config.kind {
+            EventQueue::BinaryHeap => Queue::Heap(BinaryHeap::new()),
+            EventQueue::Calendar => Queue::Calendar(CalendarQueue::new(config.buckets)),
+        };
+        Self { queue, next_seq: 0 }
+    }
+
+    pub(crate) fn push(&mut self, item: T) {
+        let entry = Entry {
+            time: item.time(),
+            seq: self.next_seq,
+            item,
+        };
+        self.next_seq += 1;
+        match &mut self.queue {
+            Queue::Heap(heap) => heap.push(Reverse(entry)),
+            Queue::Calendar(calendar) => calendar.push(entry),
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let entry = match &mut self.queue {
+            Queue::Heap(heap) => heap.pop()?.0,
+            Queue::Calendar(calendar) => calendar.pop()?,
+        };
+        Some(entry.item)
+    }
+
+    /// Like [`FutureEvents::pop`], but `split` may take just the part of the earliest
+    /// item that is due, e.g. the next delivery of a fan-out, and return it. The rest
+    /// stays queued at its new time, in place, and keeps its order among items due
+    /// then. If `split` returns `None`, it must leave the item as is, which is popped.
+    pub(crate) fn pop_split(&mut self, split: impl FnOnce(&mut T) -> Option<T>) -> Option<T> {
+        match &mut self.queue {
+            Queue::Heap(heap) => {
+                let mut first = heap.peek_mut()?;
+                match split(&mut first.0.item) {
+                    // Sifted down to its new place once dropped
+                    Some(part) => {
+                        first.0.time = first.0.item.time();
+                        Some(part)
+                    }
+                    None => Some(PeekMut::pop(first).0.item),
+                }
+            }
+            Queue::Calendar(calendar) => {
+                let first = calendar.first_mut()?;
+                match split(&mut first.item) {
+                    Some(part) => {
+                        let time = first.item.time();
+                        // Stays first if the rest is due at the same time
+                        if time != first.time {
+                            first.time = time;
+                            calendar.reschedule_first();
+                        }
+                        Some(part)
+                    }
+                    None => Some(calendar.pop().expect("Just peeked").item),
+                }
+            }
+        }
+    }
+
+    /// Time of the earliest item.
+    pub(crate) fn peek_time(&self) -> Option<Jiffies> {
+        match &self.queue {
+            Queue::Heap(heap) => Some(heap.peek()?.0.time),
+            Queue::Calendar(calendar) => calendar.peek_time(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match &self.queue {
+            Queue::Heap(heap) => heap.len(),
+            Queue::Calendar(calendar) => calendar.len(),
+        }
+    }
+
+    /// Every item, in the order they pop.
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn ordered(&self) -> Vec<&T> {
+        let mut entries: Vec<&Entry<T>> = match &self.queue {
+            Queue::Heap(heap) => heap.iter().map(|entry| &entry.0).collect(),
+            Queue::Calendar(calendar) => calendar.entries().collect(),
+        };
+        entries.sort_unstable();
+        entries.into_iter().map(|entry| &entry.item).collect()
+    }
+
+    /// Keeps the items `keep` returns `true` for. It may modify them, including their time.
+    pub(crate) fn retain_mut(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+        self.rebuild(|mut entry| keep(&mut entry.item).then_some(entry));
+    }
+
+    /// Removes and returns the items `remove` returns `true` for.
+    pub(crate) fn extract(&mut self, mut remove: impl FnMut(&T) -> bool) -> Vec<T> {
+        let mut extracted = Vec::new();
+        self.rebuild(|entry| {
+            if remove(&entry.item) {
+                extracted.push(entry.item);
+                return None;
+            }
+            Some(entry)
+        });
+        extracted
+    }
+
+    /// Passes every entry through `f` in place, in `O(n)`. Entries keep their
+    /// order among those due at the same time, also if `f` changes their time.
+    fn rebuild(&mut self, mut f: impl FnMut(Entry<T>) -> Option<Entry<T>>) {
+        let mut f = |entry| {
+            let mut entry = f(entry)?;
+            entry.time = entry.item.time();
+            Some(entry)
+        };
+        match &mut self.queue {
+            Queue::Heap(heap) => {
+                let entries: Vec<Reverse<Entry<T>>> = std::mem::take(heap)
+                    .into_vec()
+                    .into_iter()
+                    .filter_map(|entry| f(entry.0).map(Reverse))
+                    .collect();
+                *heap = entries.into();
+            }
+            Queue::Calendar(calendar) => calendar.filter_map(f),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use crate::{ProcessHandle, Rank};
+
+/// What a process factory passed to [`crate::SimulationBuilder::add_pool_with`]
+/// knows about the process it builds, besides its rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FactoryCtx<'a> {
+    /// Name of the pool the process is added with.
+    pub pool: &'a str,
+    /// Position of the process within its pool: 0 for the first process
+    /// ever added to it, incremented for every next one, spawned ones included.
+    pub index: usize,
+    /// Number of processes in the pool: as added to the builder, or including
+    /// the process itself if it is spawned at runtime.
+    pub pool_size: usize,
+    /// 0 when the simulation is built, incremented by every
+    /// [`crate::SimulationRunner::restart`] of the process.
+    pub incarnation: usize,
+}
+
+pub(crate) type Factory =
+    Arc<dyn Fn(Rank, &FactoryCtx) -> Box<dyn ProcessHandle + Send> + Send + Sync>;
+
+#[derive(Clone)]
+struct Origin {
+    factory: Factory,
+    pool: String,
+    index: usize,
+    pool_size: usize,
+    incarnation: usize,
+}
+
+/// Factory of every rank, kept by the runners to rebuild restarted processes.
+#[derive(Clone, Default)]
+pub(crate) struct Factories {
+    // None for ranks reserved by a spawn that did not join yet
+    origins: Vec<Option<Origin>>,
+}
+
+impl Factories {
+    pub(crate) fn insert(
+        &mut self,
+        rank: Rank,
+        factory: Factory,
+        pool: &str,
+        index: usize,
+        pool_size: usize,
+    ) {
+        if self.origins.len() <= rank {
+            self.origins.resize_with(rank + 1, || None);
+        }
+        self.origins[rank] = Some(Origin {
+            factory,
+            pool: pool.to_string(),
+            index,
+            pool_size,
+            incarnation: 0,
+        });
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.origins.len()
+    }
+
+    /// Builds the current incarnation of the process.
+    pub(crate) fn build(&self, rank: Rank) -> Box<dyn ProcessHandle + Send> {
+        let origin = self.origins[rank].as_ref().expect("Unknown process");
+        let ctx = FactoryCtx {
+            pool: &origin.pool,
+            index: origin.index,
+            pool_size: origin.pool_size,
+            incarnation: origin.incarnation,
+        };
+        (origin.factory)(rank, &ctx)
+    }
+
+    /// Builds the next incarnation of the process.
+    pub(crate) fn rebuild(&mut self, rank: Rank) -> Box<dyn ProcessHandle + Send> {
+        self.origins[rank]
+            .as_mut()
+            .expect("Unknown process")
+            .incarnation += 1;
+        self.build(rank)
+    }
+}
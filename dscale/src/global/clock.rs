@@ -1,4 +1,7 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use log::debug;
 
@@ -6,15 +9,34 @@ use crate::Jiffies;
 
 pub(crate) static CLOCK: AtomicUsize = AtomicUsize::new(0);
 
+thread_local! {
+    // Time of the step handled on this thread. The parallel runner lets pools
+    // run ahead of the global clock, so a step may happen later than it says.
+    static STEP_CLOCK: Cell<Option<Jiffies>> = const { Cell::new(None) };
+}
+
 pub(crate) fn fast_forward_clock(future: Jiffies) {
     let present = Jiffies(CLOCK.swap(future.0, Ordering::Release));
     debug_assert!(present <= future, "Future < Present");
     debug!("Global time now: {future}");
 }
 
+/// Sets the clock to a time restored from a checkpoint or of another simulation,
+/// which may be in the past.
+pub(crate) fn set_clock(time: Jiffies) {
+    CLOCK.store(time.0, Ordering::Release);
+}
+
+/// Makes [`now`] return the time of the step handled on this thread, until cleared.
+pub(crate) fn set_step_clock(time: Option<Jiffies>) {
+    STEP_CLOCK.set(time);
+}
+
 /// Returns the current simulation time.
 pub fn now() -> Jiffies {
-    Jiffies(CLOCK.load(Ordering::Acquire))
+    STEP_CLOCK
+        .get()
+        .unwrap_or_else(|| Jiffies(CLOCK.load(Ordering::Acquire)))
 }
 
 pub(crate) fn reset() {
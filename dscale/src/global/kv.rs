@@ -1,11 +1,81 @@
-use std::any::Any;
+use std::any::{Any, TypeId, type_name};
 
 use dashmap::DashMap;
-use rustc_hash::FxBuildHasher;
+use rustc_hash::{FxBuildHasher, FxHashMap};
 
 type Map<K, V> = DashMap<K, V, FxBuildHasher>;
 
-static KV: std::sync::LazyLock<Map<String, Box<dyn Any + Send + Sync>>> =
+/// Stored value.
+pub(crate) trait Value: Any + Send + Sync {
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T: Any + Send + Sync> Value for T {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+}
+
+type Cloner = fn(&dyn Any) -> Box<dyn Value>;
+
+fn clone_as<T: Clone + Send + Sync + 'static>(value: &dyn Any) -> Box<dyn Value> {
+    Box::new(
+        value
+            .downcast_ref::<T>()
+            .expect("Cloner of another type")
+            .clone(),
+    )
+}
+
+/// Types of entries that are copied into forks of the simulation,
+/// see [`crate::SimulationBuilder::fork_value`].
+#[derive(Clone)]
+pub(crate) struct Cloners(FxHashMap<TypeId, Cloner>);
+
+impl Default for Cloners {
+    fn default() -> Self {
+        let mut cloners = Self(FxHashMap::default());
+        cloners.add::<usize>();
+        cloners.add::<u64>();
+        cloners.add::<u32>();
+        cloners.add::<i64>();
+        cloners.add::<i32>();
+        cloners.add::<f64>();
+        cloners.add::<bool>();
+        cloners.add::<String>();
+        cloners
+    }
+}
+
+impl Cloners {
+    pub(crate) fn add<T: Clone + Send + Sync + 'static>(&mut self) {
+        self.0.insert(TypeId::of::<T>(), clone_as::<T>);
+    }
+
+    /// Copy of the entries for a fork. Entries of types that are not registered
+    /// are left out with a warning, the fork fails only if it reads them.
+    pub(crate) fn copy(&self, entries: &Entries) -> Entries {
+        entries
+            .iter()
+            .filter_map(|(key, value)| {
+                let any = &**value as &dyn Any;
+                let Some(cloner) = self.0.get(&any.type_id()) else {
+                    log::warn!(
+                        "Key-value store entry {key} of type {} is not copied into the fork: register it with SimulationBuilder::fork_value",
+                        (**value).type_name()
+                    );
+                    return None;
+                };
+                Some((key.clone(), cloner(any)))
+            })
+            .collect()
+    }
+}
+
+/// Entries of a simulation that is not active, see [`crate::global::activate`].
+pub(crate) type Entries = Vec<(String, Box<dyn Value>)>;
+
+static KV: std::sync::LazyLock<Map<String, Box<dyn Value>>> =
     std::sync::LazyLock::new(|| DashMap::with_hasher(FxBuildHasher));
 
 /// Stores a value under the given key, replacing any previous value.
@@ -16,8 +86,7 @@ pub fn set<T: 'static + Send + Sync>(key: &str, value: T) {
 /// Retrieves a clone of the value stored under the given key.
 /// Panics if the key is missing or the type does not match.
 pub fn get<T: 'static + Clone + Send + Sync>(key: &str) -> T {
-    KV.get(key)
-        .expect("No key")
+    (&**KV.get(key).expect("No key") as &dyn Any)
         .downcast_ref::<T>()
         .cloned()
         .expect("Wrong type cast")
@@ -27,7 +96,33 @@ pub fn get<T: 'static + Clone + Send + Sync>(key: &str) -> T {
 /// Panics if the key is missing or the type does not match.
 pub fn modify<T: 'static + Send + Sync>(key: &str, f: impl FnOnce(&mut T)) {
     let mut entry = KV.get_mut(key).expect("No key");
-    f(entry.downcast_mut::<T>().expect("Wrong type cast"));
+    f((&mut **entry as &mut dyn Any)
+        .downcast_mut::<T>()
+        .expect("Wrong type cast"));
+}
+
+/// Every entry, sorted by key.
+#[cfg(feature = "checkpoint")]
+pub(crate) fn save<R>(mut f: impl FnMut(&str, &dyn Any) -> R) -> Vec<R> {
+    let mut keys: Vec<String> = KV.iter().map(|entry| entry.key().clone()).collect();
+    keys.sort_unstable();
+    keys.iter()
+        .map(|key| f(key, &**KV.get(key).expect("No key") as &dyn Any))
+        .collect()
+}
+
+/// Replaces every entry.
+pub(crate) fn load(entries: impl IntoIterator<Item = (String, Box<dyn Value>)>) {
+    KV.clear();
+    entries.into_iter().for_each(|(key, value)| {
+        KV.insert(key, value);
+    });
+}
+
+/// Removes every entry.
+pub(crate) fn take() -> Entries {
+    let keys: Vec<String> = KV.iter().map(|entry| entry.key().clone()).collect();
+    keys.into_iter().filter_map(|key| KV.remove(&key)).collect()
 }
 
 pub(crate) fn reset() {
@@ -1,20 +1,29 @@
 use std::cell::RefCell;
+use std::fmt::Display;
 use std::mem;
-use std::sync::Arc;
+use std::sync::{Arc, Once};
 
 use crossbeam_channel::Sender;
 use smallvec::SmallVec;
 
 use crate::destination::Destination;
 use crate::event::Event;
-use crate::random::{Randomizer, Seed};
-use crate::runners::task::{TaskId, TaskResult};
-use crate::{MessagePtr, global_unique_id, now};
+use crate::factory::{Factory, FactoryCtx};
+use crate::global::clock::set_step_clock;
+use crate::random::Randomizer;
+use crate::runners::task::{Snapshot, TaskId, TaskResult};
+use crate::step::Step;
+use crate::typed::Receives;
+use crate::{MessagePtr, ProcessHandle, global_unique_id, now};
 
-use crate::{
-    Message, Rank, actors::timer_actor::TimerId, debug_process, jiffy::Jiffies,
-    topology::GLOBAL_POOL,
-};
+use crate::{Message, Rank, actors::timer_actor::TimerId, jiffy::Jiffies, topology::GLOBAL_POOL};
+
+// Lines of the framework itself are only logged, the per-process capture is left to the user
+macro_rules! debug_access {
+    ($($arg:tt)+) => {
+        log::debug!("[Now: {} | P{}] [Access] {}", now(), rank(), format_args!($($arg)+))
+    };
+}
 
 const PREDICTION_SCHEDULED_PER_STEP: usize = 2;
 
@@ -22,29 +31,105 @@ pub(crate) type EventBatch = SmallVec<[Event; PREDICTION_SCHEDULED_PER_STEP]>;
 
 thread_local! {
     pub(crate) static LOCAL_ACCESS: RefCell<LocalAccess> = RefCell::new(LocalAccess::default());
+    // Kept apart from LOCAL_ACCESS so it is readable from the panic hook
+    // even if the panic happened while LOCAL_ACCESS was borrowed.
+    static EXECUTING: RefCell<Option<(Rank, Handling)>> = const { RefCell::new(None) };
+}
+
+/// Handler a process is executing.
+#[derive(Clone)]
+pub(crate) enum Handling {
+    Start,
+    Message { source: Rank, message: MessagePtr },
+    Timer { id: TimerId },
+}
+
+impl From<&Step> for Handling {
+    fn from(step: &Step) -> Self {
+        match step {
+            Step::Start { .. } => Handling::Start,
+            Step::NetworkStep {
+                source, message, ..
+            } => Handling::Message {
+                source: *source,
+                message: message.clone(),
+            },
+            Step::TimerStep { id, .. } => Handling::Timer { id: *id },
+        }
+    }
+}
+
+impl Display for Handling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Handling::Start => write!(f, "on_start"),
+            Handling::Message { source, message } => {
+                write!(f, "on_message {message:?} from P{source}")
+            }
+            Handling::Timer { id } => write!(f, "on_timer {id}"),
+        }
+    }
+}
+
+/// Makes panics inside process handlers report the process, simulation time
+/// and the handler with the message that was being processed. Any panic, e.g.
+/// of a failed assertion, prints the captured process logs.
+pub(crate) fn install_panic_report() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let executing = EXECUTING
+                .try_with(|cell| cell.try_borrow().ok().and_then(|e| e.clone()))
+                .ok()
+                .flatten();
+            let involved = executing.map(|(rank, handling)| {
+                eprintln!("P{rank} panicked at {} while executing {handling}", now());
+                match handling {
+                    Handling::Message { source, .. } if source != rank => vec![rank, source],
+                    _ => vec![rank],
+                }
+            });
+            if super::logs::enabled() {
+                eprint!("{}", super::logs::dump_on_panic(involved));
+            }
+            previous(info);
+        }));
+    });
+}
+
+fn set_executing(executing: Option<(Rank, Handling)>) {
+    EXECUTING.with(|cell| *cell.borrow_mut() = executing);
 }
 
 fn with_local_access<R>(f: impl FnOnce(&mut LocalAccess) -> R) -> R {
     LOCAL_ACCESS.with(|cell| f(&mut cell.borrow_mut()))
 }
 
-pub(crate) fn setup_local_access(seed: Seed, coordinator: Sender<TaskResult>) {
-    with_local_access(|access| {
-        access.random = Randomizer::new(seed);
-        access.coordinator = Some(coordinator)
-    });
+pub(crate) fn setup_local_access(coordinator: Sender<TaskResult>) {
+    with_local_access(|access| access.coordinator = Some(coordinator));
 }
 
 #[derive(Default)]
 pub(crate) struct LocalAccess {
     process_on_execution: Rank,
     current_task: TaskId,
+    // Generator of the executing process, it travels with the process between steps
     random: Randomizer,
     scheduled_events: EventBatch,
     coordinator: Option<Sender<TaskResult>>,
 }
 
 impl LocalAccess {
+    /// Access of a fork of the simulation.
+    pub(crate) fn fork(&self) -> Self {
+        debug_assert!(self.scheduled_events.is_empty(), "Forked during a step");
+        Self {
+            coordinator: self.coordinator.clone(),
+            ..Self::default()
+        }
+    }
+
     fn broadcast_within_pool(&mut self, pool_name: &'static str, message: impl Message + 'static) {
         self.scheduled_events.push(Event::NetworkEvent {
             source: self.process_on_execution,
@@ -61,6 +146,14 @@ impl LocalAccess {
         });
     }
 
+    fn multicast(&mut self, ranks: &[Rank], message: impl Message + 'static) {
+        self.scheduled_events.push(Event::NetworkEvent {
+            source: self.process_on_execution,
+            destination: Destination::Multicast(ranks.into()),
+            message: MessagePtr(Arc::new(message)),
+        });
+    }
+
     fn send_random_from_pool(&mut self, pool: &str, message: impl Message + 'static) {
         let target = self.choose_from_pool(pool);
         self.send_to(target, message);
@@ -68,25 +161,77 @@ impl LocalAccess {
 
     fn choose_from_pool(&mut self, pool_name: &str) -> Rank {
         let pool = super::shared_access::list_pool(pool_name);
-        self.random.choose_from_slice(pool)
+        self.random.choose_from_slice(&pool)
+    }
+
+    fn spawn(&mut self, pool: &str, factory: Factory, receives: Receives) -> Rank {
+        super::shared_access::assert_churn("spawn", self.process_on_execution);
+        let rank = super::shared_access::reserve_rank(pool, self.process_on_execution);
+        self.scheduled_events.push(Event::Spawn {
+            rank,
+            pool: pool.to_string(),
+            factory,
+            receives,
+        });
+        rank
     }
 
-    fn schedule_timer_after(&mut self, after: Jiffies) -> TimerId {
+    fn exit(&mut self) {
+        super::shared_access::assert_churn("exit", self.process_on_execution);
+        self.scheduled_events.push(Event::Exit {
+            rank: self.process_on_execution,
+        });
+    }
+
+    fn create_pool(&mut self, pool: &str) {
+        super::shared_access::assert_churn("create_pool", self.process_on_execution);
+        self.scheduled_events.push(Event::CreatePool {
+            pool: pool.to_string(),
+        });
+    }
+
+    fn join_pool(&mut self, pool: &str, rank: Rank) {
+        super::shared_access::assert_churn("join_pool", self.process_on_execution);
+        assert_ne!(
+            pool, GLOBAL_POOL,
+            "Can not change membership of {GLOBAL_POOL}"
+        );
+        self.scheduled_events.push(Event::JoinPool {
+            rank,
+            pool: pool.to_string(),
+        });
+    }
+
+    fn leave_pool(&mut self, pool: &str, rank: Rank) {
+        super::shared_access::assert_churn("leave_pool", self.process_on_execution);
+        assert_ne!(
+            pool, GLOBAL_POOL,
+            "Can not change membership of {GLOBAL_POOL}"
+        );
+        self.scheduled_events.push(Event::LeavePool {
+            rank,
+            pool: pool.to_string(),
+        });
+    }
+
+    fn schedule_timer_after(&mut self, after: Jiffies, periodic: bool) -> TimerId {
         let timer_id = global_unique_id();
         self.scheduled_events.push(Event::TimerEvent {
             rank: self.process_on_execution,
             id: timer_id,
             fire_after: after,
+            periodic,
         });
         timer_id
     }
 
-    fn set_task(&mut self, task_id: TaskId, proc_id: Rank) {
+    fn set_task(&mut self, task_id: TaskId, proc_id: Rank, random: Randomizer) {
         self.process_on_execution = proc_id;
         self.current_task = task_id;
+        self.random = random;
     }
 
-    fn done(&mut self) {
+    fn done(&mut self, proc: Box<dyn ProcessHandle + Send>, before: Option<Snapshot>) {
         let _ = self
             .coordinator
             .as_ref()
@@ -95,11 +240,17 @@ impl LocalAccess {
                 id: self.current_task,
                 rank: self.process_on_execution,
                 events: mem::take(&mut self.scheduled_events),
+                proc: Some(proc),
+                random: mem::take(&mut self.random),
+                before,
             });
     }
 
-    fn take_events(&mut self) -> EventBatch {
-        mem::take(&mut self.scheduled_events)
+    fn take_events(&mut self) -> (EventBatch, Randomizer) {
+        (
+            mem::take(&mut self.scheduled_events),
+            mem::take(&mut self.random),
+        )
     }
 
     fn rank(&self) -> Rank {
@@ -107,51 +258,79 @@ impl LocalAccess {
     }
 }
 
-pub(crate) fn set_task(task_id: TaskId, proc_id: Rank) {
-    with_local_access(|access| access.set_task(task_id, proc_id));
+/// Makes the thread execute a step of the process, drawing its random choices from `random`.
+pub(crate) fn set_task(task_id: TaskId, proc_id: Rank, handling: Handling, random: Randomizer) {
+    set_executing(Some((proc_id, handling)));
+    set_step_clock(Some(task_id.0));
+    with_local_access(|access| access.set_task(task_id, proc_id, random));
 }
 
-pub(crate) fn done() {
-    with_local_access(|access| access.done());
+pub(crate) fn done(proc: Box<dyn ProcessHandle + Send>, before: Option<Snapshot>) {
+    set_executing(None);
+    set_step_clock(None);
+    with_local_access(|access| access.done(proc, before));
 }
 
-pub(crate) fn take_events() -> EventBatch {
+/// Ends the step, returning what it scheduled and the generator of the process.
+pub(crate) fn take_events() -> (EventBatch, Randomizer) {
+    set_executing(None);
+    set_step_clock(None);
     with_local_access(|access| access.take_events())
 }
 
 /// Schedules a timer for the current process, firing after the given delay.
 /// Returns a [`TimerId`] that will be passed to [`crate::ProcessHandle::on_timer`].
 pub fn schedule_timer_after(after: Jiffies) -> TimerId {
-    debug_process!("[Access] scheduling timer after {after}");
-    with_local_access(|access| access.schedule_timer_after(after))
+    debug_access!("scheduling timer after {after}");
+    with_local_access(|access| access.schedule_timer_after(after, false))
+}
+
+/// Like [`schedule_timer_after`], for a timer of periodic background activity,
+/// e.g. a heartbeat that its handler schedules again.
+///
+/// [`crate::SimulationRunner::run_until_quiescent`] does not wait for such timers,
+/// unlike for those of [`schedule_timer_after`], e.g. retry or election timeouts.
+pub fn schedule_periodic_timer_after(after: Jiffies) -> TimerId {
+    debug_access!("scheduling periodic timer after {after}");
+    with_local_access(|access| access.schedule_timer_after(after, true))
 }
 
 /// Sends a message to all processes in [`GLOBAL_POOL`] (i.e. every process).
 pub fn broadcast(message: impl Message + 'static) {
+    debug_access!("broadcasting {}", message.label());
     with_local_access(|access| access.broadcast_within_pool(GLOBAL_POOL, message));
 }
 
 /// Sends a message to all processes within the named pool.
 pub fn broadcast_within_pool(pool: &'static str, message: impl Message + 'static) {
-    debug_process!("[Access] broadcasting within: {pool}");
+    debug_access!("broadcasting {} within: {pool}", message.label());
     with_local_access(|access| access.broadcast_within_pool(pool, message));
 }
 
 /// Sends a message to the process with the given rank.
 pub fn send_to(rank: Rank, message: impl Message + 'static) {
-    debug_process!("[Access] send to: P{rank}");
+    debug_access!("send {} to: P{rank}", message.label());
     with_local_access(|access| access.send_to(rank, message));
 }
 
+/// Sends one message to every process in `ranks`, e.g. a quorum.
+///
+/// Unlike calling [`send_to`] for every rank, the message is shared
+/// and expanded into deliveries by the network.
+pub fn multicast(ranks: &[Rank], message: impl Message + 'static) {
+    debug_access!("multicast {} to: P{ranks:?}", message.label());
+    with_local_access(|access| access.multicast(ranks, message));
+}
+
 /// Sends a message to a randomly chosen process from [`GLOBAL_POOL`].
 pub fn send_random(message: impl Message + 'static) {
-    debug_process!("[Access] sending random P from GLOBAL_POOL");
+    debug_access!("sending {} to random P from GLOBAL_POOL", message.label());
     with_local_access(|access| access.send_random_from_pool(GLOBAL_POOL, message));
 }
 
 /// Sends a message to a randomly chosen process from the named pool.
 pub fn send_random_from_pool(pool: &'static str, message: impl Message + 'static) {
-    debug_process!("[Access] sending random from pool {pool}");
+    debug_access!("sending {} to random P from pool {pool}", message.label());
     with_local_access(|access| access.send_random_from_pool(pool, message));
 }
 
@@ -165,6 +344,78 @@ pub fn choose_from_pool(pool_name: &str) -> Rank {
     with_local_access(|access| access.choose_from_pool(pool_name))
 }
 
+/// Starts a default-constructed process of type `P` in the named pool and returns its rank.
+///
+/// The pool must have been added to the [`crate::SimulationBuilder`], its latency
+/// applies to the new process. The process joins the pool and [`GLOBAL_POOL`]
+/// and is started once the current step finishes. With the parallel runner,
+/// spawning into another pool than the own one must be allowed with
+/// [`crate::SimulationBuilder::allow_spawn_into`]. Panics under the optimistic runner,
+/// as do [`exit`], [`create_pool`], [`join_pool`] and [`leave_pool`].
+pub fn spawn<P: ProcessHandle + Default + Send + 'static>(pool: &str) -> Rank {
+    spawn_with(pool, |_, _| P::default())
+}
+
+/// Like [`spawn`], but builds the process with `factory`, which is also used
+/// to rebuild it on [`crate::SimulationRunner::restart`].
+pub fn spawn_with<P: ProcessHandle + Send + 'static>(
+    pool: &str,
+    factory: impl Fn(Rank, &FactoryCtx) -> P + Send + Sync + 'static,
+) -> Rank {
+    let factory: Factory = Arc::new(move |rank, ctx| Box::new(factory(rank, ctx)));
+    let receives = Receives::of::<P>();
+    let rank = with_local_access(|access| access.spawn(pool, factory, receives));
+    debug_access!("spawning P{rank} in pool {pool}");
+    rank
+}
+
+/// Removes the current process from the simulation once the current step finishes.
+///
+/// It leaves every pool, its pending timers are dropped and messages to it,
+/// in flight or sent later, are counted as dropped in the [`crate::RunReport`] traffic.
+pub fn exit() {
+    debug_access!("exiting");
+    with_local_access(|access| access.exit());
+}
+
+/// Creates an empty named pool once the current step finishes.
+/// Creating a pool that already exists does nothing, so processes may create
+/// the same pool independently.
+///
+/// Processes are added with [`join_pool`]. Unlike pools added to the
+/// [`crate::SimulationBuilder`], it has no latency of its own, so processes
+/// can not be spawned into it: latency stays the one of their home pool.
+pub fn create_pool(pool: &str) {
+    debug_access!("creating pool {pool}");
+    with_local_access(|access| access.create_pool(pool));
+}
+
+/// Adds the process to the named pool once the current step finishes,
+/// so [`broadcast_within_pool`], [`choose_from_pool`] and [`crate::list_pool`]
+/// see it from the next step on.
+///
+/// Adding a process that is already in the pool or has exited does nothing.
+pub fn join_pool(pool: &str, rank: Rank) {
+    debug_access!("adding P{rank} to pool {pool}");
+    with_local_access(|access| access.join_pool(pool, rank));
+}
+
+/// Removes the process from the named pool once the current step finishes.
+/// Messages already sent to the pool are still delivered to it.
+pub fn leave_pool(pool: &str, rank: Rank) {
+    debug_access!("removing P{rank} from pool {pool}");
+    with_local_access(|access| access.leave_pool(pool, rank));
+}
+
+/// Access of this thread to a simulation that is not active, see [`crate::global::activate`].
+pub(crate) fn take() -> LocalAccess {
+    LOCAL_ACCESS.with(|cell| cell.take())
+}
+
+pub(crate) fn put(access: LocalAccess) {
+    LOCAL_ACCESS.with(|cell| *cell.borrow_mut() = access);
+}
+
 pub(crate) fn reset() {
     LOCAL_ACCESS.with(|cell| *cell.borrow_mut() = LocalAccess::default());
 }
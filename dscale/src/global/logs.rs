@@ -0,0 +1,155 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::{Arguments, Write},
+    sync::{
+        LazyLock, Mutex, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use log::Level;
+
+use crate::{Jiffies, Rank, global};
+
+/// Line logged by a process with [`crate::log_process!`] or [`crate::debug_process!`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub time: Jiffies,
+    pub rank: Rank,
+    pub level: Level,
+    pub text: String,
+}
+
+// Lines kept per process, 0 while capture is disabled.
+static CAPACITY: AtomicUsize = AtomicUsize::new(0);
+static BUFFERS: LazyLock<RwLock<Vec<Mutex<VecDeque<LogLine>>>>> = LazyLock::new(Default::default);
+
+thread_local! {
+    // Processes whose lines the next panic of the thread prints, see [`crate::assert_ranks!`]
+    static FOCUS: RefCell<Option<Vec<Rank>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn setup(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Release);
+}
+
+fn with_buffer<R>(rank: Rank, f: impl FnOnce(&mut VecDeque<LogLine>) -> R) -> R {
+    {
+        let buffers = BUFFERS.read().unwrap();
+        if let Some(buffer) = buffers.get(rank) {
+            return f(&mut buffer.lock().unwrap());
+        }
+    }
+    let mut buffers = BUFFERS.write().unwrap();
+    if buffers.len() <= rank {
+        buffers.resize_with(rank + 1, Default::default);
+    }
+    f(&mut buffers[rank].lock().unwrap())
+}
+
+/// Logs a line of the executing process under `target` and captures it.
+#[doc(hidden)]
+pub fn emit(level: Level, target: &str, args: Arguments) {
+    log::log!(target: target, level, "[Now: {} | P{}] {}", global::now(), global::rank(), args);
+    capture(level, args);
+}
+
+fn capture(level: Level, args: Arguments) {
+    let capacity = CAPACITY.load(Ordering::Acquire);
+    if capacity == 0 {
+        return;
+    }
+    let rank = global::rank();
+    let line = LogLine {
+        time: global::now(),
+        rank,
+        level,
+        text: args.to_string(),
+    };
+    with_buffer(rank, |buffer| {
+        if buffer.len() == capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(line);
+    });
+}
+
+/// Captured lines of a process, oldest first.
+pub fn of(rank: Rank) -> Vec<LogLine> {
+    BUFFERS
+        .read()
+        .unwrap()
+        .get(rank)
+        .map(|buffer| buffer.lock().unwrap().iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Captured lines of a process logged within `[from, to]`.
+pub fn between(rank: Rank, from: Jiffies, to: Jiffies) -> Vec<LogLine> {
+    of(rank)
+        .into_iter()
+        .filter(|line| from <= line.time && line.time <= to)
+        .collect()
+}
+
+/// Formats the captured lines of the given processes for printing.
+pub fn dump(ranks: &[Rank]) -> String {
+    let mut out = String::new();
+    for &rank in ranks {
+        let _ = writeln!(out, "--- P{rank} log ---");
+        for line in of(rank) {
+            let _ = writeln!(out, "[{}] {:<5} {}", line.time, line.level, line.text);
+        }
+    }
+    out
+}
+
+/// Makes the next panic of this thread print the captured lines of only these processes.
+#[doc(hidden)]
+pub fn focus(ranks: &[Rank]) {
+    FOCUS.with(|focus| *focus.borrow_mut() = Some(ranks.to_vec()));
+}
+
+/// Lines printed when a thread panics: of the processes given to [`focus`],
+/// else of those involved in the panicking step, else of every process.
+pub(crate) fn dump_on_panic(involved: Option<Vec<Rank>>) -> String {
+    let focused = FOCUS.try_with(|focus| focus.take()).ok().flatten();
+    let ranks = focused
+        .or(involved)
+        .unwrap_or_else(|| (0..BUFFERS.read().unwrap().len()).collect());
+    dump(&ranks)
+}
+
+/// Whether [`crate::SimulationBuilder::capture_process_logs`] is enabled.
+#[doc(hidden)]
+pub fn enabled() -> bool {
+    CAPACITY.load(Ordering::Acquire) > 0
+}
+
+/// Lines of a simulation that is not active, see [`crate::global::activate`].
+#[derive(Clone)]
+pub(crate) struct Parked {
+    capacity: usize,
+    buffers: Vec<VecDeque<LogLine>>,
+}
+
+pub(crate) fn take() -> Parked {
+    Parked {
+        capacity: CAPACITY.swap(0, Ordering::AcqRel),
+        buffers: std::mem::take(&mut *BUFFERS.write().unwrap())
+            .into_iter()
+            .map(|buffer| buffer.into_inner().unwrap())
+            .collect(),
+    }
+}
+
+pub(crate) fn put(parked: Parked) {
+    CAPACITY.store(parked.capacity, Ordering::Release);
+    *BUFFERS.write().unwrap() = parked.buffers.into_iter().map(Mutex::new).collect();
+}
+
+pub(crate) fn reset() {
+    CAPACITY.store(0, Ordering::Release);
+    BUFFERS.write().unwrap().clear();
+}
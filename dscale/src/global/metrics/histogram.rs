@@ -0,0 +1,116 @@
+/// Values below this are recorded exactly.
+const EXACT: u64 = 128;
+/// Buckets per power of two above [`EXACT`]. Bounds the relative error to 1/64.
+const SUB_BUCKETS: u64 = 64;
+
+/// Latency histogram with HDR-style log-linear buckets.
+///
+/// Values are recorded with a relative precision of about 1.5%, while
+/// count, sum, min and max are exact.
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+fn bucket_of(value: u64) -> usize {
+    if value < EXACT {
+        return value as usize;
+    }
+    let msb = 63 - value.leading_zeros() as u64;
+    let shift = msb - 6;
+    (SUB_BUCKETS * shift + (value >> shift)) as usize
+}
+
+fn value_of(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < EXACT {
+        return bucket;
+    }
+    let shift = bucket / SUB_BUCKETS - 1;
+    let mantissa = bucket % SUB_BUCKETS + SUB_BUCKETS;
+    // Middle of the bucket range
+    (mantissa << shift) + (1 << shift) / 2
+}
+
+impl Histogram {
+    /// Records a single value.
+    pub fn record(&mut self, value: u64) {
+        let bucket = bucket_of(value);
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+        self.min = if self.count == 0 {
+            value
+        } else {
+            self.min.min(value)
+        };
+        self.max = self.max.max(value);
+        self.count += 1;
+        self.sum += value as u128;
+    }
+
+    /// Adds all values recorded by `other`.
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.count == 0 {
+            return;
+        }
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *bucket += count;
+        }
+        self.min = if self.count == 0 {
+            other.min
+        } else {
+            self.min.min(other.min)
+        };
+        self.max = self.max.max(other.max);
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+
+    /// Number of recorded values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Smallest recorded value, 0 if empty.
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    /// Largest recorded value, 0 if empty.
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Arithmetic mean of recorded values, 0 if empty.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum as f64 / self.count as f64
+    }
+
+    /// Value at quantile `q` in `[0, 1]`, e.g. `0.99` for the 99th percentile. 0 if empty.
+    pub fn value_at_quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return value_of(bucket).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+}
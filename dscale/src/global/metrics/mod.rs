@@ -0,0 +1,342 @@
+//! Counters, gauges and histograms updated from process handlers.
+//!
+//! Every update is tagged with the rank of the process on execution, so
+//! values can be aggregated per process, per home pool or globally.
+//! Updates go to a per-rank slot and never contend across processes,
+//! which keeps them cheap in both simple and parallel runs.
+//!
+//! With [`crate::SimulationBuilder::sample_metrics_every`] the registry is
+//! additionally sampled over simulated time; [`write_csv`] exports the
+//! resulting time series together with the final values.
+//!
+//! Like [`crate::global::kv`], the registry is cleared when the runner is dropped.
+
+mod histogram;
+
+use std::{
+    io::{self, Write},
+    sync::{LazyLock, Mutex, RwLock},
+};
+
+pub use histogram::Histogram;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    Jiffies, Rank,
+    global::{self, shared_access},
+};
+
+#[derive(Debug, Clone)]
+enum Metric {
+    Counter(u64),
+    Gauge(f64),
+    Histogram(Histogram),
+}
+
+type Slot = FxHashMap<&'static str, Metric>;
+
+#[derive(Default)]
+struct Registry {
+    slots: RwLock<Vec<Mutex<Slot>>>,
+    series: Mutex<Vec<Sample>>,
+}
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+/// Value of a single metric of one process.
+#[derive(Debug, Clone)]
+pub enum MetricValue {
+    Counter(u64),
+    Gauge(f64),
+    Histogram(Histogram),
+}
+
+/// Value of a metric of one process at a point in simulated time.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub time: Jiffies,
+    pub metric: &'static str,
+    pub rank: Rank,
+    /// Home pool of the process.
+    pub pool: String,
+    pub value: MetricValue,
+}
+
+fn with_slot<R>(rank: Rank, f: impl FnOnce(&mut Slot) -> R) -> R {
+    {
+        let slots = REGISTRY.slots.read().unwrap();
+        if let Some(slot) = slots.get(rank) {
+            return f(&mut slot.lock().unwrap());
+        }
+    }
+    let mut slots = REGISTRY.slots.write().unwrap();
+    if slots.len() <= rank {
+        slots.resize_with(rank + 1, Default::default);
+    }
+    f(&mut slots[rank].lock().unwrap())
+}
+
+fn update(name: &'static str, init: impl FnOnce() -> Metric, f: impl FnOnce(&mut Metric) -> bool) {
+    let rank = global::rank();
+    with_slot(rank, |slot| {
+        let metric = slot.entry(name).or_insert_with(init);
+        assert!(
+            f(metric),
+            "Metric '{name}' was registered with a different kind"
+        );
+    })
+}
+
+/// Adds `delta` to the counter `name` of the current process.
+pub fn increment(name: &'static str, delta: u64) {
+    update(
+        name,
+        || Metric::Counter(0),
+        |metric| match metric {
+            Metric::Counter(value) => {
+                *value += delta;
+                true
+            }
+            _ => false,
+        },
+    )
+}
+
+/// Sets the gauge `name` of the current process.
+pub fn set_gauge(name: &'static str, value: f64) {
+    update(
+        name,
+        || Metric::Gauge(0.0),
+        |metric| match metric {
+            Metric::Gauge(current) => {
+                *current = value;
+                true
+            }
+            _ => false,
+        },
+    )
+}
+
+/// Records `value` (e.g. a latency in jiffies) into the histogram `name` of the current process.
+pub fn record(name: &'static str, value: u64) {
+    update(
+        name,
+        || Metric::Histogram(Histogram::default()),
+        |metric| match metric {
+            Metric::Histogram(histogram) => {
+                histogram.record(value);
+                true
+            }
+            _ => false,
+        },
+    )
+}
+
+fn fold<T>(
+    name: &str,
+    ranks: impl Fn(Rank) -> bool,
+    mut acc: T,
+    mut f: impl FnMut(&mut T, &Metric),
+) -> T {
+    let slots = REGISTRY.slots.read().unwrap();
+    slots
+        .iter()
+        .enumerate()
+        .filter(|(rank, _)| ranks(*rank))
+        .for_each(|(_, slot)| {
+            if let Some(metric) = slot.lock().unwrap().get(name) {
+                f(&mut acc, metric)
+            }
+        });
+    acc
+}
+
+fn sum_counter(acc: &mut u64, metric: &Metric) {
+    if let Metric::Counter(value) = metric {
+        *acc += value
+    }
+}
+
+fn merge_histogram(acc: &mut Histogram, metric: &Metric) {
+    if let Metric::Histogram(histogram) = metric {
+        acc.merge(histogram)
+    }
+}
+
+fn in_pool(pool: &str) -> impl Fn(Rank) -> bool {
+    let ranks = global::list_pool(pool);
+    move |rank| ranks.contains(&rank)
+}
+
+/// Sum of the counter `name` over all processes.
+pub fn counter(name: &str) -> u64 {
+    fold(name, |_| true, 0, sum_counter)
+}
+
+/// Value of the counter `name` of a single process.
+pub fn counter_of(name: &str, rank: Rank) -> u64 {
+    fold(name, |r| r == rank, 0, sum_counter)
+}
+
+/// Sum of the counter `name` over all processes of a pool.
+pub fn counter_in_pool(name: &str, pool: &str) -> u64 {
+    fold(name, in_pool(pool), 0, sum_counter)
+}
+
+/// Last value of the gauge `name` set by a single process.
+pub fn gauge_of(name: &str, rank: Rank) -> Option<f64> {
+    fold(
+        name,
+        |r| r == rank,
+        None,
+        |acc, metric| {
+            if let Metric::Gauge(value) = metric {
+                *acc = Some(*value)
+            }
+        },
+    )
+}
+
+/// Histogram `name` merged over all processes.
+pub fn histogram(name: &str) -> Histogram {
+    fold(name, |_| true, Histogram::default(), merge_histogram)
+}
+
+/// Histogram `name` of a single process.
+pub fn histogram_of(name: &str, rank: Rank) -> Histogram {
+    fold(name, |r| r == rank, Histogram::default(), merge_histogram)
+}
+
+/// Histogram `name` merged over all processes of a pool.
+pub fn histogram_in_pool(name: &str, pool: &str) -> Histogram {
+    fold(name, in_pool(pool), Histogram::default(), merge_histogram)
+}
+
+/// Current value of every metric of every process, ordered by metric name and rank.
+pub fn snapshot() -> Vec<Sample> {
+    snapshot_at(global::now())
+}
+
+fn snapshot_at(time: Jiffies) -> Vec<Sample> {
+    let slots = REGISTRY.slots.read().unwrap();
+    let mut samples: Vec<Sample> = slots
+        .iter()
+        .enumerate()
+        .flat_map(|(rank, slot)| {
+            let pool = shared_access::home_pool(rank).to_string();
+            slot.lock()
+                .unwrap()
+                .iter()
+                .map(|(metric, value)| Sample {
+                    time,
+                    metric,
+                    rank,
+                    pool: pool.clone(),
+                    value: match value {
+                        Metric::Counter(v) => MetricValue::Counter(*v),
+                        Metric::Gauge(v) => MetricValue::Gauge(*v),
+                        Metric::Histogram(h) => MetricValue::Histogram(h.clone()),
+                    },
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    samples.sort_by(|a, b| a.metric.cmp(b.metric).then(a.rank.cmp(&b.rank)));
+    samples
+}
+
+/// Time series collected with [`crate::SimulationBuilder::sample_metrics_every`].
+pub fn samples() -> Vec<Sample> {
+    REGISTRY.series.lock().unwrap().clone()
+}
+
+/// Writes the sampled time series followed by the current values as CSV.
+///
+/// Columns are `time,metric,rank,pool,stat,value`. Counters and gauges
+/// produce a single `count`/`value` row, histograms produce
+/// `count`, `mean`, `p50`, `p90`, `p99` and `max` rows.
+pub fn write_csv(mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "time,metric,rank,pool,stat,value")?;
+    let series = REGISTRY.series.lock().unwrap();
+    for sample in series.iter().chain(snapshot().iter()) {
+        let prefix = format!(
+            "{},{},{},{}",
+            sample.time.0, sample.metric, sample.rank, sample.pool
+        );
+        match &sample.value {
+            MetricValue::Counter(v) => writeln!(out, "{prefix},count,{v}")?,
+            MetricValue::Gauge(v) => writeln!(out, "{prefix},value,{v}")?,
+            MetricValue::Histogram(h) => {
+                writeln!(out, "{prefix},count,{}", h.count())?;
+                writeln!(out, "{prefix},mean,{}", h.mean())?;
+                writeln!(out, "{prefix},p50,{}", h.value_at_quantile(0.5))?;
+                writeln!(out, "{prefix},p90,{}", h.value_at_quantile(0.9))?;
+                writeln!(out, "{prefix},p99,{}", h.value_at_quantile(0.99))?;
+                writeln!(out, "{prefix},max,{}", h.max())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Samples the registry whenever simulated time crosses a multiple of the period.
+#[derive(Debug, Clone)]
+pub(crate) struct Sampler {
+    period: Option<Jiffies>,
+    next: Jiffies,
+}
+
+impl Sampler {
+    pub(crate) fn new(period: Option<Jiffies>) -> Self {
+        assert!(
+            period.is_none_or(|p| p > Jiffies(0)),
+            "Metrics sampling period must be positive"
+        );
+        Self {
+            period,
+            next: Jiffies(0),
+        }
+    }
+
+    /// Called by the runner before executing steps at `now`.
+    /// Several skipped periods produce a single sample at the latest one.
+    pub(crate) fn observe(&mut self, now: Jiffies) {
+        let Some(period) = self.period else {
+            return;
+        };
+        if now < self.next {
+            return;
+        }
+        let boundary = Jiffies(now.0 - now.0 % period.0);
+        let samples = snapshot_at(boundary);
+        REGISTRY.series.lock().unwrap().extend(samples);
+        self.next = boundary + period;
+    }
+}
+
+/// Registry of a simulation that is not active, see [`crate::global::activate`].
+#[derive(Clone)]
+pub(crate) struct Parked {
+    slots: Vec<Slot>,
+    series: Vec<Sample>,
+}
+
+pub(crate) fn take() -> Parked {
+    Parked {
+        slots: std::mem::take(&mut *REGISTRY.slots.write().unwrap())
+            .into_iter()
+            .map(|slot| slot.into_inner().unwrap())
+            .collect(),
+        series: std::mem::take(&mut *REGISTRY.series.lock().unwrap()),
+    }
+}
+
+pub(crate) fn put(parked: Parked) {
+    *REGISTRY.slots.write().unwrap() = parked.slots.into_iter().map(Mutex::new).collect();
+    *REGISTRY.series.lock().unwrap() = parked.series;
+}
+
+pub(crate) fn reset() {
+    REGISTRY.slots.write().unwrap().clear();
+    REGISTRY.series.lock().unwrap().clear();
+}
@@ -1,30 +1,176 @@
+use std::sync::{
+    Arc, LazyLock, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::{Jiffies, random::Seed, topology::Topology};
+use local_access::LocalAccess;
+
 pub(crate) mod clock;
 /// Per-process configuration (seed, total process count).
 pub mod configuration;
 /// Thread-safe key-value store shared across all processes.
 pub mod kv;
 pub(crate) mod local_access;
+/// Per-process capture of lines logged with `log_process!` and `debug_process!`.
+pub mod logs;
+/// Counters, gauges and histograms tagged by process and pool.
+pub mod metrics;
 mod shared_access;
-mod tso;
+pub(crate) mod tso;
 
 pub(crate) use clock::fast_forward_clock;
 pub use clock::now;
+pub(crate) use local_access::install_panic_report;
+
+// Simulation the globals belong to, 0 if none
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static NEXT_SIMULATION: AtomicUsize = AtomicUsize::new(1);
+static PARKED: LazyLock<Mutex<FxHashMap<usize, Globals>>> = LazyLock::new(Default::default);
+
+/// Global state of a simulation while another one is active.
+struct Globals {
+    time: Jiffies,
+    next_id: usize,
+    kv: kv::Entries,
+    shared_access: shared_access::Parked,
+    metrics: metrics::Parked,
+    logs: logs::Parked,
+    local_access: LocalAccess,
+}
+
+impl Globals {
+    /// Takes the state out of the globals, leaving them cleared.
+    fn take() -> Self {
+        let globals = Self {
+            time: clock::now(),
+            next_id: tso::next_id(),
+            kv: kv::take(),
+            shared_access: shared_access::take(),
+            metrics: metrics::take(),
+            logs: logs::take(),
+            local_access: local_access::take(),
+        };
+        reset();
+        globals
+    }
+
+    fn put(self) {
+        clock::set_clock(self.time);
+        tso::set_next_id(self.next_id);
+        kv::load(self.kv);
+        shared_access::put(self.shared_access);
+        metrics::put(self.metrics);
+        logs::put(self.logs);
+        local_access::put(self.local_access);
+    }
+}
+
+/// Parks the globals of the active simulation, if any.
+fn park() {
+    let active = ACTIVE.swap(0, Ordering::AcqRel);
+    if active != 0 {
+        PARKED.lock().unwrap().insert(active, Globals::take());
+    }
+}
 
-pub(crate) fn reset() {
+/// Starts a new simulation with cleared globals. Returns its id.
+pub(crate) fn enter() -> usize {
+    park();
+    let id = NEXT_SIMULATION.fetch_add(1, Ordering::Relaxed);
+    ACTIVE.store(id, Ordering::Release);
+    id
+}
+
+/// Simulation the globals belong to.
+pub(crate) fn active() -> usize {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+/// Makes the globals hold the state of the simulation, parking the active one.
+/// Called by runners whenever they are used, so several simulations can be alive.
+pub(crate) fn activate(id: usize) {
+    if ACTIVE.load(Ordering::Acquire) == id {
+        return;
+    }
+    park();
+    let globals = PARKED
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .expect("Unknown simulation");
+    globals.put();
+    ACTIVE.store(id, Ordering::Release);
+}
+
+/// Parks a copy of the active simulation on the `topology` copied from it, with
+/// its process seeds derived from `seed`, and returns its id. Entries of the
+/// key-value store are copied with `cloners`. The active simulation stays active.
+pub(crate) fn fork(topology: Arc<Topology>, seed: Seed, cloners: &kv::Cloners) -> usize {
+    let origin = Globals::take();
+    let fork = Globals {
+        time: origin.time,
+        next_id: origin.next_id,
+        kv: cloners.copy(&origin.kv),
+        shared_access: shared_access::Parked::new(topology.clone()),
+        metrics: origin.metrics.clone(),
+        logs: origin.logs.clone(),
+        local_access: origin.local_access.fork(),
+    };
+    // The seeds of the processes are entries of the fork
+    fork.put();
+    for rank in 0..topology.process_number() {
+        configuration::setup_local_configuration(rank, seed);
+    }
+    let fork = Globals::take();
+    origin.put();
+    let id = NEXT_SIMULATION.fetch_add(1, Ordering::Relaxed);
+    PARKED.lock().unwrap().insert(id, fork);
+    id
+}
+
+/// Clears the globals of a dropped simulation.
+pub(crate) fn leave(id: usize) {
+    if ACTIVE
+        .compare_exchange(id, 0, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok()
+    {
+        reset();
+    } else {
+        PARKED.lock().unwrap().remove(&id);
+    }
+}
+
+fn reset() {
     clock::reset();
     tso::reset();
     shared_access::reset();
     local_access::reset();
     kv::reset();
+    metrics::reset();
+    logs::reset();
 }
+
 pub use local_access::broadcast;
 pub use local_access::broadcast_within_pool;
 pub use local_access::choose_from_pool;
+pub use local_access::create_pool;
+pub use local_access::exit;
+pub use local_access::join_pool;
+pub use local_access::leave_pool;
+pub use local_access::multicast;
 pub use local_access::rank;
-pub use local_access::schedule_timer_after;
 pub use local_access::send_random;
 pub use local_access::send_random_from_pool;
 pub use local_access::send_to;
+pub use local_access::spawn;
+pub use local_access::spawn_with;
+pub use local_access::{schedule_periodic_timer_after, schedule_timer_after};
+#[cfg(feature = "tracing")]
+pub(crate) use shared_access::home_pool;
 pub use shared_access::list_pool;
+pub(crate) use shared_access::receives;
 pub(crate) use shared_access::setup_shared_access;
 pub use tso::global_unique_id;
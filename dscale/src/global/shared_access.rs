@@ -3,7 +3,7 @@ use std::sync::{
     atomic::{AtomicPtr, Ordering},
 };
 
-use crate::{Rank, topology::Topology};
+use crate::{Rank, topology::Topology, typed::Receives};
 
 static SHARED_ACCESS: AtomicPtr<SharedAccess> = AtomicPtr::new(std::ptr::null_mut());
 
@@ -36,16 +36,72 @@ impl SharedAccess {
     }
 }
 
-/// Returns a slice of all process ranks in the named pool.
-pub fn list_pool(pool_name: &str) -> &'static [Rank] {
+/// Returns the ranks of all processes currently in the named pool.
+///
+/// Pools change as processes are spawned and exit, so the returned
+/// listing is a snapshot.
+pub fn list_pool(pool_name: &str) -> Arc<[Rank]> {
     shared().topology.list_pool(pool_name)
 }
 
-pub(crate) fn reset() {
-    let old = SHARED_ACCESS.swap(std::ptr::null_mut(), Ordering::Release);
-    if !old.is_null() {
-        unsafe {
-            drop(Box::from_raw(old));
-        }
+/// Name of the pool the process was added with.
+pub(crate) fn home_pool(rank: Rank) -> &'static str {
+    let topology = &shared().topology;
+    &topology.pool_names()[topology.home_pool(rank)]
+}
+
+/// What the process was built to receive.
+pub(crate) fn receives(rank: Rank) -> Receives {
+    shared().topology.receives(rank)
+}
+
+/// Fails the step of `caller` right away if the runner can not apply `api`,
+/// instead of once its result reaches the runner.
+pub(crate) fn assert_churn(api: &str, caller: Rank) {
+    assert!(
+        shared().topology.allows_churn(),
+        "P{caller} called {api}, which is not supported by the optimistic runner"
+    );
+}
+
+/// Hands out the rank of a process that `spawner` is about to spawn.
+pub(crate) fn reserve_rank(pool: &str, spawner: Rank) -> Rank {
+    let topology = &shared().topology;
+    let home = topology
+        .pool_names()
+        .iter()
+        .position(|name| name == pool)
+        .unwrap_or_else(|| panic!("Can not spawn into unknown pool {pool}"));
+    // A process spawned into the pool of its spawner reaches others no sooner than it
+    assert!(
+        home == topology.home_pool(spawner) || topology.can_spawn_into(home),
+        "P{spawner} can not spawn into pool {pool}: the parallel runner needs it \
+         to be allowed with SimulationBuilder::allow_spawn_into"
+    );
+    topology.reserve_rank()
+}
+
+/// Topology of a simulation that is not active, see [`crate::global::activate`].
+pub(crate) struct Parked(Option<Box<SharedAccess>>);
+
+impl Parked {
+    pub(crate) fn new(topology: Arc<Topology>) -> Self {
+        Self(Some(Box::new(SharedAccess::new(topology))))
     }
 }
+
+pub(crate) fn take() -> Parked {
+    let old = SHARED_ACCESS.swap(std::ptr::null_mut(), Ordering::Release);
+    // SAFETY: the pointer comes from Box::into_raw and is no longer shared
+    Parked((!old.is_null()).then(|| unsafe { Box::from_raw(old) }))
+}
+
+pub(crate) fn put(parked: Parked) {
+    let ptr = parked.0.map_or(std::ptr::null_mut(), Box::into_raw);
+    let old = SHARED_ACCESS.swap(ptr, Ordering::Release);
+    debug_assert!(old.is_null(), "Shared access of another simulation");
+}
+
+pub(crate) fn reset() {
+    drop(take());
+}
@@ -7,6 +7,15 @@ pub fn global_unique_id() -> usize {
     TSO.fetch_add(1, Ordering::Relaxed)
 }
 
+/// The ID [`global_unique_id`] returns next.
+pub(crate) fn next_id() -> usize {
+    TSO.load(Ordering::Relaxed)
+}
+
+pub(crate) fn set_next_id(id: usize) {
+    TSO.store(id, Ordering::Relaxed);
+}
+
 pub(crate) fn reset() {
     TSO.store(0, Ordering::Relaxed);
 }
@@ -1,8 +1,47 @@
 /// Logs a debug message prefixed with the current simulation time and process rank.
 /// Controlled by the `RUST_LOG` environment variable.
+///
+/// The message is also captured into the per-process log
+/// (see [`crate::SimulationBuilder::capture_process_logs`]), even in release builds.
+/// The arguments are only evaluated if the line is logged or captured.
 #[macro_export]
 macro_rules! debug_process {
     ($($arg:tt)+) => {
-        log::debug!("[Now: {} | P{}] {}", now(), rank(), format_args!($($arg)+));
+        if $crate::global::logs::enabled() || log::log_enabled!(log::Level::Debug) {
+            $crate::global::logs::emit(log::Level::Debug, module_path!(), format_args!($($arg)+));
+        }
     }
 }
+
+/// Logs an info message prefixed with the current simulation time and process rank,
+/// and captures it into the per-process log
+/// (see [`crate::SimulationBuilder::capture_process_logs`]).
+/// The arguments are only evaluated if the line is logged or captured.
+#[macro_export]
+macro_rules! log_process {
+    ($($arg:tt)+) => {
+        if $crate::global::logs::enabled() || log::log_enabled!(log::Level::Info) {
+            $crate::global::logs::emit(log::Level::Info, module_path!(), format_args!($($arg)+));
+        }
+    }
+}
+
+/// Asserts a condition about the given processes.
+/// On failure only their captured logs are printed, instead of those of
+/// every process as for other failed assertions.
+///
+/// ```ignore
+/// assert_ranks!([1, 3], leader_of(1) == leader_of(3), "leaders diverged");
+/// ```
+#[macro_export]
+macro_rules! assert_ranks {
+    ($ranks:expr, $cond:expr $(,)?) => {
+        $crate::assert_ranks!($ranks, $cond, "assertion failed: {}", stringify!($cond))
+    };
+    ($ranks:expr, $cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::global::logs::focus(&$ranks);
+            panic!($($arg)+);
+        }
+    };
+}
@@ -4,7 +4,8 @@ use std::{
 };
 
 /// The simulation time unit. One jiffy is the smallest discrete time step.
-#[derive(PartialEq, PartialOrd, Ord, Eq, Copy, Clone, Default)]
+#[derive(PartialEq, PartialOrd, Ord, Eq, Hash, Copy, Clone, Default)]
+#[cfg_attr(feature = "checkpoint", derive(serde::Serialize, serde::Deserialize))]
 pub struct Jiffies(pub usize);
 
 impl Add for Jiffies {
@@ -13,8 +13,14 @@
 
 mod actors;
 mod alloc;
+mod async_process;
+#[cfg(feature = "checkpoint")]
+pub mod checkpoint;
+mod context;
 mod destination;
 mod event;
+mod event_queue;
+mod factory;
 /// Global simulation state: clock, configuration, key-value store, and process interaction functions.
 pub mod global;
 /// Helper utilities for simulation processes.
@@ -22,12 +28,46 @@ pub mod helpers;
 mod jiffy;
 mod message;
 mod process_handle;
+mod property;
 mod random;
 mod runners;
 mod simulation_builder;
 mod simulation_flavor;
 mod step;
 mod topology;
+mod trace;
+#[cfg(feature = "tracing")]
+mod tracing_support;
+mod typed;
+mod view;
+
+pub use async_process::Async;
+pub use async_process::AsyncCtx;
+pub use async_process::AsyncProcess;
+pub use async_process::Recv;
+pub use async_process::RecvRequest;
+pub use async_process::Reply;
+pub use async_process::Request;
+pub use async_process::Sleep;
+pub use async_process::Timeout;
+
+pub use context::ContextProcess;
+pub use context::Ctx;
+pub use context::MembershipChange;
+pub use context::MockCtx;
+pub use context::Recipient;
+pub use context::Sent;
+pub use context::WithContext;
+
+pub use typed::Accepts;
+pub use typed::MessageSet;
+pub use typed::Typed;
+pub use typed::TypedProcess;
+pub use typed::broadcast_typed_within_pool;
+pub use typed::multicast_typed;
+pub use typed::send_typed;
+
+pub use factory::FactoryCtx;
 
 pub use message::Message;
 pub use message::MessagePtr;
@@ -35,22 +75,33 @@ pub use message::MessagePtr;
 pub use process_handle::ProcessHandle;
 pub use process_handle::Rank;
 
+pub use property::Property;
+
 pub use simulation_builder::SimulationBuilder;
 
 pub use global::broadcast;
 pub use global::broadcast_within_pool;
 pub use global::choose_from_pool;
+pub use global::create_pool;
+pub use global::exit;
 pub use global::global_unique_id;
+pub use global::join_pool;
+pub use global::leave_pool;
 pub use global::list_pool;
+pub use global::multicast;
 pub use global::now;
 pub use global::rank;
-pub use global::schedule_timer_after;
 pub use global::send_random;
 pub use global::send_random_from_pool;
 pub use global::send_to;
+pub use global::spawn;
+pub use global::spawn_with;
+pub use global::{schedule_periodic_timer_after, schedule_timer_after};
 
 pub use actors::network_actor::BandwidthConfig;
 
+pub use event_queue::EventQueue;
+
 pub use topology::GLOBAL_POOL;
 
 pub use random::Distributions;
@@ -59,4 +110,16 @@ pub use actors::timer_actor::TimerId;
 pub use jiffy::Jiffies;
 pub use runners::RunStatus;
 pub use runners::SimulationRunner;
+pub use runners::Violation;
+pub use runners::report::ParallelStats;
+pub use runners::report::RunReport;
+pub use runners::report::TrafficStats;
 pub use runners::threads::Threads;
+pub use trace::ChartFilter;
+pub use trace::Timeline;
+pub use trace::TraceEvent;
+pub use trace::TraceKind;
+pub use view::View;
+
+#[cfg(feature = "tracing")]
+pub use tracing_support::SimTime;
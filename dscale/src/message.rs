@@ -1,4 +1,9 @@
-use std::{any::Any, sync::Arc};
+use std::{
+    any::{Any, type_name},
+    borrow::Cow,
+    fmt::Debug,
+    sync::Arc,
+};
 
 /// Trait for values that can be sent between simulated processes.
 ///
@@ -11,6 +16,12 @@ pub trait Message: Any + Send + Sync {
     fn virtual_size(&self) -> usize {
         usize::default()
     }
+
+    /// Human-readable name shown in timelines and charts.
+    /// Defaults to the type name.
+    fn label(&self) -> Cow<'_, str> {
+        Cow::Borrowed(type_name::<Self>())
+    }
 }
 
 /// Reference-counted wrapper around a [`Message`].
@@ -25,13 +36,37 @@ impl MessagePtr {
         (&*self.0 as &dyn Any).downcast_ref::<T>()
     }
 
+    /// Attempts to downcast to a shared `T` without cloning the message.
+    pub fn downcast_arc<T: Message>(&self) -> Option<Arc<T>> {
+        let any: Arc<dyn Any + Send + Sync> = self.0.clone();
+        any.downcast::<T>().ok()
+    }
+
     /// Returns `true` if the contained message is of type `T`.
     pub fn is<T: 'static>(&self) -> bool {
         self.try_as_type::<T>().is_some()
     }
 
+    /// Whether both point to the same sent message. Every send creates a new one.
+    pub(crate) fn is_same(&self, other: &MessagePtr) -> bool {
+        std::ptr::addr_eq(Arc::as_ptr(&self.0), Arc::as_ptr(&other.0))
+    }
+
     /// Downcasts to `T`, panicking if the type does not match.
     pub fn as_type<T: 'static>(&self) -> &T {
-        self.try_as_type::<T>().expect("Failed as_type")
+        self.try_as_type::<T>().unwrap_or_else(|| {
+            panic!(
+                "Failed as_type: expected {}, got {}",
+                type_name::<T>(),
+                self.0.label()
+            )
+        })
+    }
+}
+
+/// Shows the [`Message::label`] of the contained message.
+impl Debug for MessagePtr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.label())
     }
 }
@@ -1,7 +1,10 @@
+use std::any::{Any, TypeId};
+
 use crate::{MessagePtr, actors::timer_actor::TimerId};
 
 /// Unique identifier for a process within the simulation. Ranks are assigned
-/// sequentially starting from 0 in the order pools are added.
+/// sequentially starting from 0 in the order pools are added. Processes spawned
+/// at runtime get the next ranks; ranks of exited processes are not reused.
 pub type Rank = usize;
 
 /// Core trait for defining process behavior in the simulation.
@@ -9,7 +12,9 @@ pub type Rank = usize;
 /// Each process reacts to three kinds of events: startup, incoming messages,
 /// and timer firings. Inside any handler you may call the global interaction
 /// functions ([`crate::send_to`], [`crate::broadcast`], [`crate::schedule_timer_after`], etc.).
-pub trait ProcessHandle {
+///
+/// Process state can be inspected between steps through a [`crate::View`].
+pub trait ProcessHandle: Any {
     /// Called once when the simulation starts, before any messages are delivered.
     fn on_start(&mut self);
 
@@ -18,6 +23,63 @@ pub trait ProcessHandle {
 
     /// Called when a previously scheduled timer fires.
     fn on_timer(&mut self, id: TimerId);
+
+    /// Type and name of the [`crate::MessageSet`] the process accepts, if it is
+    /// a [`crate::Typed`] one. Typed sends check it in debug builds.
+    #[doc(hidden)]
+    fn message_set() -> Option<(TypeId, &'static str)>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Copy of the current state. The optimistic runner (see
+    /// [`crate::SimulationBuilder::optimistic`]) rolls the process back to it if
+    /// a step turns out to have run too early, and [`crate::SimulationRunner::fork`]
+    /// starts the copy of the process from it. Only needed by those: `Clone`
+    /// processes implement it as `Some(Box::new(self.clone()))`.
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        None
+    }
+
+    /// State of the process for a checkpoint (see [`crate::SimulationRunner::checkpoint`]).
+    /// Processes that derive `serde::Serialize` implement it as
+    /// `Some(dscale::checkpoint::encode(self))`.
+    #[cfg(feature = "checkpoint")]
+    fn save(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Takes the state written by [`ProcessHandle::save`] when a checkpoint is restored,
+    /// usually as `*self = dscale::checkpoint::decode(state)`.
+    #[cfg(feature = "checkpoint")]
+    fn load(&mut self, state: &[u8]) {
+        let _ = state;
+        panic!("Process can not be restored: implement ProcessHandle::load")
+    }
+}
+
+/// Placeholder for a rank whose process exited or has not joined yet.
+/// Never receives steps.
+pub(crate) struct Vacant;
+
+impl ProcessHandle for Vacant {
+    fn on_start(&mut self) {
+        unreachable!("Vacant rank started")
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {
+        unreachable!("Vacant rank received a message")
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        unreachable!("Vacant rank received a timer")
+    }
+
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        Some(Box::new(Vacant))
+    }
 }
 
 impl<T: ProcessHandle + ?Sized> ProcessHandle for Box<T> {
@@ -32,4 +94,18 @@ impl<T: ProcessHandle + ?Sized> ProcessHandle for Box<T> {
     fn on_timer(&mut self, id: TimerId) {
         (**self).on_timer(id)
     }
+
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        (**self).snapshot()
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn save(&self) -> Option<Vec<u8>> {
+        (**self).save()
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn load(&mut self, state: &[u8]) {
+        (**self).load(state)
+    }
 }
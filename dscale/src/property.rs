@@ -0,0 +1,153 @@
+use std::rc::Rc;
+
+use crate::{jiffy::Jiffies, runners::monitor::Predicate, view::View};
+
+/// Temporal property over simulated time, registered with
+/// [`crate::SimulationBuilder::property`].
+///
+/// Properties are observed at the same check points as invariants. A violation
+/// stops the run with [`crate::RunStatus::LivenessViolated`]. Obligations still
+/// open when the simulation runs out of events can never be met and are reported
+/// as violations too.
+#[derive(Clone)]
+pub struct Property {
+    predicate: Predicate,
+    kind: Kind,
+}
+
+#[derive(Clone)]
+enum Kind {
+    Eventually {
+        deadline: Jiffies,
+        satisfied: bool,
+    },
+    AlwaysEventually {
+        period: Jiffies,
+        last_seen: Jiffies,
+        holding: bool,
+    },
+    Within {
+        trigger: Predicate,
+        deadline: Jiffies,
+        triggered: bool,
+        armed: Option<Jiffies>,
+    },
+}
+
+impl Property {
+    /// `predicate` must hold at some point no later than `deadline`.
+    pub fn eventually(deadline: Jiffies, predicate: impl Fn(&View) -> bool + 'static) -> Self {
+        Self {
+            predicate: Rc::new(predicate),
+            kind: Kind::Eventually {
+                deadline,
+                satisfied: false,
+            },
+        }
+    }
+
+    /// `predicate` must hold at least once in every window of `period` jiffies.
+    pub fn always_eventually(period: Jiffies, predicate: impl Fn(&View) -> bool + 'static) -> Self {
+        Self {
+            predicate: Rc::new(predicate),
+            kind: Kind::AlwaysEventually {
+                period,
+                last_seen: Jiffies(0),
+                holding: false,
+            },
+        }
+    }
+
+    /// Each time `trigger` starts holding, `predicate` must hold within `deadline` jiffies.
+    ///
+    /// If `trigger` stops holding before that, the obligation is dropped and
+    /// re-armed the next time it starts holding. For example "within 50_000
+    /// jiffies after the last fault heals, every request is acknowledged".
+    pub fn within_after(
+        trigger: impl Fn(&View) -> bool + 'static,
+        deadline: Jiffies,
+        predicate: impl Fn(&View) -> bool + 'static,
+    ) -> Self {
+        Self {
+            predicate: Rc::new(predicate),
+            kind: Kind::Within {
+                trigger: Rc::new(trigger),
+                deadline,
+                triggered: false,
+                armed: None,
+            },
+        }
+    }
+
+    /// Observes the current state. Returns `false` if the property is violated.
+    pub(crate) fn observe(&mut self, now: Jiffies, view: &View) -> bool {
+        match &mut self.kind {
+            Kind::Eventually {
+                deadline,
+                satisfied,
+            } => {
+                if !*satisfied && now <= *deadline {
+                    *satisfied = (self.predicate)(view);
+                }
+                *satisfied || now <= *deadline
+            }
+            Kind::AlwaysEventually {
+                period,
+                last_seen,
+                holding,
+            } => {
+                // State only changes in steps, so it kept holding up to now
+                if *holding {
+                    *last_seen = now;
+                }
+                *holding = (self.predicate)(view);
+                if *holding {
+                    *last_seen = now;
+                }
+                now - *last_seen <= *period
+            }
+            Kind::Within {
+                trigger,
+                deadline,
+                triggered,
+                armed,
+            } => {
+                let active = trigger(view);
+                if active && !*triggered {
+                    *armed = Some(now + *deadline);
+                }
+                if !active {
+                    *armed = None;
+                }
+                *triggered = active;
+
+                match *armed {
+                    Some(until) if now > until => false,
+                    Some(_) => {
+                        if (self.predicate)(view) {
+                            *armed = None;
+                        }
+                        true
+                    }
+                    None => true,
+                }
+            }
+        }
+    }
+
+    /// Returns `false` if an open obligation can not be met by `horizon`.
+    pub(crate) fn expire(&self, horizon: Jiffies) -> bool {
+        match &self.kind {
+            Kind::Eventually {
+                deadline,
+                satisfied,
+            } => *satisfied || horizon <= *deadline,
+            Kind::AlwaysEventually {
+                period,
+                last_seen,
+                holding,
+            } => *holding || horizon - *last_seen <= *period,
+            Kind::Within { armed, .. } => armed.is_none_or(|until| horizon <= until),
+        }
+    }
+}
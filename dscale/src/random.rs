@@ -1,7 +1,7 @@
-use rand::{Rng, SeedableRng, distr::Uniform, seq::IndexedRandom};
+use rand::{Rng, RngCore, SeedableRng, distr::Uniform, seq::IndexedRandom};
 use rand_distr::{Bernoulli, Normal};
 
-use crate::Jiffies;
+use crate::{Jiffies, Rank};
 
 pub(crate) type Seed = u64;
 
@@ -25,31 +25,114 @@ pub enum Distributions {
 
 impl Distributions {
     pub(super) fn safe_window(&self) -> Jiffies {
-        match self.clone() {
+        match *self {
             Self::Uniform(a, _) => a,
             Self::Bernoulli(_, _) => Jiffies(1),
             Self::Normal { low, .. } => low,
         }
     }
+
+    pub(super) fn upper_bound(&self) -> Jiffies {
+        match *self {
+            Self::Uniform(_, b) => b,
+            Self::Bernoulli(_, value) => value,
+            Self::Normal { high, .. } => high,
+        }
+    }
+}
+
+/// Seed of the `n`th fork of a simulation with `seed`, see
+/// [`crate::SimulationRunner::fork`].
+pub(crate) fn derive_seed(seed: Seed, n: u64) -> Seed {
+    Xoshiro::seed_from_u64(seed ^ n.wrapping_mul(0x9e37_79b9_7f4a_7c15)).random()
+}
+
+/// Generators of process `rank`: the one of its random choices, e.g. in
+/// [`crate::choose_from_pool`], and the one of the latencies of its messages.
+/// Each process draws from its own, so the draws do not depend on the order in
+/// which processes run, and roll back with the process.
+pub(crate) fn process_generators(seed: Seed, rank: Rank) -> (Randomizer, Randomizer) {
+    let mut rnd = Xoshiro::seed_from_u64(seed ^ (rank as u64).wrapping_mul(0xd1b5_4a32_d192_ed03));
+    (Randomizer::new(rnd.random()), Randomizer::new(rnd.random()))
 }
 
-#[derive(Debug)]
+/// Xoshiro256++, the algorithm behind [`rand::rngs::SmallRng`] on 64-bit
+/// platforms, with the same output for the same seed. Unlike `SmallRng` its
+/// state can be saved, so checkpoints hold the generators as they are.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "checkpoint", derive(serde::Serialize, serde::Deserialize))]
+struct Xoshiro {
+    s: [u64; 4],
+}
+
+impl SeedableRng for Xoshiro {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: [u8; 32]) -> Self {
+        let mut s = [0; 4];
+        for (word, bytes) in s.iter_mut().zip(seed.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        if s == [0; 4] {
+            return Self::seed_from_u64(0);
+        }
+        Self { s }
+    }
+
+    // SplitMix64, as in `rand`
+    fn seed_from_u64(mut state: u64) -> Self {
+        let mut s = [0; 4];
+        for word in s.iter_mut() {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+        Self { s }
+    }
+}
+
+impl RngCore for Xoshiro {
+    fn next_u32(&mut self) -> u32 {
+        // The lowest bits are weaker
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        rand::rand_core::impls::fill_bytes_via_next(self, dst)
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "checkpoint", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Randomizer {
-    rnd: rand::rngs::SmallRng,
+    rnd: Xoshiro,
 }
 
 impl Default for Randomizer {
     fn default() -> Self {
-        Self {
-            rnd: rand::rngs::SmallRng::seed_from_u64(0),
-        }
+        Self::new(0)
     }
 }
 
 impl Randomizer {
     pub(crate) fn new(seed: Seed) -> Self {
         Self {
-            rnd: rand::rngs::SmallRng::seed_from_u64(seed),
+            rnd: Xoshiro::seed_from_u64(seed),
         }
     }
 
@@ -81,7 +164,7 @@ impl Randomizer {
         }
     }
 
-    pub(crate) fn choose_from_slice<'a, T: Copy>(&mut self, from: &[T]) -> T {
+    pub(crate) fn choose_from_slice<T: Copy>(&mut self, from: &[T]) -> T {
         from.choose(&mut self.rnd)
             .copied()
             .expect("Chose from empty slice")
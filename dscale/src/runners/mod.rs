@@ -1,13 +1,32 @@
+pub(crate) mod monitor;
+pub(crate) mod optimistic;
 mod progress;
+pub(crate) mod report;
 pub(crate) mod scalable;
 pub(crate) mod simple;
 pub(super) mod task;
 pub(crate) mod threads;
 pub(crate) mod workers;
 
-use crate::jiffy::Jiffies;
+use crate::{
+    Rank, jiffy::Jiffies, random::Seed, runners::report::RunReport, trace::Timeline, view::View,
+};
 
-/// Outcome of a simulation run.
+/// Report of a failed invariant or temporal property registered on the
+/// [`crate::SimulationBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    /// Name the invariant or property was registered with.
+    pub property: &'static str,
+    /// Simulation time at which the violation was observed.
+    pub time: Jiffies,
+    /// Total number of steps executed since the simulation started.
+    pub step: usize,
+    /// Seed of the simulation, to reproduce the run.
+    pub seed: Seed,
+}
+
+/// Why a simulation run stopped.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RunStatus {
     /// Ran all requested steps (for `run_steps`) or the full sub-budget (for `run_sub_budget`).
@@ -16,33 +35,142 @@ pub enum RunStatus {
     BudgetExhausted { steps: usize },
     /// No more events to process — the simulation has quiesced.
     NoMoreEvents { steps: usize },
+    /// An invariant did not hold. The simulation is halted and every
+    /// subsequent run returns the same violation.
+    InvariantViolated { steps: usize, violation: Violation },
+    /// A temporal property did not hold. The simulation is halted and every
+    /// subsequent run returns the same violation.
+    LivenessViolated { steps: usize, violation: Violation },
+    /// The predicate passed to `run_until` holds.
+    PredicateMet { steps: usize, time: Jiffies },
+    /// No message was in flight for the grace period passed to `run_until_quiescent`.
+    Quiescent { steps: usize, time: Jiffies },
 }
 
 impl RunStatus {
-    /// Number of steps that were actually executed.
+    /// Number of steps that were actually executed, counting the `on_start`
+    /// of every process in the first run, as [`Violation::step`] does.
     pub fn steps(&self) -> usize {
         match *self {
             RunStatus::Completed { steps }
             | RunStatus::BudgetExhausted { steps }
-            | RunStatus::NoMoreEvents { steps } => steps,
+            | RunStatus::NoMoreEvents { steps }
+            | RunStatus::InvariantViolated { steps, .. }
+            | RunStatus::LivenessViolated { steps, .. }
+            | RunStatus::PredicateMet { steps, .. }
+            | RunStatus::Quiescent { steps, .. } => steps,
+        }
+    }
+
+    pub(crate) fn with_steps(mut self, total: usize) -> Self {
+        match &mut self {
+            RunStatus::Completed { steps }
+            | RunStatus::BudgetExhausted { steps }
+            | RunStatus::NoMoreEvents { steps }
+            | RunStatus::InvariantViolated { steps, .. }
+            | RunStatus::LivenessViolated { steps, .. }
+            | RunStatus::PredicateMet { steps, .. }
+            | RunStatus::Quiescent { steps, .. } => *steps = total,
         }
+        self
     }
 }
 
 /// Execution engine returned by [`crate::SimulationBuilder::build`].
+///
+/// Every run method returns a [`RunReport`] with the [`RunStatus`] and what
+/// happened during the run.
 pub trait SimulationRunner {
     /// Runs the simulation until the total time budget is exhausted
     /// or no more events remain.
-    fn run_full_budget(&mut self) -> RunStatus;
+    ///
+    /// Temporal properties are settled whenever a run ends this way.
+    fn run_full_budget(&mut self) -> RunReport;
 
-    /// Runs up to `k` steps and returns the outcome.
+    /// Runs up to `k` steps.
     ///
     /// The simulation can be resumed by calling this method again.
-    fn run_steps(&mut self, k: usize) -> RunStatus;
+    fn run_steps(&mut self, k: usize) -> RunReport;
 
     /// Runs the simulation for at most `sub_budget` additional time.
     ///
     /// Stops early if the total time budget is hit or no more events remain.
     /// The simulation can be resumed by calling any run method again.
-    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunStatus;
+    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunReport;
+
+    /// Runs the simulation until `predicate` holds over the process states.
+    ///
+    /// The predicate is checked before the first step and after every step
+    /// (after every window for the parallel runner). Stops early if the total
+    /// time budget is hit or no more events remain.
+    fn run_until(&mut self, predicate: &mut dyn FnMut(&View) -> bool) -> RunReport;
+
+    /// Runs the simulation until no message has been in flight for `grace` jiffies
+    /// and no one-shot timer is pending.
+    ///
+    /// Timers scheduled with [`crate::schedule_periodic_timer_after`] are background
+    /// activity: they keep firing during the grace period, but only the messages
+    /// they send reset it. Other timers, like election or retry timeouts, have to
+    /// fire first, also if their handler schedules them again.
+    fn run_until_quiescent(&mut self, grace: Jiffies) -> RunReport;
+
+    /// Handler invocations recorded so far, if enabled with
+    /// [`crate::SimulationBuilder::record_timeline`].
+    fn timeline(&self) -> Option<&Timeline>;
+
+    /// Simulates a restart of the process at the current time: its pending
+    /// timers are dropped, it is rebuilt by the factory of its pool and started again.
+    ///
+    /// Messages already in flight are delivered to the new incarnation.
+    fn restart(&mut self, rank: Rank);
+
+    /// Copy of the simulation at the current time that runs independently of it,
+    /// e.g. to explore several futures from the same prefix.
+    ///
+    /// Processes are copied with [`crate::ProcessHandle::snapshot`]. Every fork gets
+    /// its own seed, derived from the seed of the simulation and the number of forks
+    /// taken so far: its latencies, random choices and [`crate::global::configuration::seed`]
+    /// differ from those of the simulation, which continues as if it was not forked.
+    /// Metrics, captured logs, the timeline and the state of invariants and
+    /// properties are copied too.
+    ///
+    /// Entries of the [`crate::global::kv`] store are copied too, their types must be
+    /// registered with [`crate::SimulationBuilder::fork_value`] unless they are
+    /// integers, floats, `bool` or `String`. Entries of other types are left out
+    /// of the fork with a warning. Global accessors used outside of
+    /// handlers, like [`crate::global::kv`] and [`crate::now`], see the simulation
+    /// that was built or run last: forking does not switch them to the fork.
+    /// Only the single-threaded runner supports forks.
+    fn fork(&mut self) -> Box<dyn SimulationRunner> {
+        panic!("Forks are only supported by the single-threaded runner")
+    }
+
+    /// Saves the state of the simulation to bytes, to [`SimulationRunner::restore`]
+    /// it later. See [`crate::checkpoint`] for what it holds.
+    ///
+    /// The checkpoint holds the states of the random generators, so the simulation
+    /// continues the same after it is restored as it would have without the checkpoint.
+    /// Metrics, captured logs, the timeline and the state of invariants and
+    /// properties are not saved. Panics if processes spawned or exited, or pools
+    /// changed. Only the single-threaded runner supports checkpoints.
+    #[cfg(feature = "checkpoint")]
+    fn checkpoint(&self) -> Vec<u8> {
+        panic!("Checkpoints are only supported by the single-threaded runner")
+    }
+
+    /// Resumes from a [`SimulationRunner::checkpoint`] of a simulation built
+    /// with the same pools and processes, discarding the current state.
+    #[cfg(feature = "checkpoint")]
+    fn restore(&mut self, checkpoint: &[u8]) {
+        let _ = checkpoint;
+        panic!("Checkpoints are only supported by the single-threaded runner")
+    }
+}
+
+/// What a run is heading for, besides the total time budget.
+pub(crate) enum Goal<'a> {
+    Deadline(Jiffies),
+    Steps(usize),
+    Predicate(&'a mut dyn FnMut(&View) -> bool),
+    Quiescence(Jiffies),
 }
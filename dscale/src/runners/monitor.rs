@@ -0,0 +1,131 @@
+use std::rc::Rc;
+
+use crate::{
+    jiffy::Jiffies,
+    now,
+    property::Property,
+    random::Seed,
+    runners::{RunStatus, Violation},
+    view::View,
+};
+
+// Shared by forks of the simulation
+pub(crate) type Predicate = Rc<dyn Fn(&View) -> bool>;
+
+#[derive(Clone, Copy)]
+enum Halt {
+    Invariant(Violation),
+    Liveness(Violation),
+}
+
+/// Evaluates user invariants and temporal properties over all process states
+/// as steps are executed.
+#[derive(Clone)]
+pub(crate) struct Monitor {
+    invariants: Vec<(&'static str, Predicate)>,
+    properties: Vec<(&'static str, Property)>,
+    period: usize,
+    seed: Seed,
+    steps: usize,
+    pending: bool,
+    halt: Option<Halt>,
+}
+
+impl Monitor {
+    pub(crate) fn new(
+        invariants: Vec<(&'static str, Predicate)>,
+        properties: Vec<(&'static str, Property)>,
+        period: usize,
+        seed: Seed,
+    ) -> Self {
+        assert!(period > 0, "Check period should be greater than zero");
+        Self {
+            invariants,
+            properties,
+            period,
+            seed,
+            steps: 0,
+            pending: false,
+            halt: None,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.invariants.is_empty() || !self.properties.is_empty()
+    }
+
+    /// Accounts one executed step and marks a check as pending once the period elapses.
+    pub(crate) fn step_done(&mut self) {
+        self.steps += 1;
+        if self.enabled() && self.steps.is_multiple_of(self.period) {
+            self.pending = true;
+        }
+    }
+
+    pub(crate) fn check(&mut self, view: &View) {
+        self.pending = false;
+        if self.halt.is_some() {
+            return;
+        }
+        if let Some((name, _)) = self.invariants.iter().find(|(_, check)| !check(view)) {
+            self.halt = Some(Halt::Invariant(self.violation("Invariant", name)));
+            return;
+        }
+        let now = now();
+        let violated = self
+            .properties
+            .iter_mut()
+            .find_map(|(name, property)| (!property.observe(now, view)).then_some(*name));
+        if let Some(name) = violated {
+            self.halt = Some(Halt::Liveness(self.violation("Property", name)));
+        }
+    }
+
+    /// Final check once a run stops. Obligations that can not be met before
+    /// `horizon` are reported as violated.
+    pub(crate) fn settle(&mut self, view: &View, horizon: Jiffies) {
+        if !self.enabled() {
+            return;
+        }
+        self.check(view);
+        if self.halt.is_some() {
+            return;
+        }
+        if let Some((name, _)) = self
+            .properties
+            .iter()
+            .find(|(_, property)| !property.expire(horizon))
+        {
+            self.halt = Some(Halt::Liveness(self.violation("Property", name)));
+        }
+    }
+
+    fn violation(&self, kind: &str, name: &'static str) -> Violation {
+        let violation = Violation {
+            property: name,
+            time: now(),
+            step: self.steps,
+            seed: self.seed,
+        };
+        log::error!(
+            "{kind} '{}' violated at {} (step {}, seed {})",
+            violation.property,
+            violation.time,
+            violation.step,
+            violation.seed
+        );
+        violation
+    }
+
+    pub(crate) fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Status to report if the simulation is halted by a violation.
+    pub(crate) fn halted(&self, steps: usize) -> Option<RunStatus> {
+        self.halt.map(|halt| match halt {
+            Halt::Invariant(violation) => RunStatus::InvariantViolated { steps, violation },
+            Halt::Liveness(violation) => RunStatus::LivenessViolated { steps, violation },
+        })
+    }
+}
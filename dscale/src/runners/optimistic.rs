@@ -0,0 +1,652 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    mem,
+};
+
+use crossbeam_channel::RecvError;
+
+use crate::{
+    MessagePtr, Rank, TimerId,
+    actors::{Actors, SimulationActor},
+    destination::Destination,
+    event::Event,
+    global::{self, clock::set_step_clock, metrics::Sampler},
+    global_unique_id,
+    jiffy::Jiffies,
+    now,
+    random::Randomizer,
+    runners::{
+        Goal, RunStatus, SimulationRunner,
+        monitor::Monitor,
+        progress::Bar,
+        report::{RunRecorder, RunReport},
+        task::{Snapshot, TaskId, TaskResult},
+        workers::Workers,
+    },
+    step::Step,
+    trace::Timeline,
+    view::View,
+};
+
+/// Step that ran, but may still be rolled back.
+struct Processed {
+    id: TaskId,
+    step: Step,
+    // State of the process before the step
+    before: Snapshot,
+    // Generator of the latencies of its messages before the step
+    latencies: Randomizer,
+    timers: Vec<TimerId>,
+    sent: Vec<Sent>,
+}
+
+/// Message sent by a step, with the targets it was accounted for.
+struct Sent {
+    message: MessagePtr,
+    alive: Vec<Rank>,
+    dead: Vec<Rank>,
+}
+
+#[derive(Default)]
+struct Lane {
+    // Steps taken from the actors, run in order
+    held: BTreeMap<TaskId, Step>,
+    executing: Option<(TaskId, Step)>,
+    // Steps that ran and are not committed yet, in the order they ran
+    history: VecDeque<Processed>,
+    // The executing step was rolled back: its result is thrown away...
+    stale: bool,
+    // ...and it is not run again
+    cancelled: bool,
+    // State to restore once the executing step finishes
+    restore: Option<Snapshot>,
+}
+
+impl Lane {
+    /// Time of the latest step that ran or runs.
+    fn latest(&self) -> Option<Jiffies> {
+        self.executing
+            .as_ref()
+            .map(|(id, _)| id.0)
+            .or_else(|| self.history.back().map(|done| done.id.0))
+    }
+}
+
+/// Time Warp runner. Every process runs its next step as soon as it is idle,
+/// as long as the step is within the window past the GVT (the time of the
+/// earliest unfinished step). A step reaching a process that already ran later
+/// ones rolls the process back, taking back the messages and timers of the
+/// undone steps, which may roll back their targets in turn.
+/// Steps up to the GVT can not be rolled back anymore and are committed.
+pub(crate) struct OptimisticRunner {
+    // Id of the simulation in `global`, entered by the builder
+    simulation: usize,
+    actors: Actors,
+    time_budget: Jiffies,
+    workers: Workers,
+    progress_bar: Bar,
+    window: Jiffies,
+    lanes: Vec<Lane>,
+    // Earliest held step of every process
+    heads: BTreeSet<(TaskId, Rank)>,
+    executing: BTreeSet<TaskId>,
+    in_flight: usize,
+    // Every step in a history, to commit them in time order
+    uncommitted: BTreeMap<TaskId, Rank>,
+    monitor: Monitor,
+    sampler: Sampler,
+    timeline: Option<Timeline>,
+    last_delivery: Jiffies,
+    started: bool,
+}
+
+impl OptimisticRunner {
+    pub(crate) fn new(
+        actors: Actors,
+        time_budget: Jiffies,
+        mut workers: Workers,
+        window: Jiffies,
+        monitor: Monitor,
+        sampler: Sampler,
+        timeline: Option<Timeline>,
+    ) -> Self {
+        workers.keep_snapshots();
+        let num_procs = workers.num_procs();
+        Self {
+            simulation: global::active(),
+            actors,
+            time_budget,
+            workers,
+            progress_bar: Bar::new(time_budget),
+            window,
+            lanes: (0..num_procs).map(|_| Lane::default()).collect(),
+            heads: BTreeSet::new(),
+            executing: BTreeSet::new(),
+            in_flight: 0,
+            uncommitted: BTreeMap::new(),
+            monitor,
+            sampler,
+            timeline,
+            last_delivery: Jiffies(0),
+            started: false,
+        }
+    }
+
+    fn ensure_started(&mut self) {
+        if !self.started {
+            self.started = true;
+            for rank in 0..self.workers.num_procs() {
+                self.hold(
+                    rank,
+                    (global::now(), global_unique_id()),
+                    Step::Start { rank },
+                );
+            }
+        }
+    }
+}
+
+impl Drop for OptimisticRunner {
+    fn drop(&mut self) {
+        global::leave(self.simulation);
+    }
+}
+
+impl SimulationRunner for OptimisticRunner {
+    fn run_full_budget(&mut self) -> RunReport {
+        self.run(Goal::Deadline(self.time_budget))
+    }
+
+    fn run_steps(&mut self, k: usize) -> RunReport {
+        self.run(Goal::Steps(k))
+    }
+
+    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunReport {
+        self.run(Goal::Deadline(now() + sub_budget))
+    }
+
+    fn run_until(&mut self, predicate: &mut dyn FnMut(&View) -> bool) -> RunReport {
+        self.run(Goal::Predicate(predicate))
+    }
+
+    fn run_until_quiescent(&mut self, grace: Jiffies) -> RunReport {
+        self.run(Goal::Quiescence(grace))
+    }
+
+    fn timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
+    }
+
+    fn restart(&mut self, rank: Rank) {
+        global::activate(self.simulation);
+        assert!(self.actors.topology.is_alive(rank), "P{rank} has exited");
+        // Runs end with every step that is not committed rolled back and held
+        self.ensure_started();
+        self.actors.timers.discard_of(rank);
+        self.with_held(rank, |held| {
+            held.retain(|_, step| !matches!(step, Step::TimerStep { .. }))
+        });
+        self.workers.rebuild(rank);
+        self.hold(
+            rank,
+            (global::now(), global_unique_id()),
+            Step::Start { rank },
+        );
+    }
+}
+
+impl OptimisticRunner {
+    fn run(&mut self, goal: Goal) -> RunReport {
+        global::activate(self.simulation);
+        let recorder = RunRecorder::start(&mut self.actors, Some(&self.workers));
+        self.ensure_started();
+        let status = self.coordinate(goal);
+        let status = self.join_workers(status);
+        if let RunStatus::BudgetExhausted { .. } = status {
+            self.progress_bar.finish();
+        }
+        let status = self.finish(status);
+        recorder.finish(status, &mut self.actors, Some(&self.workers))
+    }
+
+    /// Coordinate the worker pool until the goal is reached, the time budget
+    /// is exhausted or no more events remain. Only committed steps count.
+    ///
+    /// Checks that need a consistent view over all processes (invariants,
+    /// predicates, quiescence) roll every process back to the GVT first.
+    fn coordinate(&mut self, mut goal: Goal) -> RunStatus {
+        let mut steps: usize = 0;
+        if let Some(status) = self.monitor.halted(steps) {
+            return status;
+        }
+
+        let deadline = match goal {
+            Goal::Deadline(deadline) => deadline.min(self.time_budget),
+            _ => self.time_budget,
+        };
+        let needs_barrier = matches!(goal, Goal::Predicate(_) | Goal::Quiescence(_));
+        self.last_delivery = global::now();
+
+        loop {
+            steps += self.settle();
+            let time = global::now();
+            if self.in_flight == 0 {
+                if needs_barrier || self.monitor.pending() {
+                    self.roll_back_all();
+                }
+                if self.monitor.pending() {
+                    self.check_invariants();
+                    if let Some(status) = self.monitor.halted(steps) {
+                        return status;
+                    }
+                }
+                match &mut goal {
+                    Goal::Predicate(predicate) => {
+                        let view = View::new(self.workers.all());
+                        if predicate(&view) {
+                            return RunStatus::PredicateMet { steps, time };
+                        }
+                    }
+                    Goal::Quiescence(grace) if self.quiescent(*grace) => {
+                        return RunStatus::Quiescent { steps, time };
+                    }
+                    _ => {}
+                }
+                if self.gvt().is_none() {
+                    return RunStatus::NoMoreEvents { steps };
+                }
+            }
+
+            if let Goal::Steps(k) = goal
+                && steps >= k
+            {
+                return RunStatus::Completed { steps };
+            }
+            if time >= deadline {
+                if time >= self.time_budget {
+                    return RunStatus::BudgetExhausted { steps };
+                }
+                return RunStatus::Completed { steps };
+            }
+
+            // Keep in-flight steps from racing ahead of a pending consistent check
+            if self.in_flight == 0 || !(needs_barrier || self.monitor.pending()) {
+                self.advance(deadline);
+            }
+            if self.in_flight == 0 {
+                continue;
+            }
+
+            // Block until at least one result arrives
+            match self.workers.next_result() {
+                Ok(first) => {
+                    self.ingest(first);
+
+                    // Drain all immediately available results
+                    while let Some(result) = self.workers.try_next_result() {
+                        self.ingest(result);
+                    }
+                }
+                Err(RecvError) => {
+                    unreachable!("unexpected worker disconnection")
+                }
+            }
+        }
+    }
+
+    fn ingest(&mut self, task_result: TaskResult) {
+        let rank = task_result.rank;
+        self.executing.remove(&task_result.id);
+        self.in_flight -= 1;
+        let before = task_result.before.expect("Step without snapshot");
+        let lane = &mut self.lanes[rank];
+        let (id, step) = lane.executing.take().expect("Result of no step");
+        if mem::take(&mut lane.stale) {
+            let state = lane.restore.take().unwrap_or(before);
+            self.workers.restore(rank, state);
+            if !mem::take(&mut lane.cancelled) {
+                self.hold(rank, id, step);
+            }
+            return;
+        }
+
+        // Whatever the step caused happens at its own time, not at the global one
+        set_step_clock(Some(id.0));
+        let mut done = Processed {
+            id,
+            step,
+            before,
+            latencies: self.actors.network.save_generator(rank),
+            timers: Vec::new(),
+            sent: Vec::new(),
+        };
+        for event in task_result.events {
+            match event {
+                Event::TimerEvent { id, .. } => {
+                    done.timers.push(id);
+                    self.actors.timers.submit(event);
+                }
+                Event::NetworkEvent {
+                    ref destination,
+                    ref message,
+                    ..
+                } => {
+                    let (alive, dead) = self.targets(destination);
+                    done.sent.push(Sent {
+                        message: message.clone(),
+                        alive,
+                        dead,
+                    });
+                    self.actors.network.submit(event);
+                }
+                _ => panic!(
+                    "Spawning, exiting and changing pools are not supported by the optimistic runner"
+                ),
+            }
+        }
+        set_step_clock(None);
+        self.uncommitted.insert(id, rank);
+        self.lanes[rank].history.push_back(done);
+    }
+
+    /// Targets of a message, split by whether they are alive, as the network accounts them.
+    fn targets(&self, destination: &Destination) -> (Vec<Rank>, Vec<Rank>) {
+        let members = self.actors.topology.members();
+        let targets = match destination {
+            Destination::BroadcastWithinPool(pool) => members.pool(pool),
+            Destination::Target(rank) => std::slice::from_ref(rank),
+            Destination::Multicast(ranks) => &ranks[..],
+        };
+        targets
+            .iter()
+            .partition(|&&target| members.is_alive(target))
+    }
+
+    fn check_invariants(&mut self) {
+        let view = View::new(self.workers.all());
+        self.monitor.check(&view);
+    }
+
+    /// No message or one-shot timer is pending and nothing but periodic timers
+    /// happens within the grace period.
+    fn quiescent(&self, grace: Jiffies) -> bool {
+        let held_messages = self
+            .lanes
+            .iter()
+            .flat_map(|lane| lane.held.values())
+            .any(|step| matches!(step, Step::NetworkStep { .. }));
+        self.actors.idle()
+            && !held_messages
+            && self
+                .gvt()
+                .is_none_or(|next| next > self.last_delivery + grace)
+    }
+
+    /// Settles temporal properties once the simulation can not go any further.
+    fn finish(&mut self, status: RunStatus) -> RunStatus {
+        let horizon = match status {
+            RunStatus::BudgetExhausted { .. } => global::now(),
+            RunStatus::NoMoreEvents { .. } => Jiffies(usize::MAX),
+            _ => return status,
+        };
+        let view = View::new(self.workers.all());
+        self.monitor.settle(&view, horizon);
+        self.monitor.halted(status.steps()).unwrap_or(status)
+    }
+
+    /// Global virtual time: every step that is not committed, and everything
+    /// it may cause, happens at or after it. `None` if nothing is left.
+    fn gvt(&self) -> Option<Jiffies> {
+        [
+            self.heads.first().map(|(id, _)| id.0),
+            self.executing.first().map(|id| id.0),
+            self.uncommitted.first_key_value().map(|(id, _)| id.0),
+            self.actors.peek_next_step(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Commits every step up to the GVT and moves the clock there.
+    /// Returns the number of committed steps.
+    fn settle(&mut self) -> usize {
+        // Steps that ran are final up to the earliest step that may still arrive
+        let horizon = [
+            self.heads.first().map(|(id, _)| id.0),
+            self.executing.first().map(|id| id.0),
+            self.actors.peek_next_step(),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let mut committed = 0;
+        while let Some((&id, &rank)) = self.uncommitted.first_key_value()
+            && horizon.is_none_or(|horizon| id.0 <= horizon)
+        {
+            self.uncommitted.pop_first();
+            let done = self.lanes[rank]
+                .history
+                .pop_front()
+                .expect("Uncommitted step out of history");
+            debug_assert!(done.id == id, "History out of order");
+            if let Step::NetworkStep { .. } = done.step {
+                self.last_delivery = self.last_delivery.max(id.0);
+            }
+            if let Some(timeline) = &mut self.timeline {
+                timeline.record(id.0, &done.step);
+            }
+            self.monitor.step_done();
+            committed += 1;
+        }
+        if let Some(gvt) = self.gvt()
+            && gvt > global::now()
+        {
+            global::fast_forward_clock(gvt);
+            self.sampler.observe(gvt);
+            self.progress_bar.make_progress(gvt);
+        }
+        committed
+    }
+
+    /// Takes steps up to the window past the GVT from the actors,
+    /// then runs the next held step of every idle process within the window.
+    fn advance(&mut self, deadline: Jiffies) {
+        let Some(gvt) = self.gvt() else {
+            return;
+        };
+        let limit = deadline.min(gvt + self.window);
+        while let Some(next) = self.actors.peek_next_step()
+            && next <= limit
+        {
+            let step = self.actors.next_step();
+            self.deliver((next, global_unique_id()), step);
+        }
+
+        let ready: Vec<Rank> = self
+            .heads
+            .iter()
+            .take_while(|(id, _)| id.0 <= limit)
+            .map(|&(_, rank)| rank)
+            .filter(|&rank| self.lanes[rank].executing.is_none())
+            .collect();
+        for rank in ready {
+            let (id, step) = self
+                .with_held(rank, |held| held.pop_first())
+                .expect("Head of no step");
+            self.executing.insert(id);
+            self.in_flight += 1;
+            self.lanes[rank].executing = Some((id, step.clone()));
+            self.workers.spawn_step(id, step);
+        }
+    }
+
+    /// Holds a new step for its process. If the process already ran later
+    /// steps, it is rolled back first.
+    fn deliver(&mut self, id: TaskId, step: Step) {
+        let rank = step.target_rank();
+        if self.lanes[rank]
+            .latest()
+            .is_some_and(|latest| latest > id.0)
+        {
+            self.roll_back(rank, id.0);
+        }
+        self.hold(rank, id, step);
+    }
+
+    fn hold(&mut self, rank: Rank, id: TaskId, step: Step) {
+        self.with_held(rank, |held| held.insert(id, step));
+    }
+
+    /// Runs `f` on the held steps of the process, keeping its head indexed.
+    fn with_held<R>(&mut self, rank: Rank, f: impl FnOnce(&mut BTreeMap<TaskId, Step>) -> R) -> R {
+        let held = &mut self.lanes[rank].held;
+        let head = held.first_key_value().map(|(id, _)| *id);
+        let result = f(held);
+        let new_head = held.first_key_value().map(|(id, _)| *id);
+        if head != new_head {
+            if let Some(head) = head {
+                self.heads.remove(&(head, rank));
+            }
+            if let Some(new_head) = new_head {
+                self.heads.insert((new_head, rank));
+            }
+        }
+        result
+    }
+
+    /// Undoes every step of the process later than `time`, latest first.
+    fn roll_back(&mut self, rank: Rank, time: Jiffies) {
+        let lane = &mut self.lanes[rank];
+        if lane.executing.as_ref().is_some_and(|(id, _)| id.0 > time) {
+            lane.stale = true;
+        }
+        while self.lanes[rank]
+            .history
+            .back()
+            .is_some_and(|done| done.id.0 > time)
+        {
+            let done = self.lanes[rank].history.pop_back().unwrap();
+            self.undo(rank, done, true);
+        }
+    }
+
+    /// Rolls every process back to its committed state.
+    fn roll_back_all(&mut self) {
+        // Whatever is left in histories runs after the GVT
+        while let Some((_, &rank)) = self.uncommitted.last_key_value() {
+            let gvt = self.gvt().expect("Uncommitted steps without GVT");
+            self.roll_back(rank, gvt);
+        }
+    }
+
+    /// Restores the state before the latest step of the process, with its generators,
+    /// and takes back what it caused. The step is held to run again unless it must never run.
+    fn undo(&mut self, rank: Rank, done: Processed, again: bool) {
+        self.uncommitted.remove(&done.id);
+        let lane = &mut self.lanes[rank];
+        if lane.executing.is_some() {
+            // Runs after the undone step, so it is undone as well once it finishes
+            lane.stale = true;
+            lane.restore = Some(done.before);
+        } else {
+            self.workers.restore(rank, done.before);
+        }
+        self.actors.network.restore_generator(rank, done.latencies);
+        if again {
+            self.hold(rank, done.id, done.step);
+        }
+        for id in done.timers {
+            if !self.actors.timers.cancel(id) {
+                self.retract(
+                    rank,
+                    move |step| matches!(*step, Step::TimerStep { id: timer, .. } if timer == id),
+                );
+            }
+        }
+        for sent in done.sent {
+            self.cancel(rank, sent);
+        }
+    }
+
+    /// Sends the anti-message of a message: deliveries still in flight are
+    /// removed, the ones already taken are retracted from their targets.
+    fn cancel(&mut self, source: Rank, sent: Sent) {
+        let mut taken = sent.alive;
+        for target in self.actors.network.cancel(source, &sent.message) {
+            let position = taken
+                .iter()
+                .position(|&t| t == target)
+                .expect("Delivery to no target");
+            taken.swap_remove(position);
+        }
+        for target in taken {
+            let message = sent.message.clone();
+            let retracted = self.retract(target, move |step| {
+                matches!(step, Step::NetworkStep { source: s, message: m, .. } if *s == source && m.is_same(&message))
+            });
+            if retracted {
+                self.actors.network.retract(source, target, &sent.message);
+            } else {
+                self.actors.network.retract_dropped(source, target);
+            }
+        }
+        for target in sent.dead {
+            self.actors.network.retract_dropped(source, target);
+        }
+    }
+
+    /// Takes back a step of the process that must never run: drops it if it is
+    /// held, or rolls the process back to before it if it ran.
+    /// Returns `false` if the step is not known, e.g. it was dropped.
+    fn retract(&mut self, rank: Rank, is_it: impl Fn(&Step) -> bool) -> bool {
+        let held = self.lanes[rank]
+            .held
+            .iter()
+            .find(|(_, step)| is_it(step))
+            .map(|(id, _)| *id);
+        if let Some(id) = held {
+            self.with_held(rank, |held| held.remove(&id));
+            return true;
+        }
+        let lane = &mut self.lanes[rank];
+        if lane.executing.as_ref().is_some_and(|(_, step)| is_it(step)) {
+            lane.stale = true;
+            lane.cancelled = true;
+            return true;
+        }
+        if !lane.history.iter().any(|done| is_it(&done.step)) {
+            return false;
+        }
+        loop {
+            let done = self.lanes[rank].history.pop_back().unwrap();
+            let found = is_it(&done.step);
+            self.undo(rank, done, !found);
+            if found {
+                return true;
+            }
+        }
+    }
+
+    /// Waits for every in-flight step and rolls back whatever is not committed,
+    /// so the run can be resumed later. Returns `status` with the steps
+    /// committed meanwhile accounted.
+    fn join_workers(&mut self, status: RunStatus) -> RunStatus {
+        while self.in_flight > 0 {
+            match self.workers.next_result() {
+                Ok(result) => self.ingest(result),
+                Err(RecvError) => {
+                    unreachable!("unexpected worker disconnection")
+                }
+            }
+        }
+        let steps = status.steps() + self.settle();
+        self.roll_back_all();
+        if self.monitor.pending() {
+            self.check_invariants();
+        }
+        self.monitor
+            .halted(steps)
+            .unwrap_or(status.with_steps(steps))
+    }
+}
@@ -0,0 +1,230 @@
+use std::{ops::Sub, time::Duration, time::Instant};
+
+use crate::{
+    actors::Actors,
+    jiffy::Jiffies,
+    now,
+    runners::{RunStatus, workers::Workers},
+};
+
+/// Message accounting between the processes of two pools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "checkpoint", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrafficStats {
+    /// Messages sent, one per target of a broadcast.
+    pub sent: usize,
+    /// Messages delivered to their target.
+    pub delivered: usize,
+    /// Messages discarded before delivery.
+    pub dropped: usize,
+    /// Virtual bytes (see [`crate::Message::virtual_size`]) of delivered messages.
+    pub bytes: usize,
+}
+
+/// Traffic between two reports, every counter saturating at zero
+/// if `rhs` is the later report.
+impl Sub for TrafficStats {
+    type Output = TrafficStats;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        TrafficStats {
+            sent: self.sent.saturating_sub(rhs.sent),
+            delivered: self.delivered.saturating_sub(rhs.delivered),
+            dropped: self.dropped.saturating_sub(rhs.dropped),
+            bytes: self.bytes.saturating_sub(rhs.bytes),
+        }
+    }
+}
+
+/// Counters accumulated by the actors since the simulation started.
+#[derive(Clone, Default)]
+pub(crate) struct Counters {
+    /// Flattened `pools x pools` matrix indexed by home pool of sender and receiver.
+    pub(crate) traffic: Vec<TrafficStats>,
+    pub(crate) timers_fired: usize,
+}
+
+/// Scheduling counters accumulated by the workers of a parallel runner.
+#[derive(Clone, Default)]
+pub(crate) struct WorkerCounters {
+    pub(crate) windows: usize,
+    pub(crate) steps: usize,
+    /// Sum over windows of the share of threads each one kept busy.
+    pub(crate) occupied: f64,
+    pub(crate) blocked: Duration,
+    /// Time every thread spent executing steps.
+    pub(crate) busy: Vec<Duration>,
+}
+
+/// How well a parallel run kept its worker threads busy, to tell whether
+/// the simulation benefits from running in parallel.
+///
+/// A window is the batch of steps handed to the workers before the
+/// coordinator waits for results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallelStats {
+    /// Worker threads of the runner.
+    pub threads: usize,
+    /// Number of windows that handed out at least one step.
+    pub windows: usize,
+    /// Steps handed out per window, on average.
+    pub parallelism: f64,
+    /// Share of the worker threads a window kept busy, on average.
+    pub window_utilization: f64,
+    /// Wall-clock time the coordinator waited for step results.
+    pub coordinator_blocked: Duration,
+    /// Share of the wall-clock time of the run every worker thread spent executing steps,
+    /// 0 if the run took no measurable time.
+    pub thread_busy: Vec<f64>,
+}
+
+/// Outcome of a run together with what happened during it.
+///
+/// Every counter covers only the run call that produced the report.
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    /// Why the run stopped.
+    pub status: RunStatus,
+    /// Simulation time reached.
+    pub time: Jiffies,
+    /// Wall-clock duration of the run.
+    pub wall_time: Duration,
+    /// Number of timers that fired.
+    pub timers_fired: usize,
+    /// Peak number of messages waiting for NIC bandwidth
+    /// (always zero with [`crate::BandwidthConfig::Unbounded`]).
+    pub peak_queue_depth: usize,
+    /// Scheduling diagnostics of the parallel runners, `None` for the single-threaded one.
+    pub parallel: Option<ParallelStats>,
+    pools: Vec<String>,
+    traffic: Vec<TrafficStats>,
+}
+
+impl RunReport {
+    /// Number of steps that were actually executed.
+    pub fn steps(&self) -> usize {
+        self.status.steps()
+    }
+
+    /// Executed steps per wall-clock second, 0 if the run took no measurable time.
+    pub fn steps_per_sec(&self) -> f64 {
+        let secs = self.wall_time.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.steps() as f64 / secs
+    }
+
+    /// Traffic from processes added with pool `from` to processes added with pool `to`.
+    /// Returns `None` if either pool is unknown.
+    pub fn traffic(&self, from: &str, to: &str) -> Option<TrafficStats> {
+        let from = self.pools.iter().position(|p| p == from)?;
+        let to = self.pools.iter().position(|p| p == to)?;
+        Some(self.traffic[from * self.pools.len() + to])
+    }
+
+    /// Iterates over `(from, to, traffic)` for every pair of pools.
+    pub fn pool_pairs(&self) -> impl Iterator<Item = (&str, &str, TrafficStats)> {
+        let n = self.pools.len();
+        self.traffic
+            .iter()
+            .enumerate()
+            .map(move |(i, t)| (self.pools[i / n].as_str(), self.pools[i % n].as_str(), *t))
+    }
+
+    /// Traffic summed over all pool pairs.
+    pub fn total_traffic(&self) -> TrafficStats {
+        self.traffic
+            .iter()
+            .fold(TrafficStats::default(), |acc, t| TrafficStats {
+                sent: acc.sent + t.sent,
+                delivered: acc.delivered + t.delivered,
+                dropped: acc.dropped + t.dropped,
+                bytes: acc.bytes + t.bytes,
+            })
+    }
+}
+
+/// Captures counters at the start of a run to report their difference at the end.
+pub(crate) struct RunRecorder {
+    started: Instant,
+    before: Counters,
+    workers: Option<WorkerCounters>,
+}
+
+impl RunRecorder {
+    pub(crate) fn start(actors: &mut Actors, workers: Option<&Workers>) -> Self {
+        actors.take_peak_queue_depth();
+        Self {
+            started: Instant::now(),
+            before: actors.counters(),
+            workers: workers.map(Workers::counters),
+        }
+    }
+
+    pub(crate) fn finish(
+        self,
+        status: RunStatus,
+        actors: &mut Actors,
+        workers: Option<&Workers>,
+    ) -> RunReport {
+        let after = actors.counters();
+        let wall_time = self.started.elapsed();
+        let parallel = self
+            .workers
+            .zip(workers)
+            .map(|(before, workers)| parallel_stats(&before, workers, wall_time));
+        RunReport {
+            status,
+            time: now(),
+            wall_time,
+            timers_fired: after.timers_fired - self.before.timers_fired,
+            peak_queue_depth: actors.take_peak_queue_depth(),
+            parallel,
+            pools: actors.pool_names().to_vec(),
+            traffic: after
+                .traffic
+                .iter()
+                .zip(self.before.traffic.iter())
+                .map(|(a, b)| *a - *b)
+                .collect(),
+        }
+    }
+}
+
+fn parallel_stats(
+    before: &WorkerCounters,
+    workers: &Workers,
+    wall_time: Duration,
+) -> ParallelStats {
+    let after = workers.counters();
+    let secs = wall_time.as_secs_f64();
+    let windows = after.windows - before.windows;
+    // Zero rather than NaN for runs without a single window
+    let per_window = |total: f64| {
+        if windows == 0 {
+            0.0
+        } else {
+            total / windows as f64
+        }
+    };
+    ParallelStats {
+        threads: after.busy.len(),
+        windows,
+        parallelism: per_window((after.steps - before.steps) as f64),
+        window_utilization: per_window(after.occupied - before.occupied),
+        coordinator_blocked: after.blocked - before.blocked,
+        thread_busy: after
+            .busy
+            .iter()
+            .zip(&before.busy)
+            .map(|(a, b)| {
+                if secs == 0.0 {
+                    0.0
+                } else {
+                    (*a - *b).as_secs_f64() / secs
+                }
+            })
+            .collect(),
+    }
+}
@@ -1,35 +1,58 @@
-use std::{cmp::Reverse, collections::VecDeque};
+use std::collections::{BTreeMap, BTreeSet};
 
 use crossbeam_channel::RecvError;
 
 use crate::{
-    actors::Actors,
-    global::{self},
+    Rank,
+    actors::{Actors, Churn},
+    event::Event,
+    global::{self, clock::set_step_clock, local_access::EventBatch, metrics::Sampler},
     global_unique_id,
     jiffy::Jiffies,
     now,
     runners::{
-        RunStatus, SimulationRunner,
+        Goal, RunStatus, SimulationRunner,
+        monitor::Monitor,
         progress::Bar,
-        task::{TaskId, TaskIndex, TaskResult},
+        report::{RunRecorder, RunReport},
+        task::{TaskId, TaskResult},
         workers::Workers,
     },
     step::Step,
+    topology::Topology,
+    trace::Timeline,
+    view::View,
 };
 
+/// Conservative parallel runner. Each home pool advances on its own: a step
+/// is executed once no unfinished step of any pool can cause an earlier one,
+/// judged by the lowest latency from each pool to another (its lookahead).
+/// The global clock follows the earliest unfinished step.
 pub(crate) struct ScalableRunner {
+    // Id of the simulation in `global`, entered by the builder
+    simulation: usize,
     actors: Actors,
     time_budget: Jiffies,
     workers: Workers,
     progress_bar: Bar,
-    window_delta: Jiffies,
-    on_execution: TaskIndex,
-    done: TaskIndex,
+    pools: usize,
+    // Lookahead of every (home pool, home pool) pair, flattened like `Topology::pool_pair`
+    lookahead: Vec<Jiffies>,
+    max_lookahead: Jiffies,
+    // Steps executing in the thread pool, per home pool
+    executing: Vec<BTreeSet<TaskId>>,
+    in_flight: usize,
+    // Steps taken from the actors but not safe to execute yet, per home pool.
+    // A step also waits while its process is busy, which keeps per process order
+    held: Vec<BTreeMap<TaskId, Step>>,
     // Whether a process currently has a task executing in the thread pool.
     busy: Vec<bool>,
-    // Per-process queue of tasks within the window but deferred because the process is busy.
-    // Keeps sequential order per process within window
-    waiting: Vec<VecDeque<(TaskId, Step)>>,
+    // Pool changes of finished steps, applied once every earlier step finished
+    pool_changes: BTreeMap<TaskId, EventBatch>,
+    monitor: Monitor,
+    sampler: Sampler,
+    timeline: Option<Timeline>,
+    last_delivery: Jiffies,
     started: bool,
 }
 
@@ -38,79 +61,209 @@ impl ScalableRunner {
         actors: Actors,
         time_budget: Jiffies,
         workers: Workers,
-        safe_window: Jiffies,
+        monitor: Monitor,
+        sampler: Sampler,
+        timeline: Option<Timeline>,
     ) -> Self {
         let num_procs = workers.num_procs();
+        let pools = actors.topology.pool_names().len();
+        let lookahead = Self::lookahead(&actors.topology);
+        let max_lookahead = lookahead.iter().copied().max().unwrap_or(Jiffies(0));
         Self {
+            simulation: global::active(),
             actors,
             time_budget,
             workers,
             progress_bar: Bar::new(time_budget),
-            window_delta: safe_window,
-            on_execution: TaskIndex::new(),
-            done: TaskIndex::new(),
+            pools,
+            lookahead,
+            max_lookahead,
+            executing: vec![BTreeSet::new(); pools],
+            in_flight: 0,
+            held: (0..pools).map(|_| BTreeMap::new()).collect(),
             busy: vec![false; num_procs],
-            waiting: (0..num_procs).map(|_| VecDeque::new()).collect(),
+            pool_changes: BTreeMap::new(),
+            monitor,
+            sampler,
+            timeline,
+            last_delivery: Jiffies(0),
             started: false,
         }
     }
 
+    /// Lowest latency from every home pool to every other over any path of pools,
+    /// flattened like `Topology::pool_pair`. A step can cause a later one in another
+    /// pool through steps in between, or by spawning a process into a pool, which
+    /// starts at once and so counts as a path of no latency to it.
+    fn lookahead(topology: &Topology) -> Vec<Jiffies> {
+        let pools = topology.pool_names().len();
+        let mut lookahead: Vec<Jiffies> = (0..pools * pools)
+            .map(|pair| match (pair / pools, pair % pools) {
+                (_, to) if topology.can_spawn_into(to) => Jiffies(0),
+                (from, to) => topology.lookahead(from, to),
+            })
+            .collect();
+        for via in 0..pools {
+            for from in 0..pools {
+                for to in 0..pools {
+                    let through = lookahead[from * pools + via] + lookahead[via * pools + to];
+                    if through < lookahead[from * pools + to] {
+                        lookahead[from * pools + to] = through;
+                    }
+                }
+            }
+        }
+        lookahead
+    }
+
     fn ensure_started(&mut self) {
         if !self.started {
             self.started = true;
             for rank in 0..self.workers.num_procs() {
-                let step = Step::Start { rank };
-                let task_id: TaskId = (global::now(), global_unique_id());
-                self.workers.install_step(task_id, step);
-                self.busy[rank] = true;
-                self.on_execution.push(Reverse(task_id));
+                self.install_start(rank);
             }
         }
     }
+
+    /// Runs `on_start` of the process right away. Its result is ingested by the next run.
+    fn install_start(&mut self, rank: Rank) {
+        let step = Step::Start { rank };
+        let task_id: TaskId = (global::now(), global_unique_id());
+        if let Some(timeline) = &mut self.timeline {
+            timeline.record(task_id.0, &step);
+        }
+        self.track(task_id, rank);
+        self.workers.install_step(task_id, step);
+    }
 }
 
 impl Drop for ScalableRunner {
     fn drop(&mut self) {
-        global::reset();
+        global::leave(self.simulation);
     }
 }
 
 impl SimulationRunner for ScalableRunner {
-    fn run_full_budget(&mut self) -> RunStatus {
-        self.ensure_started();
-        let status = self.coordinate(None, self.time_budget);
-        self.join_workers();
-        self.progress_bar.finish();
-        status
+    fn run_full_budget(&mut self) -> RunReport {
+        self.run(Goal::Deadline(self.time_budget))
     }
 
-    fn run_steps(&mut self, k: usize) -> RunStatus {
-        self.ensure_started();
-        let status = self.coordinate(Some(k), self.time_budget);
-        self.join_workers();
-        status
+    fn run_steps(&mut self, k: usize) -> RunReport {
+        self.run(Goal::Steps(k))
+    }
+
+    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunReport {
+        self.run(Goal::Deadline(now() + sub_budget))
+    }
+
+    fn run_until(&mut self, predicate: &mut dyn FnMut(&View) -> bool) -> RunReport {
+        self.run(Goal::Predicate(predicate))
+    }
+
+    fn run_until_quiescent(&mut self, grace: Jiffies) -> RunReport {
+        self.run(Goal::Quiescence(grace))
+    }
+
+    fn timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
     }
 
-    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunStatus {
+    fn restart(&mut self, rank: Rank) {
+        global::activate(self.simulation);
+        assert!(self.actors.topology.is_alive(rank), "P{rank} has exited");
+        // Runs leave no step in flight, so the process is not executing
         self.ensure_started();
-        let deadline = std::cmp::min(now() + sub_budget, self.time_budget);
-        let status = self.coordinate(None, deadline);
-        self.join_workers();
-        status
+        self.actors.timers.discard_of(rank);
+        let pool = self.actors.topology.home_pool(rank);
+        self.held[pool]
+            .retain(|_, step| !matches!(*step, Step::TimerStep { rank: r, .. } if r == rank));
+        self.workers.rebuild(rank);
+        self.install_start(rank);
     }
 }
 
 impl ScalableRunner {
-    /// Coordinate the worker pool.
-    /// - `max_steps`: if `Some(k)`, stop after `k` ingested results.
-    /// - `deadline`: stop when simulation time reaches this value.
-    fn coordinate(&mut self, max_steps: Option<usize>, deadline: Jiffies) -> RunStatus {
+    fn run(&mut self, goal: Goal) -> RunReport {
+        global::activate(self.simulation);
+        let recorder = RunRecorder::start(&mut self.actors, Some(&self.workers));
+        self.ensure_started();
+        let status = self.coordinate(goal);
+        let status = self.join_workers(status);
+        if let RunStatus::BudgetExhausted { .. } = status {
+            self.progress_bar.finish();
+        }
+        let status = self.finish(status);
+        recorder.finish(status, &mut self.actors, Some(&self.workers))
+    }
+
+    /// Coordinate the worker pool until the goal is reached, the time budget
+    /// is exhausted or no more events remain.
+    ///
+    /// Checks that need a consistent view over all processes (invariants,
+    /// predicates, quiescence) are done once every in-flight step has finished.
+    fn coordinate(&mut self, mut goal: Goal) -> RunStatus {
         let mut steps: usize = 0;
+        if let Some(status) = self.monitor.halted(steps) {
+            return status;
+        }
+
+        let deadline = match goal {
+            Goal::Deadline(deadline) => deadline.min(self.time_budget),
+            _ => self.time_budget,
+        };
+        let needs_barrier = matches!(goal, Goal::Predicate(_) | Goal::Quiescence(_));
+        self.last_delivery = global::now();
+
         loop {
-            if let Some(k) = max_steps {
-                if steps >= k {
-                    return RunStatus::Completed { steps };
+            let time = global::now();
+            if self.in_flight == 0 {
+                if self.monitor.pending() {
+                    self.check_invariants();
+                    if let Some(status) = self.monitor.halted(steps) {
+                        return status;
+                    }
                 }
+                match &mut goal {
+                    Goal::Predicate(predicate) => {
+                        let view = View::new(self.workers.all());
+                        if predicate(&view) {
+                            return RunStatus::PredicateMet { steps, time };
+                        }
+                    }
+                    Goal::Quiescence(grace) if self.quiescent(*grace) => {
+                        return RunStatus::Quiescent { steps, time };
+                    }
+                    _ => {}
+                }
+                if self.earliest_pending().is_none() {
+                    return RunStatus::NoMoreEvents { steps };
+                }
+            }
+
+            if let Goal::Steps(k) = goal
+                && steps >= k
+            {
+                return RunStatus::Completed { steps };
+            }
+            if time >= deadline {
+                if time >= self.time_budget {
+                    return RunStatus::BudgetExhausted { steps };
+                }
+                return RunStatus::Completed { steps };
+            }
+
+            // Keep in-flight steps from racing ahead of a pending consistent check
+            if self.in_flight == 0 || !(needs_barrier || self.monitor.pending()) {
+                // Never execute more steps than asked for
+                let room = match goal {
+                    Goal::Steps(k) => k.saturating_sub(steps + self.in_flight),
+                    _ => usize::MAX,
+                };
+                self.advance(deadline, room);
+            }
+            // Every remaining step lies beyond the deadline
+            if self.in_flight == 0 {
+                continue;
             }
 
             // Block until at least one result arrives
@@ -119,32 +272,11 @@ impl ScalableRunner {
                     self.ingest(first);
                     steps += 1;
 
-                    if global::now() >= deadline {
-                        if global::now() >= self.time_budget {
-                            return RunStatus::BudgetExhausted { steps };
-                        }
-                        return RunStatus::Completed { steps };
-                    }
-
-                    if let Some(k) = max_steps {
-                        if steps >= k {
-                            return RunStatus::Completed { steps };
-                        }
-                    }
-
                     // Drain all immediately available results
                     while let Some(result) = self.workers.try_next_result() {
                         self.ingest(result);
                         steps += 1;
-                        if let Some(k) = max_steps {
-                            if steps >= k {
-                                return RunStatus::Completed { steps };
-                            }
-                        }
                     }
-
-                    self.adjust_task_index();
-                    self.try_advance();
                 }
                 Err(RecvError) => {
                     unreachable!("unexpected worker disconnection")
@@ -154,97 +286,251 @@ impl ScalableRunner {
     }
 
     fn ingest(&mut self, mut task_result: TaskResult) {
+        let (time, _) = task_result.id;
         let rank = task_result.rank;
-        self.actors.submit(&mut task_result.events);
-        self.done.push(Reverse(task_result.id));
-
-        // The process is no longer busy — check if there's a deferred task waiting.
-        if let Some((waiting_id, waiting_step)) = self.waiting[rank].pop_front() {
-            self.workers.spawn_step(waiting_id, waiting_step);
-        } else {
-            self.busy[rank] = false;
+        let pool = self.actors.topology.home_pool(rank);
+        // Whatever the step caused happens at its own time, not at the global one
+        set_step_clock(Some(time));
+        let (pool_changes, mut events): (EventBatch, EventBatch) =
+            task_result.events.drain(..).partition(|event| {
+                matches!(
+                    event,
+                    Event::CreatePool { .. } | Event::JoinPool { .. } | Event::LeavePool { .. }
+                )
+            });
+        let churn = self.actors.submit(&mut events);
+        churn.into_iter().for_each(|churn| self.apply(churn));
+        set_step_clock(None);
+        if !pool_changes.is_empty() {
+            self.pool_changes.insert(task_result.id, pool_changes);
         }
-    }
 
-    fn adjust_task_index(&mut self) {
-        while let (Some(d), Some(e)) = (self.done.peek(), self.on_execution.peek()) {
-            if d == e {
-                self.done.pop();
-                self.on_execution.pop();
-            } else {
-                break;
-            }
-        }
+        self.executing[pool].remove(&task_result.id);
+        self.in_flight -= 1;
+        self.busy[rank] = false;
+        self.monitor.step_done();
+        self.apply_pool_changes();
     }
 
-    fn try_advance(&mut self) {
-        if self.try_move_window() {
-            self.spawn_remain_within_window()
+    /// Applies pool changes in the order of their steps, each once every earlier
+    /// step has finished, so those saw the pools as before. Later steps wait for
+    /// it in [`ScalableRunner::advance`], unless they were already executing.
+    fn apply_pool_changes(&mut self) {
+        while let Some(entry) = self.pool_changes.first_entry() {
+            let change = *entry.key();
+            let earlier_executing = self
+                .executing
+                .iter()
+                .filter_map(|executing| executing.first())
+                .any(|&id| id < change);
+            let earlier_held = self
+                .held
+                .iter()
+                .filter_map(|held| held.first_key_value())
+                .any(|(&id, _)| id < change);
+            let earlier_pending = self
+                .actors
+                .peek_next_step()
+                .is_some_and(|next| next < change.0);
+            if earlier_executing || earlier_held || earlier_pending {
+                return;
+            }
+            set_step_clock(Some(change.0));
+            self.actors.submit(&mut entry.remove());
+            set_step_clock(None);
         }
     }
 
-    fn try_move_window(&mut self) -> bool {
-        if let Some(top) = self.on_execution.peek() {
-            if global::now() == top.0.0 {
-                return false;
-            } else {
-                // There is still some top task executing in window — move to this task
-                global::fast_forward_clock(top.0.0);
-                self.progress_bar.make_progress(top.0.0);
+    /// Adds and starts a spawned process, or vacates the rank of an exited one.
+    fn apply(&mut self, churn: Churn) {
+        match churn {
+            Churn::Spawned {
+                rank,
+                pool,
+                factory,
+                index,
+                pool_size,
+            } => {
+                self.workers.join(rank, factory, &pool, index, pool_size);
+                if self.busy.len() <= rank {
+                    self.busy.resize(rank + 1, false);
+                }
+                if let Some(timeline) = &mut self.timeline {
+                    timeline.add_process(rank, &pool);
+                }
+                // Nothing can be addressed to the process before it exists
+                self.spawn((global::now(), global_unique_id()), Step::Start { rank });
             }
-        } else {
-            // No tasks in window — try to find new next task outside window
-            if let Some(next_step_invocation_time) = self.actors.peek_next_step() {
-                global::fast_forward_clock(next_step_invocation_time);
-                self.progress_bar.make_progress(next_step_invocation_time);
-            } else {
-                // No more events — quiesced. Not a deadlock, coordinate will
-                // exit on the next iteration when it blocks on next_result and
-                // no workers are busy, or the caller checks the step limit.
-                return false;
+            Churn::Exited { rank } => {
+                // Steps held before the exit are never executed
+                let pool = self.actors.topology.home_pool(rank);
+                let network = &mut self.actors.network;
+                self.held[pool].retain(|_, step| {
+                    if step.target_rank() != rank {
+                        return true;
+                    }
+                    if let Step::NetworkStep { source, target, .. } = *step {
+                        network.undeliver(source, target);
+                    }
+                    false
+                });
+                self.workers.vacate(rank);
             }
         }
-        true
     }
 
-    fn spawn_remain_within_window(&mut self) {
-        while let Some(next_step_invocation_time) = self.actors.peek_next_step() {
-            if next_step_invocation_time - now() <= self.window_delta {
-                let next_step = self.actors.next_step();
-                self.schedule(next_step);
-            } else {
-                break;
+    fn check_invariants(&mut self) {
+        let view = View::new(self.workers.all());
+        self.monitor.check(&view);
+    }
+
+    /// No message or one-shot timer is pending and nothing but periodic timers
+    /// happens within the grace period.
+    fn quiescent(&self, grace: Jiffies) -> bool {
+        let held_messages = self
+            .held
+            .iter()
+            .flat_map(|held| held.values())
+            .any(|step| matches!(step, Step::NetworkStep { .. }));
+        self.actors.idle()
+            && !held_messages
+            && self
+                .earliest_pending()
+                .is_none_or(|next| next > self.last_delivery + grace)
+    }
+
+    /// Settles temporal properties once the simulation can not go any further.
+    fn finish(&mut self, status: RunStatus) -> RunStatus {
+        let horizon = match status {
+            RunStatus::BudgetExhausted { .. } => global::now(),
+            RunStatus::NoMoreEvents { .. } => Jiffies(usize::MAX),
+            _ => return status,
+        };
+        let view = View::new(self.workers.all());
+        self.monitor.settle(&view, horizon);
+        self.monitor.halted(status.steps()).unwrap_or(status)
+    }
+
+    /// Earliest step not executed yet, held or still in the actors.
+    fn earliest_pending(&self) -> Option<Jiffies> {
+        self.held
+            .iter()
+            .filter_map(|held| held.first_key_value().map(|(id, _)| id.0))
+            .chain(self.actors.peek_next_step())
+            .min()
+    }
+
+    /// Earliest step of the pool that is executing or held.
+    fn pool_floor(&self, pool: usize) -> Option<Jiffies> {
+        let executing = self.executing[pool].first().map(|id| id.0);
+        let held = self.held[pool].first_key_value().map(|(id, _)| id.0);
+        executing.into_iter().chain(held).min()
+    }
+
+    /// Moves the global clock to the earliest unfinished step, then executes
+    /// every held step that no other step can precede anymore, at most `room` of them.
+    fn advance(&mut self, deadline: Jiffies, mut room: usize) {
+        let executing = self
+            .executing
+            .iter()
+            .filter_map(|executing| executing.first().map(|id| id.0))
+            .min();
+        let Some(earliest) = executing.into_iter().chain(self.earliest_pending()).min() else {
+            return;
+        };
+        if earliest > global::now() {
+            global::fast_forward_clock(earliest);
+            self.sampler.observe(earliest);
+            self.progress_bar.make_progress(earliest);
+        }
+
+        // Take every step some pool could be allowed to execute
+        let horizon = deadline.min(earliest + self.max_lookahead);
+        while let Some(next) = self.actors.peek_next_step()
+            && next <= horizon
+        {
+            let step = self.actors.next_step();
+            let pool = self.actors.topology.home_pool(step.target_rank());
+            self.held[pool].insert((next, global_unique_id()), step);
+        }
+
+        // Steps still in the actors belong to no pool, so they bound every pool
+        let pending = self.actors.peek_next_step();
+        let floors: Vec<Option<Jiffies>> = (0..self.pools)
+            .map(|pool| self.pool_floor(pool).into_iter().chain(pending).min())
+            .collect();
+        for pool in 0..self.pools {
+            let Some(safe) = floors
+                .iter()
+                .enumerate()
+                .filter_map(|(from, floor)| {
+                    floor.map(|floor| floor + self.lookahead[from * self.pools + pool])
+                })
+                .min()
+            else {
+                continue;
+            };
+            let safe = safe.min(deadline);
+            // Steps after a pool change not applied yet wait for it
+            let barrier = self.pool_changes.first_key_value().map(|(&id, _)| id);
+            // Claims the process of the earliest step, later ones wait for it
+            let busy = &mut self.busy;
+            let ready: Vec<TaskId> = self.held[pool]
+                .range(..=(safe, usize::MAX))
+                .take_while(|&(&id, _)| barrier.is_none_or(|barrier| id < barrier))
+                .filter(|(_, step)| !std::mem::replace(&mut busy[step.target_rank()], true))
+                .map(|(id, _)| *id)
+                .take(room)
+                .collect();
+            room -= ready.len();
+            for task_id in ready {
+                let step = self.held[pool].remove(&task_id).expect("held step");
+                self.spawn(task_id, step);
             }
         }
     }
 
-    fn join_workers(&mut self) {
-        for queue in &mut self.waiting {
-            queue.clear();
-        }
-        while self.busy.iter().any(|&b| b) {
+    /// Waits for every in-flight step, so the run can be resumed later
+    /// without losing their events. Returns `status` with those steps accounted.
+    fn join_workers(&mut self, status: RunStatus) -> RunStatus {
+        let mut extra = 0;
+        while self.in_flight > 0 {
             match self.workers.next_result() {
                 Ok(result) => {
-                    self.busy[result.rank] = false;
+                    self.ingest(result);
+                    extra += 1;
                 }
                 Err(RecvError) => {
                     unreachable!("unexpected worker disconnection")
                 }
             }
         }
+        if self.monitor.pending() {
+            self.check_invariants();
+        }
+        let steps = status.steps() + extra;
+        self.monitor
+            .halted(steps)
+            .unwrap_or(status.with_steps(steps))
     }
 
-    /// Create a TaskId and either spawn immediately or defer if the target process is busy.
-    fn schedule(&mut self, step: Step) {
-        let task_id: TaskId = (global::now(), global_unique_id());
+    /// Executes the step in the thread pool.
+    fn spawn(&mut self, task_id: TaskId, step: Step) {
         let rank = step.target_rank();
-        self.on_execution.push(Reverse(task_id));
-
-        if self.busy[rank] {
-            self.waiting[rank].push_back((task_id, step));
-        } else {
-            self.busy[rank] = true;
-            self.workers.spawn_step(task_id, step);
+        if let Step::NetworkStep { .. } = step {
+            self.last_delivery = self.last_delivery.max(task_id.0);
+        }
+        if let Some(timeline) = &mut self.timeline {
+            timeline.record(task_id.0, &step);
         }
+        self.track(task_id, rank);
+        self.workers.spawn_step(task_id, step);
+    }
+
+    fn track(&mut self, task_id: TaskId, rank: Rank) {
+        let pool = self.actors.topology.home_pool(rank);
+        self.executing[pool].insert(task_id);
+        self.in_flight += 1;
+        self.busy[rank] = true;
     }
 }
@@ -1,122 +1,357 @@
+use std::mem;
+
 use crate::{
-    ProcessHandle,
-    actors::Actors,
+    ProcessHandle, Rank,
+    actors::{Actors, Churn},
+    factory::Factories,
     global::{
         self,
         configuration::setup_local_configuration,
-        local_access::{self, setup_local_access},
+        kv::Cloners,
+        local_access::{self, Handling, setup_local_access},
+        metrics::Sampler,
     },
     global_unique_id,
     jiffy::Jiffies,
-    random::Seed,
-    runners::{RunStatus, SimulationRunner, progress::Bar, task::TaskResult},
+    process_handle::Vacant,
+    random::{self, Randomizer, Seed},
+    runners::{
+        Goal, RunStatus, SimulationRunner,
+        monitor::Monitor,
+        progress::Bar,
+        report::{RunRecorder, RunReport},
+        task::TaskResult,
+    },
     step::Step,
+    trace::Timeline,
+    view::View,
+};
+#[cfg(feature = "checkpoint")]
+use crate::{
+    checkpoint::{self, Codecs, Image},
+    global::kv,
 };
 
 pub(crate) struct SimpleRunner {
+    // Id of the simulation in `global`, entered by the builder
+    simulation: usize,
     actors: Actors,
     time_budget: Jiffies,
     procs: Vec<Box<dyn ProcessHandle>>,
+    // Generators of the random choices of every process
+    generators: Vec<Randomizer>,
+    factories: Factories,
+    seed: Seed,
     progress_bar: Bar,
+    monitor: Monitor,
+    sampler: Sampler,
+    timeline: Option<Timeline>,
+    last_delivery: Jiffies,
     started: bool,
+    // Forks taken so far, each gets a seed derived from the count
+    forks: u64,
+    cloners: Cloners,
+    #[cfg(feature = "checkpoint")]
+    codecs: Codecs,
 }
 
 impl SimpleRunner {
     pub(crate) fn new(
         actors: Actors,
         time_budget: Jiffies,
-        procs: Vec<Box<dyn ProcessHandle>>,
+        factories: Factories,
         seed: Seed,
+        monitor: Monitor,
+        sampler: Sampler,
+        timeline: Option<Timeline>,
     ) -> Self {
+        let procs: Vec<Box<dyn ProcessHandle>> = (0..factories.len())
+            .map(|rank| factories.build(rank) as Box<dyn ProcessHandle>)
+            .collect();
         for id in 0..procs.len() {
             setup_local_configuration(id, seed);
         }
+        let generators = (0..procs.len())
+            .map(|rank| random::process_generators(seed, rank).0)
+            .collect();
         // Set up thread-local access on the main thread directly — no channel needed.
         // We pass a dummy sender that is never used since we call take_events() instead of done().
         let (tx, _rx) = crossbeam_channel::unbounded::<TaskResult>();
-        setup_local_access(seed, tx);
+        setup_local_access(tx);
         Self {
+            simulation: global::active(),
             actors,
             time_budget,
             progress_bar: Bar::new(time_budget),
             procs,
+            generators,
+            factories,
+            seed,
+            monitor,
+            sampler,
+            timeline,
+            last_delivery: Jiffies(0),
             started: false,
+            forks: 0,
+            cloners: Cloners::default(),
+            #[cfg(feature = "checkpoint")]
+            codecs: Codecs::default(),
         }
     }
 
-    fn ensure_started(&mut self) {
-        if !self.started {
-            self.started = true;
-            for rank in 0..self.procs.len() {
-                self.run_step(Step::Start { rank });
-            }
+    /// Types of key-value store entries forks can copy.
+    pub(crate) fn with_cloners(mut self, cloners: Cloners) -> Self {
+        self.cloners = cloners;
+        self
+    }
+
+    /// Types of messages and key-value store entries checkpoints can hold.
+    #[cfg(feature = "checkpoint")]
+    pub(crate) fn with_codecs(mut self, codecs: Codecs) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// Returns the number of processes started.
+    fn ensure_started(&mut self) -> usize {
+        if self.started {
+            return 0;
+        }
+        self.started = true;
+        for rank in 0..self.procs.len() {
+            self.run_step(Step::Start { rank });
         }
+        self.procs.len()
     }
 }
 
 impl Drop for SimpleRunner {
     fn drop(&mut self) {
-        global::reset();
+        global::leave(self.simulation);
     }
 }
 
 impl SimulationRunner for SimpleRunner {
-    fn run_full_budget(&mut self) -> RunStatus {
+    fn run_full_budget(&mut self) -> RunReport {
+        self.run(Goal::Deadline(self.time_budget))
+    }
+
+    fn run_steps(&mut self, k: usize) -> RunReport {
+        self.run(Goal::Steps(k))
+    }
+
+    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunReport {
+        self.run(Goal::Deadline(global::now() + sub_budget))
+    }
+
+    fn run_until(&mut self, predicate: &mut dyn FnMut(&View) -> bool) -> RunReport {
+        self.run(Goal::Predicate(predicate))
+    }
+
+    fn run_until_quiescent(&mut self, grace: Jiffies) -> RunReport {
+        self.run(Goal::Quiescence(grace))
+    }
+
+    fn timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
+    }
+
+    fn restart(&mut self, rank: Rank) {
+        global::activate(self.simulation);
+        assert!(self.actors.topology.is_alive(rank), "P{rank} has exited");
         self.ensure_started();
+        self.actors.timers.discard_of(rank);
+        self.procs[rank] = self.factories.rebuild(rank);
+        self.run_step(Step::Start { rank });
+    }
 
-        let mut steps = 0;
-        while global::now() < self.time_budget {
-            if self.actors.peek_next_step().is_none() {
-                return RunStatus::NoMoreEvents { steps };
-            }
-            self.run_next_step();
-            steps += 1;
+    fn fork(&mut self) -> Box<dyn SimulationRunner> {
+        global::activate(self.simulation);
+        self.forks += 1;
+        let seed = random::derive_seed(self.seed, self.forks);
+        let procs = self
+            .procs
+            .iter()
+            .enumerate()
+            .map(|(rank, proc)| -> Box<dyn ProcessHandle> {
+                proc.snapshot().unwrap_or_else(|| {
+                    panic!("P{rank} can not be forked: implement ProcessHandle::snapshot")
+                })
+            })
+            .collect();
+        let generators = (0..self.procs.len())
+            .map(|rank| random::process_generators(seed, rank).0)
+            .collect();
+        let actors = self.actors.fork(seed);
+        let simulation = global::fork(actors.topology.clone(), seed, &self.cloners);
+        Box::new(SimpleRunner {
+            simulation,
+            actors,
+            time_budget: self.time_budget,
+            procs,
+            generators,
+            factories: self.factories.clone(),
+            seed,
+            progress_bar: Bar::new(self.time_budget),
+            monitor: self.monitor.clone(),
+            sampler: self.sampler.clone(),
+            timeline: self.timeline.clone(),
+            last_delivery: self.last_delivery,
+            started: self.started,
+            forks: 0,
+            cloners: self.cloners.clone(),
+            #[cfg(feature = "checkpoint")]
+            codecs: self.codecs.clone(),
+        })
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn checkpoint(&self) -> Vec<u8> {
+        global::activate(self.simulation);
+        // A simulation built the same way restores the pools as they were built
+        assert!(
+            !self.actors.topology.membership_changed(),
+            "Can not checkpoint: processes were spawned, exited or changed pools"
+        );
+        let processes = self
+            .procs
+            .iter()
+            .enumerate()
+            .map(|(rank, proc)| {
+                proc.save().unwrap_or_else(|| {
+                    panic!("P{rank} can not be checkpointed: implement ProcessHandle::save")
+                })
+            })
+            .collect();
+        let codecs = &self.codecs;
+        let image = Image {
+            time: global::now(),
+            next_id: global::tso::next_id(),
+            generators: self.generators.clone(),
+            started: self.started,
+            last_delivery: self.last_delivery,
+            timers: self.actors.timers.save(),
+            network: self.actors.network.save(codecs),
+            kv: kv::save(|key, value| (key.to_string(), codecs.save_value(key, value))),
+            processes,
+        };
+        checkpoint::encode(&image)
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn restore(&mut self, checkpoint: &[u8]) {
+        global::activate(self.simulation);
+        let image: Image = checkpoint::decode(checkpoint);
+        assert_eq!(
+            image.processes.len(),
+            self.procs.len(),
+            "Checkpoint of a simulation with another number of processes"
+        );
+        global::clock::set_clock(image.time);
+        global::tso::set_next_id(image.next_id);
+        self.generators = image.generators;
+        self.started = image.started;
+        self.last_delivery = image.last_delivery;
+        self.actors.timers.load(image.timers);
+        self.actors.network.load(image.network, &self.codecs);
+        kv::load(
+            image
+                .kv
+                .iter()
+                .map(|(key, value)| (key.clone(), self.codecs.load_value(value))),
+        );
+        for (proc, state) in self.procs.iter_mut().zip(&image.processes) {
+            proc.load(state);
         }
+    }
+}
 
-        self.progress_bar.finish();
-        RunStatus::BudgetExhausted { steps }
+impl SimpleRunner {
+    fn run(&mut self, goal: Goal) -> RunReport {
+        global::activate(self.simulation);
+        let recorder = RunRecorder::start(&mut self.actors, None);
+        let status = self.drive(goal);
+        recorder.finish(status, &mut self.actors, None)
     }
 
-    fn run_steps(&mut self, k: usize) -> RunStatus {
-        self.ensure_started();
+    fn drive(&mut self, mut goal: Goal) -> RunStatus {
+        // Starting the processes takes steps too, as in the parallel runners
+        let mut steps = self.ensure_started();
+        if let Some(status) = self.monitor.halted(steps) {
+            return status;
+        }
 
-        let mut steps = 0;
-        while steps < k {
-            if global::now() >= self.time_budget {
-                return RunStatus::BudgetExhausted { steps };
+        let deadline = match goal {
+            Goal::Deadline(deadline) => deadline.min(self.time_budget),
+            _ => self.time_budget,
+        };
+        self.last_delivery = global::now();
+        loop {
+            if let Some(status) = self.goal_reached(&mut goal, steps) {
+                return status;
+            }
+            let time = global::now();
+            if time >= deadline {
+                if time >= self.time_budget {
+                    self.progress_bar.finish();
+                    return self.finish(RunStatus::BudgetExhausted { steps });
+                }
+                return RunStatus::Completed { steps };
             }
             if self.actors.peek_next_step().is_none() {
-                return RunStatus::NoMoreEvents { steps };
+                return self.finish(RunStatus::NoMoreEvents { steps });
             }
             self.run_next_step();
             steps += 1;
+            if let Some(status) = self.monitor.halted(steps) {
+                return status;
+            }
         }
-        RunStatus::Completed { steps }
     }
 
-    fn run_sub_budget(&mut self, sub_budget: Jiffies) -> RunStatus {
-        self.ensure_started();
-
-        let deadline = global::now() + sub_budget;
-        let mut steps = 0;
-        while global::now() < deadline {
-            if global::now() >= self.time_budget {
-                return RunStatus::BudgetExhausted { steps };
-            }
-            if self.actors.peek_next_step().is_none() {
-                return RunStatus::NoMoreEvents { steps };
+    fn goal_reached(&self, goal: &mut Goal, steps: usize) -> Option<RunStatus> {
+        let time = global::now();
+        match goal {
+            Goal::Deadline(_) => None,
+            Goal::Steps(k) => (steps >= *k).then_some(RunStatus::Completed { steps }),
+            Goal::Predicate(predicate) => {
+                predicate(&self.view()).then_some(RunStatus::PredicateMet { steps, time })
             }
-            self.run_next_step();
-            steps += 1;
+            Goal::Quiescence(grace) => self
+                .quiescent(*grace)
+                .then_some(RunStatus::Quiescent { steps, time }),
         }
-        RunStatus::Completed { steps }
     }
-}
 
-impl SimpleRunner {
+    fn view(&self) -> View<'_> {
+        View::new(self.procs.iter().map(|p| &**p).collect())
+    }
+
+    /// No message or one-shot timer is pending and nothing but periodic timers
+    /// happens within the grace period.
+    fn quiescent(&self, grace: Jiffies) -> bool {
+        self.actors.idle()
+            && self
+                .actors
+                .peek_next_step()
+                .is_none_or(|next| next > self.last_delivery + grace)
+    }
+
+    /// Settles temporal properties once the simulation can not go any further.
+    fn finish(&mut self, status: RunStatus) -> RunStatus {
+        let horizon = match status {
+            RunStatus::NoMoreEvents { .. } => Jiffies(usize::MAX),
+            _ => global::now(),
+        };
+        let view = View::new(self.procs.iter().map(|p| &**p).collect());
+        self.monitor.settle(&view, horizon);
+        self.monitor.halted(status.steps()).unwrap_or(status)
+    }
+
     fn run_next_step(&mut self) {
         let next_time = self.actors.peek_next_step().expect("checked by caller");
         global::fast_forward_clock(next_time);
+        self.sampler.observe(next_time);
         self.progress_bar.make_progress(next_time);
         let step = self.actors.next_step();
         self.run_step(step);
@@ -124,25 +359,78 @@ impl SimpleRunner {
 
     fn run_step(&mut self, step: Step) {
         let task_id = (global::now(), global_unique_id());
+        if let Some(timeline) = &mut self.timeline {
+            timeline.record(global::now(), &step);
+        }
+        let rank = step.target_rank();
+        local_access::set_task(
+            task_id,
+            rank,
+            Handling::from(&step),
+            mem::take(&mut self.generators[rank]),
+        );
+        #[cfg(feature = "tracing")]
+        let span = crate::tracing_support::step_span(rank);
         match step {
             Step::Start { rank } => {
-                local_access::set_task(task_id, rank);
                 self.procs[rank].on_start();
             }
             Step::NetworkStep {
                 source,
                 target,
                 message,
+                ..
             } => {
-                local_access::set_task(task_id, target);
+                self.last_delivery = global::now();
                 self.procs[target].on_message(source, message);
             }
             Step::TimerStep { rank, id } => {
-                local_access::set_task(task_id, rank);
                 self.procs[rank].on_timer(id);
             }
         }
-        let mut events = local_access::take_events();
-        self.actors.submit(&mut events);
+        #[cfg(feature = "tracing")]
+        drop(span);
+        let (mut events, random) = local_access::take_events();
+        self.generators[rank] = random;
+        let churn = self.actors.submit(&mut events);
+
+        self.monitor.step_done();
+        if self.monitor.pending() {
+            let view = View::new(self.procs.iter().map(|p| &**p).collect());
+            self.monitor.check(&view);
+        }
+        churn.into_iter().for_each(|churn| self.apply(churn));
+    }
+
+    /// Adds a spawned process and starts it, or vacates the rank of an exited one.
+    fn apply(&mut self, churn: Churn) {
+        match churn {
+            Churn::Spawned {
+                rank,
+                pool,
+                factory,
+                index,
+                pool_size,
+            } => {
+                self.factories
+                    .insert(rank, factory, &pool, index, pool_size);
+                if self.procs.len() <= rank {
+                    self.procs.resize_with(rank + 1, || Box::new(Vacant));
+                    let seed = self.seed;
+                    let spawned = self.generators.len()..=rank;
+                    self.generators
+                        .extend(spawned.map(|rank| random::process_generators(seed, rank).0));
+                }
+                self.procs[rank] = self.factories.build(rank);
+                setup_local_configuration(rank, self.seed);
+                if let Some(timeline) = &mut self.timeline {
+                    timeline.add_process(rank, &pool);
+                }
+                self.run_step(Step::Start { rank });
+            }
+            Churn::Exited { rank } => {
+                self.procs[rank] = Box::new(Vacant);
+            }
+        }
     }
 }
@@ -1,14 +1,21 @@
-use std::{cmp::Reverse, collections::BinaryHeap};
-
-use crate::{Jiffies, global::local_access::EventBatch};
+use crate::{Jiffies, ProcessHandle, global::local_access::EventBatch, random::Randomizer};
 
+// Invocation time of the step, then a unique id keeping ties in scheduling order
 pub(crate) type TaskId = (Jiffies, usize);
 
-// Sorting by arrival time, then by id
-pub(super) type TaskIndex = BinaryHeap<Reverse<TaskId>>;
+/// State of a process together with the generator of its random choices.
+pub(crate) struct Snapshot {
+    pub(crate) proc: Box<dyn ProcessHandle + Send>,
+    pub(crate) random: Randomizer,
+}
 
 pub(crate) struct TaskResult {
     pub(crate) id: TaskId,
     pub(crate) rank: usize,
     pub(crate) events: EventBatch,
+    // The process that executed the step and its generator, handed back to `Workers`
+    pub(crate) proc: Option<Box<dyn ProcessHandle + Send>>,
+    pub(crate) random: Randomizer,
+    // State of the process before the step, kept by the optimistic runner only
+    pub(crate) before: Option<Snapshot>,
 }
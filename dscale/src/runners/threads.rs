@@ -6,9 +6,9 @@ pub enum Threads {
     Specific(usize),
 }
 
-impl Into<usize> for Threads {
-    fn into(self) -> usize {
-        match self {
+impl From<Threads> for usize {
+    fn from(threads: Threads) -> usize {
+        match threads {
             Threads::All => std::thread::available_parallelism()
                 .expect("could not acquire machine core number")
                 .get(),
@@ -1,100 +1,257 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    mem,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use crossbeam_channel::{Receiver, RecvError};
 
 use crate::{
-    ProcessHandle,
+    ProcessHandle, Rank,
+    factory::{Factories, Factory},
     global::{
         configuration::setup_local_configuration,
-        local_access::{self, setup_local_access},
+        local_access::{self, Handling, setup_local_access},
     },
-    random::Seed,
+    process_handle::Vacant,
+    random::{self, Randomizer, Seed},
     runners::{
+        report::WorkerCounters,
+        task::{Snapshot, TaskId, TaskResult},
         threads::Threads,
-        task::{TaskId, TaskResult},
     },
     step::Step,
 };
 
+type OwnedProcess = Box<dyn ProcessHandle + Send>;
+
 pub(crate) struct Workers {
-    procs: Vec<Arc<Mutex<dyn ProcessHandle + Send>>>,
+    // A process moves into the task of its step and comes back with the result,
+    // so it is `None` while it executes one
+    procs: Vec<Option<OwnedProcess>>,
+    // Generators of the random choices of every process, they travel with it
+    generators: Vec<Randomizer>,
+    factories: Factories,
+    seed: Seed,
     pool: rayon::ThreadPool,
     rx: Receiver<TaskResult>,
+    // Whether every step returns the state of its process before it
+    snapshots: bool,
+    threads: usize,
+    // Nanoseconds every thread spent executing steps
+    busy: Arc<[AtomicU64]>,
+    in_flight: usize,
+    // Steps handed out since the coordinator last waited for a result
+    window: usize,
+    counters: WorkerCounters,
 }
 
 impl Workers {
-    pub(crate) fn new(
-        procs: Vec<Arc<Mutex<dyn ProcessHandle + Send>>>,
-        threads: Threads,
-        seed: Seed,
-    ) -> Self {
+    pub(crate) fn new(factories: Factories, threads: Threads, seed: Seed) -> Self {
+        let procs: Vec<Option<OwnedProcess>> = (0..factories.len())
+            .map(|rank| Some(factories.build(rank)))
+            .collect();
         for id in 0..procs.len() {
             setup_local_configuration(id, seed);
         }
+        let generators = (0..procs.len())
+            .map(|rank| random::process_generators(seed, rank).0)
+            .collect();
         let threads_number: usize = threads.into();
         let (tx, rx) = crossbeam_channel::unbounded::<TaskResult>();
         log::warn!("Using {threads_number} threads for simulation");
         let pool = rayon::ThreadPoolBuilder::new()
             .num_threads(threads_number)
             .start_handler(move |_| {
-                setup_local_access(seed, tx.clone());
+                setup_local_access(tx.clone());
             })
             .build()
             .expect("Could not build thread pool");
-        Self { procs, pool, rx }
+        Self {
+            procs,
+            generators,
+            factories,
+            seed,
+            pool,
+            rx,
+            snapshots: false,
+            threads: threads_number,
+            busy: (0..threads_number).map(|_| AtomicU64::new(0)).collect(),
+            in_flight: 0,
+            window: 0,
+            counters: WorkerCounters::default(),
+        }
+    }
+
+    /// Makes every step return a snapshot of its process taken before it ran.
+    /// Panics if a process can not be snapshotted.
+    pub(crate) fn keep_snapshots(&mut self) {
+        self.snapshots = true;
+        for (rank, proc) in self.all().into_iter().enumerate() {
+            snapshot_of(proc, rank);
+        }
     }
 
     pub(crate) fn num_procs(&self) -> usize {
         self.procs.len()
     }
 
-    pub(crate) fn spawn_step(&self, task_id: TaskId, step: Step) {
-        let (proc_id, work) = Self::step_into_work(step);
-        let proc = self.procs[proc_id].clone();
-        self.pool.spawn(Self::wrap(task_id, proc_id, proc, work));
+    pub(crate) fn spawn_step(&mut self, task_id: TaskId, step: Step) {
+        let work = self.work(task_id, step);
+        self.pool.spawn(work);
     }
 
-    pub(crate) fn install_step(&self, task_id: TaskId, step: Step) {
-        let (proc_id, work) = Self::step_into_work(step);
-        let proc = self.procs[proc_id].clone();
-        self.pool.install(Self::wrap(task_id, proc_id, proc, work));
+    pub(crate) fn install_step(&mut self, task_id: TaskId, step: Step) {
+        let work = self.work(task_id, step);
+        self.pool.install(work);
     }
 
-    pub(crate) fn try_next_result(&self) -> Option<TaskResult> {
-        self.rx.try_recv().ok()
+    /// Replaces the process with its next incarnation.
+    /// Must not be called while it executes a step.
+    pub(crate) fn rebuild(&mut self, rank: usize) {
+        self.procs[rank] = Some(self.factories.rebuild(rank));
     }
 
-    pub(crate) fn next_result(&self) -> Result<TaskResult, RecvError> {
-        self.rx.recv()
+    /// Puts the process and its generator back into an earlier state.
+    /// Must not be called while it executes a step.
+    pub(crate) fn restore(&mut self, rank: Rank, state: Snapshot) {
+        self.procs[rank] = Some(state.proc);
+        self.generators[rank] = state.random;
     }
 
-    fn step_into_work(step: Step) -> (usize, Box<dyn FnOnce(&mut dyn ProcessHandle) + Send>) {
-        match step {
-            Step::Start { rank } => (rank, Box::new(|proc| proc.on_start())),
-            Step::NetworkStep {
-                source,
-                target,
-                message,
-            } => (
-                target,
-                Box::new(move |proc| proc.on_message(source, message)),
-            ),
-            Step::TimerStep { rank, id } => (rank, Box::new(move |proc| proc.on_timer(id))),
+    /// Adds a process spawned at runtime. Ranks may join out of order.
+    pub(crate) fn join(
+        &mut self,
+        rank: Rank,
+        factory: Factory,
+        pool: &str,
+        index: usize,
+        pool_size: usize,
+    ) {
+        self.factories.insert(rank, factory, pool, index, pool_size);
+        if self.procs.len() <= rank {
+            self.procs.resize_with(rank + 1, || Some(Box::new(Vacant)));
+            let seed = self.seed;
+            let spawned = self.generators.len()..=rank;
+            self.generators
+                .extend(spawned.map(|rank| random::process_generators(seed, rank).0));
         }
+        let proc = self.factories.build(rank);
+        if self.snapshots {
+            snapshot_of(&*proc, rank);
+        }
+        self.procs[rank] = Some(proc);
+        setup_local_configuration(rank, self.seed);
+    }
+
+    /// Drops the process of an exited rank. Must not be called while it executes a step.
+    pub(crate) fn vacate(&mut self, rank: Rank) {
+        self.procs[rank] = Some(Box::new(Vacant));
     }
 
-    fn wrap(
-        task_id: TaskId,
-        proc_id: usize,
-        proc: Arc<Mutex<dyn ProcessHandle + Send>>,
-        work: Box<dyn FnOnce(&mut dyn ProcessHandle) + Send>,
-    ) -> impl FnOnce() + Send {
+    /// Every process. Callers must make sure no step is in flight.
+    pub(crate) fn all(&self) -> Vec<&dyn ProcessHandle> {
+        self.procs
+            .iter()
+            .enumerate()
+            .map(|(rank, proc)| {
+                let proc = proc
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("P{rank} is executing a step"));
+                &**proc as &dyn ProcessHandle
+            })
+            .collect()
+    }
+
+    pub(crate) fn try_next_result(&mut self) -> Option<TaskResult> {
+        let result = self.rx.try_recv().ok()?;
+        Some(self.take_back(result))
+    }
+
+    pub(crate) fn next_result(&mut self) -> Result<TaskResult, RecvError> {
+        self.close_window();
+        let waiting = Instant::now();
+        let result = self.rx.recv()?;
+        self.counters.blocked += waiting.elapsed();
+        Ok(self.take_back(result))
+    }
+
+    /// Puts the process that executed the step and its generator back in place.
+    fn take_back(&mut self, mut result: TaskResult) -> TaskResult {
+        let proc = result.proc.take().expect("Result without its process");
+        self.procs[result.rank] = Some(proc);
+        self.generators[result.rank] = mem::take(&mut result.random);
+        self.in_flight -= 1;
+        result
+    }
+
+    /// A window is the batch of steps handed out before the coordinator waits
+    /// for their results. Accounts how many threads it kept busy.
+    fn close_window(&mut self) {
+        if self.window == 0 {
+            return;
+        }
+        self.counters.windows += 1;
+        self.counters.steps += mem::take(&mut self.window);
+        self.counters.occupied += self.in_flight.min(self.threads) as f64 / self.threads as f64;
+    }
+
+    /// Scheduling counters accumulated since the workers were created.
+    pub(crate) fn counters(&self) -> WorkerCounters {
+        WorkerCounters {
+            busy: self
+                .busy
+                .iter()
+                .map(|nanos| Duration::from_nanos(nanos.load(Ordering::Relaxed)))
+                .collect(),
+            ..self.counters.clone()
+        }
+    }
+
+    /// Moves the process and its generator into the task executing the step.
+    /// Panics if the process is executing another step.
+    fn work(&mut self, task_id: TaskId, step: Step) -> impl FnOnce() + Send + use<> {
+        let rank = step.target_rank();
+        let handling = Handling::from(&step);
+        let mut proc = self.procs[rank]
+            .take()
+            .unwrap_or_else(|| panic!("P{rank} is already executing a step"));
+        let random = mem::take(&mut self.generators[rank]);
+        let snapshots = self.snapshots;
+        let busy = self.busy.clone();
+        self.in_flight += 1;
+        self.window += 1;
         move || {
-            local_access::set_task(task_id, proc_id);
-            let mut guard = proc.lock().unwrap();
-            work(&mut *guard);
-            drop(guard);
-            local_access::done();
+            let started = Instant::now();
+            let before = snapshots.then(|| Snapshot {
+                proc: snapshot_of(&*proc, rank),
+                random: random.clone(),
+            });
+            local_access::set_task(task_id, rank, handling, random);
+            #[cfg(feature = "tracing")]
+            let span = crate::tracing_support::step_span(rank);
+            match step {
+                Step::Start { .. } => proc.on_start(),
+                Step::NetworkStep {
+                    source, message, ..
+                } => proc.on_message(source, message),
+                Step::TimerStep { id, .. } => proc.on_timer(id),
+            }
+            #[cfg(feature = "tracing")]
+            drop(span);
+            // Accounted before the result is sent, so the coordinator sees it with the result
+            let thread = rayon::current_thread_index().expect("Step outside the thread pool");
+            busy[thread].fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            local_access::done(proc, before);
         }
     }
 }
+
+fn snapshot_of(proc: &dyn ProcessHandle, rank: Rank) -> Box<dyn ProcessHandle + Send> {
+    proc.snapshot().unwrap_or_else(|| {
+        panic!("P{rank} can not be rolled back: implement ProcessHandle::snapshot for it")
+    })
+}
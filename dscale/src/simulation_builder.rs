@@ -1,8 +1,11 @@
 use std::{
     collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
+    rc::Rc,
+    sync::Arc,
 };
 
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::Codecs;
 use crate::{
     ProcessHandle, Rank,
     actors::{
@@ -10,16 +13,27 @@ use crate::{
         network_actor::{BandwidthConfig, NetworkActor},
         timer_actor::TimerActor,
     },
-    global,
+    event_queue::{EventQueue, EventQueueConfig},
+    factory::{Factories, Factory, FactoryCtx},
+    global::{self, kv::Cloners, metrics::Sampler},
     jiffy::Jiffies,
+    property::Property,
     random::Distributions,
     random::Seed,
     runners::{
-        SimulationRunner, scalable::ScalableRunner, simple::SimpleRunner, threads::Threads,
+        SimulationRunner,
+        monitor::{Monitor, Predicate},
+        optimistic::OptimisticRunner,
+        scalable::ScalableRunner,
+        simple::SimpleRunner,
+        threads::Threads,
         workers::Workers,
     },
     simulation_flavor::SimulationFlavor,
-    topology::{GLOBAL_POOL, LatencyTopology, PoolListing, Topology},
+    topology::{GLOBAL_POOL, HomePools, LatencyTopology, PoolListing, Topology},
+    trace::Timeline,
+    typed::Receives,
+    view::View,
 };
 
 fn init_logger() {
@@ -35,13 +49,27 @@ pub struct SimulationBuilder {
     seed: Seed,
     time_budget: Jiffies,
     proc_id: usize,
-    handles: Vec<Option<Box<dyn ProcessHandle + Send>>>,
+    factories: Factories,
     pools: HashMap<String, Vec<Rank>>,
-    latency_topology: LatencyTopology,
+    home_pools: HomePools,
+    receives: Vec<Receives>,
+    // Applied in order, so later rules override earlier ones
+    latency_rules: Vec<(String, String, Distributions)>,
+    spawn_targets: HashSet<String>,
     configured_pairs: HashSet<(String, String)>,
     bandwidth: BandwidthConfig,
+    event_queue: EventQueue,
     flavor: Option<SimulationFlavor>,
-    safe_parallel_window: Jiffies,
+    max_latency: Jiffies,
+    invariants: Vec<(&'static str, Predicate)>,
+    properties: Vec<(&'static str, Property)>,
+    check_period: usize,
+    metrics_period: Option<Jiffies>,
+    record_timeline: bool,
+    log_capacity: usize,
+    cloners: Cloners,
+    #[cfg(feature = "checkpoint")]
+    codecs: Codecs,
 }
 
 impl Default for SimulationBuilder {
@@ -50,13 +78,26 @@ impl Default for SimulationBuilder {
             seed: Seed::default(),
             time_budget: Jiffies::default(),
             proc_id: 0,
-            handles: Vec::new(),
+            factories: Factories::default(),
             pools: HashMap::default(),
-            latency_topology: LatencyTopology::default(),
+            home_pools: HomePools::default(),
+            receives: Vec::new(),
+            latency_rules: Vec::new(),
+            spawn_targets: HashSet::default(),
             configured_pairs: HashSet::default(),
             bandwidth: BandwidthConfig::default(),
+            event_queue: EventQueue::default(),
             flavor: None,
-            safe_parallel_window: Jiffies(usize::MAX),
+            max_latency: Jiffies(0),
+            invariants: Vec::new(),
+            properties: Vec::new(),
+            check_period: 1,
+            metrics_period: None,
+            record_timeline: false,
+            log_capacity: 0,
+            cloners: Cloners::default(),
+            #[cfg(feature = "checkpoint")]
+            codecs: Codecs::default(),
         }
     }
 }
@@ -65,15 +106,34 @@ impl SimulationBuilder {
     /// Creates a named pool of `size` processes of type `P`.
     /// Every process is also added to [`GLOBAL_POOL`].
     pub fn add_pool<P: ProcessHandle + Default + Send + 'static>(
+        self,
+        name: &str,
+        size: usize,
+    ) -> SimulationBuilder {
+        self.add_pool_with(name, size, |_, _| P::default())
+    }
+
+    /// Creates a named pool of `size` processes built by `factory` from their
+    /// rank and [`FactoryCtx`], e.g. to pass initial membership or per-node config.
+    ///
+    /// Processes are built by [`SimulationBuilder::build`]. The factory is kept to
+    /// rebuild a process on [`SimulationRunner::restart`].
+    /// Every process is also added to [`GLOBAL_POOL`].
+    pub fn add_pool_with<P: ProcessHandle + Send + 'static>(
         mut self,
         name: &str,
         size: usize,
+        factory: impl Fn(Rank, &FactoryCtx) -> P + Send + Sync + 'static,
     ) -> SimulationBuilder {
-        (0..size).for_each(|_| {
+        let factory: Factory = Arc::new(move |rank, ctx| Box::new(factory(rank, ctx)));
+        (0..size).for_each(|index| {
             let id = self.proc_id;
             self.proc_id += 1;
-            self.handles.push(Some(Box::new(P::default())));
+            self.factories
+                .insert(id, factory.clone(), name, index, size);
             self.pools.entry(name.to_string()).or_default().push(id);
+            self.home_pools.add(name);
+            self.receives.push(Receives::of::<P>());
             self.pools
                 .entry(GLOBAL_POOL.to_string())
                 .or_default()
@@ -108,53 +168,13 @@ impl SimulationBuilder {
     }
 
     fn apply_latency(&mut self, from: &str, to: &str, distr: Distributions) {
-        let from_vec: Vec<Rank> = self
-            .pools
-            .get(from)
-            .unwrap_or_else(|| panic!("No pool found: {from}"))
-            .clone();
-
-        let to_vec: Vec<Rank> = self
-            .pools
-            .get(to)
-            .unwrap_or_else(|| panic!("No pool found: {to}"))
-            .clone();
-
-        let cartesian_product = from_vec
-            .iter()
-            .flat_map(|x| to_vec.iter().map(move |y| (*x, *y)));
-
-        let cartesian_product_backwards = from_vec
-            .iter()
-            .flat_map(|x| to_vec.iter().map(move |y| (*y, *x)));
-
-        // Ensure matrix is large enough
-        let max_rank = from_vec
-            .iter()
-            .chain(to_vec.iter())
-            .copied()
-            .max()
-            .unwrap_or(0)
-            + 1;
-        if self.latency_topology.len() < max_rank {
-            self.latency_topology
-                .resize_with(max_rank, || vec![None; max_rank]);
-        }
-        for row in &mut self.latency_topology {
-            if row.len() < max_rank {
-                row.resize(max_rank, None);
-            }
+        for pool in [from, to] {
+            assert!(self.pools.contains_key(pool), "No pool found: {pool}");
         }
+        self.latency_rules
+            .push((from.to_string(), to.to_string(), distr));
 
-        cartesian_product.for_each(|(from, to)| {
-            self.latency_topology[from][to] = Some(distr.clone());
-        });
-
-        cartesian_product_backwards.for_each(|(from, to)| {
-            self.latency_topology[from][to] = Some(distr.clone());
-        });
-
-        self.safe_parallel_window = std::cmp::min(self.safe_parallel_window, distr.safe_window());
+        self.max_latency = std::cmp::max(self.max_latency, distr.upper_bound());
 
         let key = if from <= to {
             (from.to_string(), to.to_string())
@@ -164,17 +184,151 @@ impl SimulationBuilder {
         self.configured_pairs.insert(key);
     }
 
+    /// Lets processes of other pools spawn into the pool with the parallel runner,
+    /// see [`crate::spawn`]. Processes can always spawn into their own pool, and
+    /// with other runners into any pool.
+    ///
+    /// A spawned process starts at the time of the step that spawned it. The parallel
+    /// runner can not tell in advance which steps spawn, so the pool does not run
+    /// ahead of any other, and others only as far ahead as they could of the pool.
+    pub fn allow_spawn_into(mut self, pool: &str) -> Self {
+        assert!(self.pools.contains_key(pool), "No pool found: {pool}");
+        self.spawn_targets.insert(pool.to_string());
+        self
+    }
+
+    /// Latency of every home pool pair, so processes spawned at runtime
+    /// get the latency of their pool.
+    fn latency_topology(&self) -> LatencyTopology {
+        let pools = self.home_pools.names.len();
+        let expand = |pool: &str| match self.home_pools.index_of(pool) {
+            Some(index) => vec![index],
+            None => (0..pools).collect(), // GLOBAL_POOL
+        };
+        let mut latency_topology = vec![None; pools * pools];
+        for (from, to, distr) in &self.latency_rules {
+            for a in expand(from) {
+                for b in expand(to) {
+                    latency_topology[a * pools + b] = Some(*distr);
+                    latency_topology[b * pools + a] = Some(*distr);
+                }
+            }
+        }
+        latency_topology
+    }
+
     /// Configures per-process NIC bandwidth limits.
     pub fn vnic_bandwidth(mut self, bandwidth: BandwidthConfig) -> Self {
         self.bandwidth = bandwidth;
         self
     }
 
+    /// Selects the data structure keeping pending timers and messages in flight.
+    /// [`EventQueue::Calendar`] is faster when most events are due within its horizon.
+    pub fn event_queue(mut self, queue: EventQueue) -> Self {
+        self.event_queue = queue;
+        self
+    }
+
+    /// Registers a global invariant over the state of all processes.
+    ///
+    /// The invariant is evaluated after every step (see [`SimulationBuilder::check_invariants_every`]).
+    /// As soon as it returns `false` the run stops with [`crate::RunStatus::InvariantViolated`],
+    /// reporting the time, step and seed of the violation.
+    ///
+    /// The parallel runners evaluate it once the steps executing at that point have
+    /// finished, so the reported step and time may lie a few steps past the one that
+    /// broke the invariant. Use the simple runner to pinpoint it.
+    pub fn invariant(
+        mut self,
+        name: &'static str,
+        check: impl Fn(&View) -> bool + 'static,
+    ) -> Self {
+        self.invariants.push((name, Rc::new(check)));
+        self
+    }
+
+    /// Registers a temporal property (eventually, always-eventually, within deadline)
+    /// evaluated over simulated time at the same check points as invariants.
+    /// A violation stops the run with [`crate::RunStatus::LivenessViolated`].
+    pub fn property(mut self, name: &'static str, property: Property) -> Self {
+        self.properties.push((name, property));
+        self
+    }
+
+    /// Evaluates invariants and properties once every `steps` steps instead of after every step.
+    /// The parallel runners evaluate them once the steps in flight at that point have finished.
+    pub fn check_invariants_every(mut self, steps: usize) -> Self {
+        self.check_period = steps;
+        self
+    }
+
+    /// Samples every metric of [`crate::global::metrics`] once per `period` of simulated time.
+    /// The time series is available through [`crate::global::metrics::samples`].
+    pub fn sample_metrics_every(mut self, period: Jiffies) -> Self {
+        self.metrics_period = Some(period);
+        self
+    }
+
+    /// Records every handler invocation, available through [`SimulationRunner::timeline`]
+    /// and exportable as a Chrome trace with [`crate::Timeline::write_chrome_trace`].
+    pub fn record_timeline(mut self) -> Self {
+        self.record_timeline = true;
+        self
+    }
+
+    /// Captures the last `lines` lines every process logged with [`crate::log_process!`]
+    /// or [`crate::debug_process!`], queryable through [`crate::global::logs`].
+    ///
+    /// Capture does not depend on `RUST_LOG` and also works in release builds.
+    /// When a process handler panics, the logs of the involved processes are printed.
+    /// Any other panic, e.g. of a failed assertion, prints the logs of every process.
+    pub fn capture_process_logs(mut self, lines: usize) -> Self {
+        self.log_capacity = lines;
+        self
+    }
+
+    /// Lets forks copy key-value store entries of type `T`,
+    /// see [`SimulationRunner::fork`].
+    ///
+    /// Entries of integer, float, `bool` and `String` types are copied without it.
+    /// Forks leave out entries of types that are not registered.
+    pub fn fork_value<T: Clone + Send + Sync + 'static>(mut self) -> Self {
+        self.cloners.add::<T>();
+        self
+    }
+
+    /// Lets checkpoints hold messages of type `M` in flight,
+    /// see [`SimulationRunner::checkpoint`].
+    ///
+    /// `name` identifies the type in checkpoints, keep it when moving or renaming the type.
+    #[cfg(feature = "checkpoint")]
+    pub fn checkpoint_message<M>(mut self, name: &'static str) -> Self
+    where
+        M: crate::Message + serde::Serialize + serde::de::DeserializeOwned,
+    {
+        self.codecs.add_message::<M>(name);
+        self
+    }
+
+    /// Lets checkpoints hold key-value store entries of type `T`,
+    /// see [`SimulationRunner::checkpoint`].
+    ///
+    /// `name` identifies the type in checkpoints, keep it when moving or renaming the type.
+    #[cfg(feature = "checkpoint")]
+    pub fn checkpoint_value<T>(mut self, name: &'static str) -> Self
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+    {
+        self.codecs.add_value::<T>(name);
+        self
+    }
+
     /// Selects single-threaded execution mode (default).
     pub fn simple(mut self) -> Self {
         assert!(
             self.flavor.is_none(),
-            "Execution mode already set; cannot call both simple() and parallel()"
+            "Execution mode already set; call only one of simple(), parallel(), optimistic() and calibrate()"
         );
         self.flavor = Some(SimulationFlavor::Simple);
         self
@@ -184,24 +338,114 @@ impl SimulationBuilder {
     pub fn parallel(mut self, threads: Threads) -> Self {
         assert!(
             self.flavor.is_none(),
-            "Execution mode already set; cannot call both simple() and parallel()"
+            "Execution mode already set; call only one of simple(), parallel(), optimistic() and calibrate()"
         );
         self.flavor = Some(SimulationFlavor::Parallel(threads));
         self
     }
 
+    /// Selects optimistic parallel execution using the given number of worker threads.
+    ///
+    /// Steps up to `window` jiffies later than the earliest unfinished one run
+    /// speculatively. A process that receives a step earlier than one it already
+    /// ran is rolled back to a [`ProcessHandle::snapshot`], and the messages and
+    /// timers of the undone steps are taken back. Every process must implement
+    /// [`ProcessHandle::snapshot`], bandwidth must be unbounded and processes
+    /// can not spawn, exit or change pool membership.
+    pub fn optimistic(mut self, threads: Threads, window: Jiffies) -> Self {
+        assert!(
+            self.flavor.is_none(),
+            "Execution mode already set; call only one of simple(), parallel(), optimistic() and calibrate()"
+        );
+        self.flavor = Some(SimulationFlavor::Optimistic { threads, window });
+        self
+    }
+
+    /// Picks the execution mode from a short calibration run: the first `steps`
+    /// steps run single-threaded and in parallel with 2, 4, 8, ... up to
+    /// `max_threads` threads, and the mode executing the most steps per second
+    /// is kept. See [`crate::RunReport::parallel`] for the mode that was picked.
+    ///
+    /// Calibration happens in [`SimulationBuilder::build`], on copies of the
+    /// simulation without invariants, properties, metrics sampling and timeline.
+    /// Their handlers run for real, so whatever they do outside of their
+    /// process (key-value store, metrics, statics) happens once per candidate,
+    /// and global state is cleared after every one: set it up after `build`.
+    pub fn calibrate(mut self, max_threads: Threads, steps: usize) -> Self {
+        assert!(
+            self.flavor.is_none(),
+            "Execution mode already set; call only one of simple(), parallel(), optimistic() and calibrate()"
+        );
+        self.flavor = Some(SimulationFlavor::Calibrated { max_threads, steps });
+        self
+    }
+
+    /// Runs the first `steps` steps with every candidate thread count,
+    /// 1 standing for single-threaded execution, and returns the fastest mode.
+    fn calibrated_flavor(&self, max_threads: usize, steps: usize) -> SimulationFlavor {
+        let mut candidates: Vec<usize> =
+            std::iter::successors(Some(1), |threads| Some(threads * 2))
+                .take_while(|&threads| threads <= max_threads)
+                .collect();
+        if !max_threads.is_power_of_two() {
+            candidates.push(max_threads);
+        }
+        let flavor = |threads| match threads {
+            1 => SimulationFlavor::Simple,
+            threads => SimulationFlavor::Parallel(Threads::Specific(threads)),
+        };
+        if candidates.len() == 1 {
+            return SimulationFlavor::Simple;
+        }
+        let (threads, rate) = candidates
+            .into_iter()
+            .map(|threads| {
+                let mut trial = self.trial(flavor(threads)).build();
+                (threads, trial.run_steps(steps).steps_per_sec())
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .expect("No candidate");
+        log::warn!("Calibration picked {threads} threads ({rate:.0} steps/sec)");
+        flavor(threads)
+    }
+
+    /// Copy of the simulation to calibrate with, without anything that only
+    /// observes it.
+    fn trial(&self, flavor: SimulationFlavor) -> SimulationBuilder {
+        SimulationBuilder {
+            seed: self.seed,
+            time_budget: self.time_budget,
+            proc_id: self.proc_id,
+            factories: self.factories.clone(),
+            pools: self.pools.clone(),
+            home_pools: self.home_pools.clone(),
+            receives: self.receives.clone(),
+            latency_rules: self.latency_rules.clone(),
+            spawn_targets: self.spawn_targets.clone(),
+            configured_pairs: self.configured_pairs.clone(),
+            bandwidth: self.bandwidth,
+            event_queue: self.event_queue,
+            flavor: Some(flavor),
+            max_latency: self.max_latency,
+            ..SimulationBuilder::default()
+        }
+    }
+
     /// Finalizes configuration and builds the simulation runner.
     pub fn build(mut self) -> Box<dyn SimulationRunner> {
         init_logger();
+        global::install_panic_report();
+        let flavor = match self.flavor.take().unwrap_or_default() {
+            SimulationFlavor::Calibrated { max_threads, steps } => {
+                self.calibrated_flavor(max_threads.into(), steps)
+            }
+            flavor => flavor,
+        };
+        // Other simulations stay alive, their globals are parked
+        global::enter();
 
         let mut pool_listing = PoolListing::default();
-
-        // Ensure latency_topology matrix is sized for all processes
         let n = self.proc_id;
-        self.latency_topology.resize_with(n, || vec![None; n]);
-        for row in &mut self.latency_topology {
-            row.resize(n, None);
-        }
 
         // Validate that every pair of non-global pools has latency configured.
         let mut user_pools: Vec<&String> = self
@@ -221,52 +465,95 @@ impl SimulationBuilder {
             }
         }
 
+        let latency_topology = self.latency_topology();
         for (name, ids) in self.pools {
-            pool_listing.insert(name, ids);
+            pool_listing.insert(name, ids.into());
         }
 
-        let topology = Topology::new_arc(pool_listing.clone(), self.latency_topology);
-        let network_actor = NetworkActor::new(self.seed, self.bandwidth, topology.clone());
-        let timers_actor = TimerActor::default();
+        let timeline = self
+            .record_timeline
+            .then(|| Timeline::new(&self.home_pools));
+        // Only the parallel runner has to know in advance where processes spawn
+        let spawn_targets = matches!(flavor, SimulationFlavor::Parallel(_)).then(|| {
+            let names = &self.home_pools.names;
+            names
+                .iter()
+                .map(|name| self.spawn_targets.contains(name))
+                .collect()
+        });
+        let topology = Topology::new_arc(
+            pool_listing,
+            latency_topology,
+            self.home_pools,
+            self.receives,
+            spawn_targets,
+            !matches!(flavor, SimulationFlavor::Optimistic { .. }),
+        );
+        let queue = EventQueueConfig::new(self.event_queue, self.max_latency);
+        let network_actor = NetworkActor::new(self.seed, self.bandwidth, topology.clone(), queue);
+        let timers_actor = TimerActor::new(queue);
         let actors = Actors {
             network: network_actor,
             timers: timers_actor,
+            topology: topology.clone(),
         };
 
         global::configuration::setup_global_configuration(n);
         global::setup_shared_access(topology);
+        global::logs::setup(self.log_capacity);
+
+        let monitor = Monitor::new(
+            self.invariants,
+            self.properties,
+            self.check_period,
+            self.seed,
+        );
+        let sampler = Sampler::new(self.metrics_period);
 
-        match self.flavor.unwrap_or_default() {
+        match flavor {
             SimulationFlavor::Simple => {
-                let procs: Vec<Box<dyn ProcessHandle>> = self
-                    .handles
-                    .into_iter()
-                    .map(|opt| opt.expect("Uninitialized process slot") as Box<dyn ProcessHandle>)
-                    .collect();
-                Box::new(SimpleRunner::new(
+                let runner = SimpleRunner::new(
                     actors,
                     self.time_budget,
-                    procs,
+                    self.factories,
                     self.seed,
-                ))
+                    monitor,
+                    sampler,
+                    timeline,
+                )
+                .with_cloners(self.cloners);
+                #[cfg(feature = "checkpoint")]
+                let runner = runner.with_codecs(self.codecs);
+                Box::new(runner)
             }
             SimulationFlavor::Parallel(cores) => {
-                let procs: Vec<Arc<Mutex<dyn ProcessHandle + Send>>> = self
-                    .handles
-                    .into_iter()
-                    .map(|opt| {
-                        let handle = opt.expect("Uninitialized process slot");
-                        Arc::new(Mutex::new(handle)) as Arc<Mutex<dyn ProcessHandle + Send>>
-                    })
-                    .collect();
-                let workers = Workers::new(procs, cores, self.seed);
+                let workers = Workers::new(self.factories, cores, self.seed);
                 Box::new(ScalableRunner::new(
                     actors,
                     self.time_budget,
                     workers,
-                    self.safe_parallel_window,
+                    monitor,
+                    sampler,
+                    timeline,
+                ))
+            }
+            SimulationFlavor::Optimistic { threads, window } => {
+                assert!(
+                    matches!(self.bandwidth, BandwidthConfig::Unbounded),
+                    "The optimistic runner requires unbounded bandwidth"
+                );
+                let workers = Workers::new(self.factories, threads, self.seed);
+                Box::new(OptimisticRunner::new(
+                    actors,
+                    self.time_budget,
+                    workers,
+                    window,
+                    monitor,
+                    sampler,
+                    timeline,
                 ))
             }
+            SimulationFlavor::Calibrated { .. } => unreachable!("Calibrated before"),
         }
     }
 }
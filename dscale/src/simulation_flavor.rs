@@ -1,8 +1,16 @@
-use crate::runners::threads::Threads;
+use crate::{jiffy::Jiffies, runners::threads::Threads};
 
 #[derive(Default)]
 pub(crate) enum SimulationFlavor {
     #[default]
     Simple,
     Parallel(Threads),
+    Optimistic {
+        threads: Threads,
+        window: Jiffies,
+    },
+    Calibrated {
+        max_threads: Threads,
+        steps: usize,
+    },
 }
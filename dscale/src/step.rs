@@ -1,7 +1,10 @@
-use std::{cmp::Reverse, collections::BinaryHeap};
-
-use crate::{Jiffies, MessagePtr, Rank, TimerId};
+use crate::{
+    Jiffies, MessagePtr, Rank, TimerId,
+    event_queue::{FutureEvents, Timed},
+};
 
+#[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum Step {
     Start {
         rank: Rank,
@@ -10,6 +13,7 @@ pub(crate) enum Step {
         source: Rank,
         target: Rank,
         message: MessagePtr,
+        sent_at: Jiffies,
     },
     TimerStep {
         rank: Rank,
@@ -27,41 +31,16 @@ impl Step {
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct TimedStep {
     pub(crate) invocation_time: Jiffies,
     pub(crate) step: Step,
 }
 
-impl PartialEq for TimedStep {
-    fn eq(&self, other: &Self) -> bool {
-        self.invocation_time.eq(&other.invocation_time)
-    }
-}
-
-impl Eq for TimedStep {}
-
-impl PartialOrd for TimedStep {
-    fn ge(&self, other: &Self) -> bool {
-        self.invocation_time.ge(&other.invocation_time)
-    }
-    fn le(&self, other: &Self) -> bool {
-        self.invocation_time.le(&other.invocation_time)
-    }
-    fn gt(&self, other: &Self) -> bool {
-        self.invocation_time.gt(&other.invocation_time)
-    }
-    fn lt(&self, other: &Self) -> bool {
-        self.invocation_time.lt(&other.invocation_time)
-    }
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.invocation_time.partial_cmp(&other.invocation_time)
-    }
-}
-
-impl Ord for TimedStep {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.invocation_time.cmp(&other.invocation_time)
+impl Timed for TimedStep {
+    fn time(&self) -> Jiffies {
+        self.invocation_time
     }
 }
 
-pub(crate) type StepQueue = BinaryHeap<Reverse<TimedStep>>;
+pub(crate) type StepQueue = FutureEvents<TimedStep>;
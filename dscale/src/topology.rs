@@ -1,37 +1,356 @@
-use std::sync::Arc;
+use std::sync::{
+    Arc, OnceLock, RwLock, RwLockReadGuard,
+    atomic::{AtomicUsize, Ordering},
+};
 
 use rustc_hash::FxHashMap;
 
-use crate::{Rank, random::Distributions};
+use crate::{Jiffies, Rank, random::Distributions, typed::Receives};
 
-pub(crate) type LatencyTopology = Vec<Vec<Option<Distributions>>>;
-pub(crate) type PoolListing = FxHashMap<String, Vec<Rank>>;
+/// Latency of every `(home pool, home pool)` pair, flattened like [`Topology::pool_pair`].
+pub(crate) type LatencyTopology = Vec<Option<Distributions>>;
+pub(crate) type PoolListing = FxHashMap<String, PoolMembers>;
 
 /// Name of the implicit pool that contains every process.
 pub const GLOBAL_POOL: &str = "global_pool";
 
+/// Ranks in a pool. Joins append in place, the shared listing handed out by
+/// [`Topology::list_pool`] is built on the first read after a change.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PoolMembers {
+    ranks: Vec<Rank>,
+    listed: OnceLock<Arc<[Rank]>>,
+}
+
+impl PoolMembers {
+    fn listed(&self) -> Arc<[Rank]> {
+        self.listed
+            .get_or_init(|| self.ranks.as_slice().into())
+            .clone()
+    }
+
+    fn push(&mut self, rank: Rank) -> usize {
+        self.ranks.push(rank);
+        self.listed.take();
+        self.ranks.len()
+    }
+
+    /// Returns whether the process was in the pool.
+    fn remove(&mut self, rank: Rank) -> bool {
+        let Some(position) = self.ranks.iter().position(|&r| r == rank) else {
+            return false;
+        };
+        self.ranks.remove(position);
+        self.listed.take();
+        true
+    }
+}
+
+impl From<Vec<Rank>> for PoolMembers {
+    fn from(ranks: Vec<Rank>) -> Self {
+        Self {
+            ranks,
+            listed: OnceLock::new(),
+        }
+    }
+}
+
+/// Pool every process was added with. Unlike [`PoolListing`], each rank
+/// belongs to exactly one home pool, so traffic can be accounted per pool pair.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct HomePools {
+    pub(crate) names: Vec<String>,
+    pub(crate) of_rank: Vec<usize>,
+}
+
+impl HomePools {
+    pub(crate) fn add(&mut self, name: &str) {
+        let index = self.index_of(name).unwrap_or_else(|| {
+            self.names.push(name.to_string());
+            self.names.len() - 1
+        });
+        self.of_rank.push(index);
+    }
+
+    pub(crate) fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+/// Processes of the simulation. Changes as processes are spawned and exit.
+#[derive(Debug, Clone)]
+struct Membership {
+    pool_listing: PoolListing,
+    home_pools: HomePools,
+    alive: Vec<bool>,
+    receives: Vec<Receives>,
+    // Processes that ever joined each home pool
+    joined: Vec<usize>,
+    // Whether processes spawned, exited or changed pools since the build
+    changed: bool,
+}
+
+/// Where a spawned process was placed.
+pub(crate) struct Joined {
+    /// Number of processes that joined the home pool before.
+    pub(crate) index: usize,
+    /// Processes in the home pool, including the spawned one.
+    pub(crate) pool_size: usize,
+}
+
 #[derive(Debug)]
 pub(crate) struct Topology {
-    pool_listing: PoolListing,
+    // Home pools never change after build, only their members do
+    pool_names: Vec<String>,
     latency_topology: LatencyTopology,
+    // Home pools processes can be spawned into, if the runner restricts it
+    spawn_targets: Option<Vec<bool>>,
+    // Whether processes can spawn, exit and change pools while the simulation runs
+    churn: bool,
+    membership: RwLock<Membership>,
+    next_rank: AtomicUsize,
 }
 
 impl Topology {
     pub(crate) fn new_arc(
         pool_listing: PoolListing,
         latency_topology: LatencyTopology,
+        home_pools: HomePools,
+        receives: Vec<Receives>,
+        spawn_targets: Option<Vec<bool>>,
+        churn: bool,
     ) -> Arc<Self> {
+        let process_number = home_pools.of_rank.len();
+        let mut joined = vec![0; home_pools.names.len()];
+        home_pools
+            .of_rank
+            .iter()
+            .for_each(|&pool| joined[pool] += 1);
         Arc::new(Self {
-            pool_listing,
+            pool_names: home_pools.names.clone(),
             latency_topology,
+            spawn_targets,
+            churn,
+            membership: RwLock::new(Membership {
+                pool_listing,
+                home_pools,
+                alive: vec![true; process_number],
+                receives,
+                joined,
+                changed: false,
+            }),
+            next_rank: AtomicUsize::new(process_number),
         })
     }
 
+    /// Independent copy, for a fork of the simulation.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            pool_names: self.pool_names.clone(),
+            latency_topology: self.latency_topology.clone(),
+            spawn_targets: self.spawn_targets.clone(),
+            churn: self.churn,
+            membership: RwLock::new(self.membership.read().unwrap().clone()),
+            next_rank: AtomicUsize::new(self.process_number()),
+        }
+    }
+
+    pub(crate) fn pool_names(&self) -> &[String] {
+        &self.pool_names
+    }
+
+    /// Reads the membership once for many lookups, e.g. for every target of a send.
+    /// Processes can not join or leave while it is held.
+    pub(crate) fn members(&self) -> Members<'_> {
+        Members {
+            pool_count: self.pool_names.len(),
+            latency_topology: &self.latency_topology,
+            membership: self.membership.read().unwrap(),
+        }
+    }
+
+    /// Index of the home pool of the process in [`Topology::pool_names`].
+    pub(crate) fn home_pool(&self, rank: Rank) -> usize {
+        self.membership.read().unwrap().home_pools.of_rank[rank]
+    }
+
+    /// Index of the `(home pool of from, home pool of to)` pair in a flattened pool matrix.
+    pub(crate) fn pool_pair(&self, from: Rank, to: Rank) -> usize {
+        self.members().pool_pair(from, to)
+    }
+
+    /// Minimum latency from a process of home pool `from` to one of home pool `to`.
+    pub(crate) fn lookahead(&self, from: usize, to: usize) -> Jiffies {
+        self.latency_topology[from * self.pool_names.len() + to]
+            .map_or(Jiffies(0), |distr| distr.safe_window())
+    }
+
+    /// Whether processes can be spawned into the home pool. Every pool, unless
+    /// the runner restricts it to those given to [`crate::SimulationBuilder::allow_spawn_into`].
+    pub(crate) fn can_spawn_into(&self, pool: usize) -> bool {
+        self.spawn_targets
+            .as_ref()
+            .is_none_or(|targets| targets[pool])
+    }
+
+    /// Whether the runner supports spawning, exiting and changing pools.
+    pub(crate) fn allows_churn(&self) -> bool {
+        self.churn
+    }
+
+    pub(crate) fn list_pool(&self, pool_name: &str) -> Arc<[Rank]> {
+        self.membership
+            .read()
+            .unwrap()
+            .pool_listing
+            .get(pool_name)
+            .expect("Invalid pool name")
+            .listed()
+    }
+
+    /// Whether the process joined the simulation and has not exited.
+    pub(crate) fn is_alive(&self, rank: Rank) -> bool {
+        self.members().is_alive(rank)
+    }
+
+    /// What the process was built to receive.
+    pub(crate) fn receives(&self, rank: Rank) -> Receives {
+        self.membership
+            .read()
+            .unwrap()
+            .receives
+            .get(rank)
+            .copied()
+            .unwrap_or(Receives::Unknown)
+    }
+
+    /// Number of ranks handed out so far, including exited processes.
+    pub(crate) fn process_number(&self) -> usize {
+        self.next_rank.load(Ordering::Acquire)
+    }
+
+    /// Hands out the rank of a process that is about to be spawned.
+    pub(crate) fn reserve_rank(&self) -> Rank {
+        self.next_rank.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// Adds a spawned process to its home pool and to [`GLOBAL_POOL`].
+    pub(crate) fn join(&self, rank: Rank, pool: &str, receives: Receives) -> Joined {
+        let home = self
+            .pool_names
+            .iter()
+            .position(|name| name == pool)
+            .unwrap_or_else(|| panic!("Can not spawn into unknown pool {pool}"));
+        let mut membership = self.membership.write().unwrap();
+        let membership = &mut *membership;
+        // Ranks reserved concurrently may join out of order
+        if membership.home_pools.of_rank.len() <= rank {
+            membership.home_pools.of_rank.resize(rank + 1, home);
+            membership.alive.resize(rank + 1, false);
+            membership.receives.resize(rank + 1, Receives::Unknown);
+        }
+        let index = membership.joined[home];
+        membership.joined[home] += 1;
+        membership.home_pools.of_rank[rank] = home;
+        membership.alive[rank] = true;
+        membership.receives[rank] = receives;
+        membership.changed = true;
+        let pool_size = Self::push(&mut membership.pool_listing, pool, rank);
+        Self::push(&mut membership.pool_listing, GLOBAL_POOL, rank);
+        Joined { index, pool_size }
+    }
+
+    /// Removes an exited process from every pool.
+    pub(crate) fn leave(&self, rank: Rank) {
+        let mut membership = self.membership.write().unwrap();
+        membership.alive[rank] = false;
+        membership.changed = true;
+        for members in membership.pool_listing.values_mut() {
+            members.remove(rank);
+        }
+    }
+
+    /// Creates an empty pool, unless it already exists.
+    /// Processes are added with [`Topology::add_to_pool`].
+    pub(crate) fn create_pool(&self, pool: &str) {
+        let mut membership = self.membership.write().unwrap();
+        if !membership.pool_listing.contains_key(pool) {
+            membership
+                .pool_listing
+                .insert(pool.to_string(), PoolMembers::default());
+            membership.changed = true;
+        }
+    }
+
+    /// Adds a live process to the pool, unless it is already there.
+    pub(crate) fn add_to_pool(&self, pool: &str, rank: Rank) {
+        let mut membership = self.membership.write().unwrap();
+        let membership = &mut *membership;
+        let members = membership
+            .pool_listing
+            .get_mut(pool)
+            .unwrap_or_else(|| panic!("Can not join unknown pool {pool}"));
+        let alive = membership.alive.get(rank).copied().unwrap_or(false);
+        if alive && !members.ranks.contains(&rank) {
+            members.push(rank);
+            membership.changed = true;
+        }
+    }
+
+    /// Removes the process from the pool, if it is there.
+    pub(crate) fn remove_from_pool(&self, pool: &str, rank: Rank) {
+        let mut membership = self.membership.write().unwrap();
+        let removed = membership
+            .pool_listing
+            .get_mut(pool)
+            .unwrap_or_else(|| panic!("Can not leave unknown pool {pool}"))
+            .remove(rank);
+        membership.changed |= removed;
+    }
+
+    /// Whether processes spawned, exited or changed pools since the build.
+    pub(crate) fn membership_changed(&self) -> bool {
+        self.membership.read().unwrap().changed
+    }
+
+    fn push(pool_listing: &mut PoolListing, pool: &str, rank: Rank) -> usize {
+        match pool_listing.get_mut(pool) {
+            Some(members) => members.push(rank),
+            None => pool_listing.entry(pool.to_string()).or_default().push(rank),
+        }
+    }
+}
+
+/// Membership of the processes, read once, see [`Topology::members`].
+pub(crate) struct Members<'a> {
+    pool_count: usize,
+    latency_topology: &'a LatencyTopology,
+    membership: RwLockReadGuard<'a, Membership>,
+}
+
+impl Members<'_> {
+    /// See [`Topology::pool_pair`].
+    pub(crate) fn pool_pair(&self, from: Rank, to: Rank) -> usize {
+        let of_rank = &self.membership.home_pools.of_rank;
+        of_rank[from] * self.pool_count + of_rank[to]
+    }
+
     pub(crate) fn get_distribution(&self, from: Rank, to: Rank) -> Distributions {
-        self.latency_topology[from][to].expect("No distr found")
+        self.latency_topology[self.pool_pair(from, to)].expect("No distr found")
+    }
+
+    /// Current members of the pool, without sharing them like [`Topology::list_pool`].
+    pub(crate) fn pool(&self, pool_name: &str) -> &[Rank] {
+        &self
+            .membership
+            .pool_listing
+            .get(pool_name)
+            .expect("Invalid pool name")
+            .ranks
     }
 
-    pub(crate) fn list_pool(&self, pool_name: &str) -> &[usize] {
-        self.pool_listing.get(pool_name).expect("Invalid pool name")
+    /// See [`Topology::is_alive`].
+    pub(crate) fn is_alive(&self, rank: Rank) -> bool {
+        self.membership.alive.get(rank).copied().unwrap_or(false)
     }
 }
@@ -0,0 +1,190 @@
+use std::io::{self, Write};
+
+use crate::{
+    Jiffies, Rank,
+    trace::{Timeline, TraceKind},
+};
+
+/// Selects what part of a [`Timeline`] is rendered as a sequence chart.
+///
+/// By default every message of the run between any processes is shown.
+#[derive(Debug, Clone, Default)]
+pub struct ChartFilter {
+    from: Jiffies,
+    to: Option<Jiffies>,
+    ranks: Option<Vec<Rank>>,
+    messages: Option<Vec<String>>,
+}
+
+impl ChartFilter {
+    /// Shows only messages sent and delivered within `[from, to]`.
+    pub fn window(mut self, from: Jiffies, to: Jiffies) -> Self {
+        self.from = from;
+        self.to = Some(to);
+        self
+    }
+
+    /// Shows only the given processes and the messages between them.
+    /// Ranks of processes not in the timeline are ignored.
+    pub fn ranks(mut self, ranks: impl IntoIterator<Item = Rank>) -> Self {
+        self.ranks = Some(ranks.into_iter().collect());
+        self
+    }
+
+    /// Shows only messages with the given labels.
+    /// Both full labels and type names without module path match.
+    pub fn messages<S: Into<String>>(mut self, labels: impl IntoIterator<Item = S>) -> Self {
+        self.messages = Some(labels.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Time window set with [`ChartFilter::window`].
+    pub(crate) fn bounds(&self) -> Option<(Jiffies, Jiffies)> {
+        self.to.map(|to| (self.from, to))
+    }
+
+    fn shows_rank(&self, rank: Rank) -> bool {
+        self.ranks
+            .as_ref()
+            .is_none_or(|ranks| ranks.contains(&rank))
+    }
+
+    fn shows_message(&self, label: &str) -> bool {
+        self.messages.as_ref().is_none_or(|messages| {
+            messages
+                .iter()
+                .any(|m| m == label || m == short_label(label))
+        })
+    }
+}
+
+/// Strips the module path from default type name labels.
+pub(crate) fn short_label(label: &str) -> &str {
+    if label.contains('<') {
+        return label;
+    }
+    label.rsplit("::").next().unwrap_or(label)
+}
+
+/// Keeps labels from breaking lines or being read as markup by PlantUML.
+fn plantuml_escape(label: &str) -> String {
+    label
+        .replace(['\\', '\n', '\r'], " ")
+        .replace('~', "~~")
+        .replace('<', "~<")
+        .replace('>', "~>")
+}
+
+pub(crate) struct Arrow<'a> {
+    pub(crate) source: Rank,
+    pub(crate) target: Rank,
+    pub(crate) sent_at: Jiffies,
+    pub(crate) delivered_at: Jiffies,
+    pub(crate) label: &'a str,
+}
+
+impl Timeline {
+    /// Filtered messages ordered by send time.
+    pub(crate) fn arrows(&self, filter: &ChartFilter) -> Vec<Arrow<'_>> {
+        let to = filter.to.unwrap_or(Jiffies(usize::MAX));
+        let mut arrows: Vec<Arrow> = self
+            .events()
+            .iter()
+            .filter_map(|event| match &event.kind {
+                TraceKind::Message {
+                    source,
+                    sent_at,
+                    label,
+                } => Some(Arrow {
+                    source: *source,
+                    target: event.rank,
+                    sent_at: *sent_at,
+                    delivered_at: event.time,
+                    label: short_label(label),
+                })
+                .filter(|_| filter.shows_message(label)),
+                _ => None,
+            })
+            .filter(|arrow| {
+                filter.from <= arrow.sent_at
+                    && arrow.delivered_at <= to
+                    && filter.shows_rank(arrow.source)
+                    && filter.shows_rank(arrow.target)
+            })
+            .collect();
+        arrows.sort_by_key(|arrow| (arrow.sent_at, arrow.delivered_at));
+        arrows
+    }
+
+    /// Processes drawn as lifelines: the selected ones, or every process taking part in a message.
+    pub(crate) fn participants(&self, filter: &ChartFilter, arrows: &[Arrow]) -> Vec<Rank> {
+        let mut ranks: Vec<Rank> = match &filter.ranks {
+            Some(ranks) => ranks
+                .iter()
+                .copied()
+                .filter(|&rank| rank < self.process_number())
+                .collect(),
+            None => arrows
+                .iter()
+                .flat_map(|arrow| [arrow.source, arrow.target])
+                .collect(),
+        };
+        ranks.sort_unstable();
+        ranks.dedup();
+        ranks
+    }
+
+    /// Writes the filtered messages as a [Mermaid](https://mermaid.js.org) sequence diagram.
+    ///
+    /// Arrows are ordered by send time and labeled with send and delivery times.
+    pub fn write_mermaid(&self, filter: &ChartFilter, mut out: impl Write) -> io::Result<()> {
+        let arrows = self.arrows(filter);
+        writeln!(out, "sequenceDiagram")?;
+        for rank in self.participants(filter, &arrows) {
+            writeln!(
+                out,
+                "    participant P{rank} as P{rank} ({})",
+                self.pool_of(rank)
+            )?;
+        }
+        for arrow in arrows {
+            writeln!(
+                out,
+                "    P{}->>P{}: {} ({}..{})",
+                arrow.source,
+                arrow.target,
+                arrow.label.replace([';', '#'], " "),
+                arrow.sent_at.0,
+                arrow.delivered_at.0
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the filtered messages as a [PlantUML](https://plantuml.com) sequence diagram.
+    ///
+    /// Arrows are ordered by send time and labeled with send and delivery times.
+    pub fn write_plantuml(&self, filter: &ChartFilter, mut out: impl Write) -> io::Result<()> {
+        let arrows = self.arrows(filter);
+        writeln!(out, "@startuml")?;
+        for rank in self.participants(filter, &arrows) {
+            writeln!(
+                out,
+                "participant \"P{rank} ({})\" as P{rank}",
+                self.pool_of(rank).replace('"', "'")
+            )?;
+        }
+        for arrow in arrows {
+            writeln!(
+                out,
+                "P{} -> P{} : {} ({}..{})",
+                arrow.source,
+                arrow.target,
+                plantuml_escape(arrow.label),
+                arrow.sent_at.0,
+                arrow.delivered_at.0
+            )?;
+        }
+        writeln!(out, "@enduml")
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    Jiffies, Rank,
+    trace::{Timeline, TraceKind, chart::short_label},
+};
+
+// One jiffy is displayed as one millisecond. Handlers take no simulated time,
+// so each one is drawn as a 1us slice, shifted when a process runs several
+// handlers within the same jiffy.
+const US_PER_JIFFY: usize = 1000;
+
+fn ts(time: Jiffies, nth: usize) -> usize {
+    time.0 * US_PER_JIFFY + nth.min(US_PER_JIFFY - 1)
+}
+
+fn escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| match c {
+            '"' => vec!['\\', '"'],
+            '\\' => vec!['\\', '\\'],
+            c if c.is_control() => format!("\\u{:04x}", c as u32).chars().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
+
+impl Timeline {
+    /// Writes the timeline in the Chrome trace event format, readable by
+    /// `chrome://tracing` and [Perfetto](https://ui.perfetto.dev).
+    ///
+    /// Every process gets its own track with a slice per handler invocation.
+    /// Messages are drawn as flow arrows from the sending process at the send
+    /// time to the slice that handled the delivery.
+    pub fn write_chrome_trace(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        let mut first = true;
+        let mut event = |out: &mut dyn Write, json: String| -> io::Result<()> {
+            if !first {
+                writeln!(out, ",")?;
+            }
+            first = false;
+            write!(out, "{json}")
+        };
+
+        for rank in 0..self.process_number() {
+            event(
+                &mut out,
+                format!(
+                    "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{rank},\"args\":{{\"name\":\"P{rank} ({})\"}}}}",
+                    escape(self.pool_of(rank))
+                ),
+            )?;
+            event(
+                &mut out,
+                format!(
+                    "{{\"name\":\"thread_sort_index\",\"ph\":\"M\",\"pid\":0,\"tid\":{rank},\"args\":{{\"sort_index\":{rank}}}}}"
+                ),
+            )?;
+        }
+
+        let mut handled: FxHashMap<(Rank, Jiffies), usize> = FxHashMap::default();
+        for (flow, trace_event) in self.events().iter().enumerate() {
+            let rank = trace_event.rank;
+            let nth = handled.entry((rank, trace_event.time)).or_default();
+            let start = ts(trace_event.time, *nth);
+            *nth += 1;
+
+            let (name, args) = match &trace_event.kind {
+                TraceKind::Start => ("start", String::new()),
+                TraceKind::Timer { id } => ("timer", format!("\"id\":{id}")),
+                TraceKind::Message {
+                    source,
+                    sent_at,
+                    label,
+                } => (
+                    short_label(label),
+                    format!("\"from\":{source},\"sent_at\":{}", sent_at.0),
+                ),
+            };
+            let name = escape(name);
+            event(
+                &mut out,
+                format!(
+                    "{{\"name\":\"{name}\",\"cat\":\"step\",\"ph\":\"X\",\"ts\":{start},\"dur\":1,\"pid\":0,\"tid\":{rank},\"args\":{{{args}}}}}"
+                ),
+            )?;
+
+            if let TraceKind::Message {
+                source, sent_at, ..
+            } = trace_event.kind
+            {
+                event(
+                    &mut out,
+                    format!(
+                        "{{\"name\":\"message\",\"cat\":\"message\",\"ph\":\"s\",\"id\":{flow},\"ts\":{},\"pid\":0,\"tid\":{source}}}",
+                        ts(sent_at, 0)
+                    ),
+                )?;
+                event(
+                    &mut out,
+                    format!(
+                        "{{\"name\":\"message\",\"cat\":\"message\",\"ph\":\"f\",\"bp\":\"e\",\"id\":{flow},\"ts\":{start},\"pid\":0,\"tid\":{rank}}}"
+                    ),
+                )?;
+            }
+        }
+        writeln!(out, "\n]}}")
+    }
+}
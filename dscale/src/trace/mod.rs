@@ -0,0 +1,94 @@
+mod chart;
+mod chrome;
+mod svg;
+
+pub use chart::ChartFilter;
+
+use crate::{Jiffies, Rank, TimerId, step::Step, topology::HomePools};
+
+/// What a process handler was invoked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceKind {
+    Start,
+    Message {
+        source: Rank,
+        sent_at: Jiffies,
+        /// [`crate::Message::label`] of the delivered message.
+        label: String,
+    },
+    Timer {
+        id: TimerId,
+    },
+}
+
+/// Single handler invocation of a recorded run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub time: Jiffies,
+    pub rank: Rank,
+    pub kind: TraceKind,
+}
+
+/// Handler invocations recorded with [`crate::SimulationBuilder::record_timeline`],
+/// in the order the runner executed them.
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    pools: Vec<String>,
+    events: Vec<TraceEvent>,
+}
+
+impl Timeline {
+    pub(crate) fn new(home_pools: &HomePools) -> Self {
+        Self {
+            pools: home_pools
+                .of_rank
+                .iter()
+                .map(|&pool| home_pools.names[pool].clone())
+                .collect(),
+            events: Vec::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, time: Jiffies, step: &Step) {
+        let (rank, kind) = match *step {
+            Step::Start { rank } => (rank, TraceKind::Start),
+            Step::NetworkStep {
+                source,
+                target,
+                sent_at,
+                ref message,
+            } => (
+                target,
+                TraceKind::Message {
+                    source,
+                    sent_at,
+                    label: message.0.label().into_owned(),
+                },
+            ),
+            Step::TimerStep { rank, id } => (rank, TraceKind::Timer { id }),
+        };
+        self.events.push(TraceEvent { time, rank, kind });
+    }
+
+    pub(crate) fn add_process(&mut self, rank: Rank, pool: &str) {
+        if self.pools.len() <= rank {
+            self.pools.resize(rank + 1, String::new());
+        }
+        self.pools[rank] = pool.to_string();
+    }
+
+    /// All recorded handler invocations.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Home pool of the process.
+    pub fn pool_of(&self, rank: Rank) -> &str {
+        &self.pools[rank]
+    }
+
+    /// Number of processes in the simulation.
+    pub fn process_number(&self) -> usize {
+        self.pools.len()
+    }
+}
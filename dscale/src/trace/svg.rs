@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+
+use crate::{
+    Jiffies,
+    trace::{ChartFilter, Timeline},
+};
+
+const COLUMN: f64 = 140.0;
+const AXIS: f64 = 70.0;
+const HEADER: f64 = 50.0;
+const FOOTER: f64 = 20.0;
+const HEIGHT: f64 = 800.0;
+const MAX_PX_PER_JIFFY: f64 = 20.0;
+const TICKS: usize = 5;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Timeline {
+    /// Writes the filtered messages as a standalone SVG space-time diagram.
+    ///
+    /// Every process is a vertical lifeline, simulated time flows downwards
+    /// and each message is an arrow from its send time on the sender to its
+    /// delivery time on the receiver.
+    pub fn write_svg(&self, filter: &ChartFilter, mut out: impl Write) -> io::Result<()> {
+        let arrows = self.arrows(filter);
+        let ranks = self.participants(filter, &arrows);
+
+        let (from, to) = filter.bounds().unwrap_or_else(|| {
+            (
+                arrows.iter().map(|a| a.sent_at).min().unwrap_or_default(),
+                arrows
+                    .iter()
+                    .map(|a| a.delivered_at)
+                    .max()
+                    .unwrap_or_default(),
+            )
+        });
+        let span = to.0.saturating_sub(from.0).max(1);
+        let scale = (HEIGHT / span as f64).min(MAX_PX_PER_JIFFY);
+        let y = |time: Jiffies| HEADER + time.0.saturating_sub(from.0) as f64 * scale;
+        let x = |rank| {
+            let column = ranks.iter().position(|r| *r == rank).unwrap_or_default();
+            AXIS + COLUMN * column as f64 + COLUMN / 2.0
+        };
+        let width = AXIS + COLUMN * ranks.len() as f64;
+        let bottom = y(to);
+        let height = bottom + FOOTER;
+
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" font-family=\"monospace\" font-size=\"11\">"
+        )?;
+        writeln!(
+            out,
+            "<defs><marker id=\"head\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\"/></marker></defs>"
+        )?;
+        writeln!(out, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+
+        for tick in 0..=TICKS {
+            let time = Jiffies(from.0 + span * tick / TICKS);
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\" fill=\"gray\">{}</text>",
+                AXIS - 10.0,
+                y(time) + 4.0,
+                time.0
+            )?;
+        }
+
+        for &rank in &ranks {
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">P{rank} ({})</text>",
+                x(rank),
+                HEADER - 20.0,
+                escape(self.pool_of(rank))
+            )?;
+            writeln!(
+                out,
+                "<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\" stroke=\"gray\" stroke-dasharray=\"4 4\"/>",
+                x(rank),
+                HEADER,
+                bottom
+            )?;
+        }
+
+        for arrow in &arrows {
+            let (x1, y1) = (x(arrow.source), y(arrow.sent_at));
+            let (x2, y2) = (x(arrow.target), y(arrow.delivered_at));
+            if arrow.source == arrow.target {
+                writeln!(
+                    out,
+                    "<polyline points=\"{x1:.1},{y1:.1} {:.1},{y1:.1} {:.1},{y2:.1} {x2:.1},{y2:.1}\" fill=\"none\" stroke=\"black\" marker-end=\"url(#head)\"/>",
+                    x1 + 30.0,
+                    x1 + 30.0
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" stroke=\"black\" marker-end=\"url(#head)\"/>"
+                )?;
+            }
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"steelblue\">{}</text>",
+                (x1 + x2) / 2.0,
+                (y1 + y2) / 2.0 - 3.0,
+                escape(arrow.label)
+            )?;
+        }
+        writeln!(out, "</svg>")
+    }
+}
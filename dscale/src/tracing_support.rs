@@ -0,0 +1,33 @@
+//! Integration with the `tracing` crate: a span per executed step and a simulated-time timer.
+
+use std::fmt;
+
+use tracing::span::EnteredSpan;
+use tracing_subscriber::fmt::{format::Writer, time::FormatTime};
+
+use crate::{Rank, global, now};
+
+/// Span entered around every executed step.
+pub(crate) fn step_span(rank: Rank) -> EnteredSpan {
+    tracing::info_span!(
+        "step",
+        sim_time = now().0,
+        rank,
+        pool = global::home_pool(rank)
+    )
+    .entered()
+}
+
+/// Timestamps `tracing-subscriber` events with simulated instead of wall time.
+///
+/// ```ignore
+/// tracing_subscriber::fmt().with_timer(dscale::SimTime).init();
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimTime;
+
+impl FormatTime for SimTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
+        write!(w, "{}", now())
+    }
+}
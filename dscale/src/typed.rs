@@ -0,0 +1,262 @@
+use std::{
+    any::{TypeId, type_name},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{Message, MessagePtr, ProcessHandle, Rank, TimerId, global};
+
+/// What a rank was built to receive, recorded for the checks of typed sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Receives {
+    // Reserved by a spawn that did not join yet
+    Unknown,
+    Untyped,
+    Set(TypeId, &'static str),
+}
+
+impl Receives {
+    pub(crate) fn of<P: ProcessHandle>() -> Self {
+        P::message_set().map_or(Receives::Untyped, |(id, name)| Receives::Set(id, name))
+    }
+}
+
+/// Closed set of message types a [`TypedProcess`] accepts, usually an enum
+/// generated by [`crate::message_set!`].
+pub trait MessageSet: Sized + 'static {
+    /// Converts a received message into the set, `None` if it is not part of it.
+    fn from_message(message: &MessagePtr) -> Option<Self>;
+}
+
+/// Marks that a [`MessageSet`] contains the message type `M`.
+///
+/// Implemented by [`crate::message_set!`] for every variant and required by
+/// [`send_typed`], so sending a message the target can not accept does not compile.
+pub trait Accepts<M: Message>: MessageSet {}
+
+/// Process whose messages arrive already dispatched into its [`MessageSet`].
+///
+/// Add it to a simulation wrapped in [`Typed`]:
+///
+/// ```ignore
+/// message_set! {
+///     pub enum PongerMsg {
+///         Ping(Ping),
+///     }
+/// }
+///
+/// impl TypedProcess for Ponger {
+///     type Msg = PongerMsg;
+///
+///     fn on_message(&mut self, from: Rank, message: PongerMsg) {
+///         match message {
+///             PongerMsg::Ping(_) => send_typed::<Pinger, _>(from, Pong),
+///         }
+///     }
+///     // ...
+/// }
+///
+/// SimulationBuilder::default().add_pool::<Typed<Ponger>>("Pongers", 3)
+/// ```
+pub trait TypedProcess: 'static {
+    /// Messages this process accepts.
+    type Msg: MessageSet;
+
+    /// Called once when the simulation starts, before any messages are delivered.
+    fn on_start(&mut self);
+
+    /// Called when a message of [`TypedProcess::Msg`] arrives from another process.
+    fn on_message(&mut self, from: Rank, message: Self::Msg);
+
+    /// Called when a previously scheduled timer fires.
+    fn on_timer(&mut self, id: TimerId);
+
+    /// Copy of the current state, see [`ProcessHandle::snapshot`]. `Clone`
+    /// processes implement it as `Some(self.clone())`.
+    fn snapshot(&self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// State of the process for a checkpoint, see [`ProcessHandle::save`].
+    #[cfg(feature = "checkpoint")]
+    fn save(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Takes the state written by `save` when a checkpoint is restored, see [`ProcessHandle::load`].
+    #[cfg(feature = "checkpoint")]
+    fn load(&mut self, state: &[u8]) {
+        let _ = state;
+        panic!("Process can not be restored: implement TypedProcess::load")
+    }
+}
+
+/// Runs a [`TypedProcess`] as a [`ProcessHandle`].
+///
+/// Panics, naming the process and the message, if a message outside of
+/// [`TypedProcess::Msg`] arrives. Dereferences to the wrapped process, and
+/// forwards its [`TypedProcess::snapshot`], `save` and `load`.
+#[derive(Default)]
+pub struct Typed<P>(pub P);
+
+impl<P: TypedProcess + Send> ProcessHandle for Typed<P> {
+    fn on_start(&mut self) {
+        self.0.on_start()
+    }
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        let Some(typed) = P::Msg::from_message(&message) else {
+            panic!(
+                "P{} ({}) can not accept {message:?} from P{from}",
+                global::rank(),
+                type_name::<P>()
+            )
+        };
+        self.0.on_message(from, typed)
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        self.0.on_timer(id)
+    }
+
+    fn message_set() -> Option<(TypeId, &'static str)> {
+        Some((TypeId::of::<P::Msg>(), type_name::<P::Msg>()))
+    }
+
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        let copy = self.0.snapshot()?;
+        Some(Box::new(Typed(copy)))
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn save(&self) -> Option<Vec<u8>> {
+        self.0.save()
+    }
+
+    #[cfg(feature = "checkpoint")]
+    fn load(&mut self, state: &[u8]) {
+        self.0.load(state)
+    }
+}
+
+impl<P> Deref for Typed<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P> DerefMut for Typed<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.0
+    }
+}
+
+/// Panics if the process was not built to accept the message set of `P`.
+/// Ranks that did not join yet are not checked.
+fn check_runs<P: TypedProcess>(rank: Rank) {
+    let expected = TypeId::of::<P::Msg>();
+    match global::receives(rank) {
+        Receives::Set(id, _) if id == expected => {}
+        Receives::Unknown => {}
+        Receives::Set(_, name) => panic!(
+            "P{rank} accepts {name}, not {} of {}",
+            type_name::<P::Msg>(),
+            type_name::<P>()
+        ),
+        Receives::Untyped => panic!(
+            "P{rank} is not a Typed process, it does not run {}",
+            type_name::<P>()
+        ),
+    }
+}
+
+/// Sends a message to the process with the given rank, which runs `P`.
+/// Only compiles if `P` accepts the message type. Debug builds also check
+/// that the process accepts the message set of `P`.
+pub fn send_typed<P, M>(rank: Rank, message: M)
+where
+    P: TypedProcess,
+    P::Msg: Accepts<M>,
+    M: Message + 'static,
+{
+    if cfg!(debug_assertions) {
+        check_runs::<P>(rank);
+    }
+    global::send_to(rank, message)
+}
+
+/// Sends one message to every process in `ranks`, which run `P`.
+/// Only compiles if `P` accepts the message type, checked like [`send_typed`].
+pub fn multicast_typed<P, M>(ranks: &[Rank], message: M)
+where
+    P: TypedProcess,
+    P::Msg: Accepts<M>,
+    M: Message + 'static,
+{
+    if cfg!(debug_assertions) {
+        ranks.iter().for_each(|&rank| check_runs::<P>(rank));
+    }
+    global::multicast(ranks, message)
+}
+
+/// Sends a message to all processes within the named pool, which run `P`.
+/// Only compiles if `P` accepts the message type, checked like [`send_typed`].
+pub fn broadcast_typed_within_pool<P, M>(pool: &'static str, message: M)
+where
+    P: TypedProcess,
+    P::Msg: Accepts<M>,
+    M: Message + 'static,
+{
+    if cfg!(debug_assertions) {
+        global::list_pool(pool)
+            .iter()
+            .for_each(|&rank| check_runs::<P>(rank));
+    }
+    global::broadcast_within_pool(pool, message)
+}
+
+/// Declares an enum of shared messages together with its [`MessageSet`] and
+/// [`Accepts`] implementations.
+///
+/// ```ignore
+/// message_set! {
+///     pub enum ReplicaMsg {
+///         Prepare(Prepare),
+///         Commit(Commit),
+///     }
+/// }
+/// ```
+///
+/// Every variant holds an `Arc` of its message type, so dispatch does not clone.
+/// Each message type may appear only once.
+#[macro_export]
+macro_rules! message_set {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident($message:ty)),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant(::std::sync::Arc<$message>)),+
+        }
+
+        impl $crate::MessageSet for $name {
+            fn from_message(message: &$crate::MessagePtr) -> Option<Self> {
+                $(
+                    if let Some(message) = message.downcast_arc::<$message>() {
+                        return Some(Self::$variant(message));
+                    }
+                )+
+                None
+            }
+        }
+
+        $(impl $crate::Accepts<$message> for $name {})+
+    };
+}
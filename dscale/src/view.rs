@@ -0,0 +1,42 @@
+use std::any::Any;
+
+use crate::{ProcessHandle, Rank, jiffy::Jiffies, now};
+
+/// Read-only view over the state of every process in the simulation.
+///
+/// Handed to invariants registered with [`crate::SimulationBuilder::invariant`].
+/// Processes are recovered by their concrete type, so a view over a
+/// simulation with several pools can be queried pool by pool.
+pub struct View<'a> {
+    procs: Vec<&'a dyn ProcessHandle>,
+}
+
+impl<'a> View<'a> {
+    pub(crate) fn new(procs: Vec<&'a dyn ProcessHandle>) -> Self {
+        Self { procs }
+    }
+
+    /// Current simulation time.
+    pub fn now(&self) -> Jiffies {
+        now()
+    }
+
+    /// Total number of processes in the view.
+    pub fn process_number(&self) -> usize {
+        self.procs.len()
+    }
+
+    /// Returns the process with the given rank if it is of type `P`.
+    pub fn get<P: ProcessHandle>(&self, rank: Rank) -> Option<&'a P> {
+        let proc: &'a dyn Any = *self.procs.get(rank)?;
+        proc.downcast_ref::<P>()
+    }
+
+    /// Iterates over all processes of type `P` together with their ranks.
+    pub fn iter<P: ProcessHandle>(&self) -> impl Iterator<Item = (Rank, &'a P)> + '_ {
+        self.procs.iter().enumerate().filter_map(|(rank, &proc)| {
+            let proc: &'a dyn Any = proc;
+            proc.downcast_ref::<P>().map(|p| (rank, p))
+        })
+    }
+}
@@ -0,0 +1,68 @@
+use dscale::{global::metrics, *};
+
+// Clients call a server with a timeout and back off between calls.
+// The server answers every fourth request too late, so some calls time out.
+
+#[derive(Clone)]
+pub struct Get {
+    pub seq: usize,
+}
+#[derive(Clone)]
+pub struct Value {
+    pub seq: usize,
+}
+
+impl Message for Get {}
+impl Message for Value {}
+
+#[derive(Default)]
+pub struct AsyncClient {}
+
+impl AsyncProcess for AsyncClient {
+    async fn run(self, ctx: AsyncCtx) {
+        let server = list_pool("Servers")[0];
+        for seq in 0..20 {
+            match ctx.rpc::<Value>(server, Get { seq }, Jiffies(50)).await {
+                Some(value) => {
+                    assert_eq!(value.seq, seq);
+                    metrics::increment("answered", 1);
+                }
+                None => metrics::increment("timed_out", 1),
+            }
+            ctx.sleep(Jiffies(10)).await;
+        }
+        metrics::increment("clients_done", 1);
+    }
+}
+
+#[derive(Default)]
+pub struct AsyncServer {}
+
+impl AsyncProcess for AsyncServer {
+    async fn run(self, ctx: AsyncCtx) {
+        let ticker = ctx.clone();
+        ctx.spawn(async move {
+            loop {
+                ticker.sleep(Jiffies(100)).await;
+                metrics::increment("ticks", 1);
+            }
+        });
+
+        let mut received = 0;
+        loop {
+            let request = ctx.recv_request::<Get>().await;
+            let seq = request.message.seq;
+            received += 1;
+            if received % 4 != 0 {
+                ctx.reply(&request, Value { seq });
+                continue;
+            }
+            // The client gave up by then, the reply must not answer its next call
+            let late = ctx.clone();
+            ctx.spawn(async move {
+                late.sleep(Jiffies(100)).await;
+                late.reply(&request, Value { seq });
+            });
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use dscale::{global::metrics, *};
+use examples::async_rpc::{AsyncClient, AsyncServer};
+
+fn rpc_sim() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Async<AsyncServer>>("Servers", 1)
+        .add_pool::<Async<AsyncClient>>("Clients", 4)
+        .within_pool_latency("Clients", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .within_pool_latency("Servers", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .between_pool_latency(
+            "Clients",
+            "Servers",
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )
+        .time_budget(Jiffies(100_000))
+        .seed(7)
+}
+
+/// Runs until every client is done and returns (answered, timed out, finish time).
+fn run(mut sim: Box<dyn SimulationRunner>) -> (u64, u64, Jiffies) {
+    let status = sim
+        .run_until(&mut |view| {
+            view.iter::<Async<AsyncClient>>()
+                .all(|(_, client)| client.finished())
+        })
+        .status;
+    let RunStatus::PredicateMet { time, .. } = status else {
+        panic!("Expected clients to finish, got {status:?}");
+    };
+    assert_eq!(metrics::counter("clients_done"), 4);
+    // The server keeps ticking in a spawned task while serving requests
+    assert!(metrics::counter("ticks") >= time.0 as u64 / 100 - 1);
+    (
+        metrics::counter("answered"),
+        metrics::counter("timed_out"),
+        time,
+    )
+}
+
+fn main() {
+    let simple = run(rpc_sim().simple().build());
+    println!("Simple (answered, timed out, finished at): {simple:?}");
+    // Every fourth of the 80 requests is answered after the call timed out
+    assert_eq!(simple.0, 60);
+    assert_eq!(simple.1, 20);
+
+    // Same seed, same execution
+    assert_eq!(run(rpc_sim().simple().build()), simple);
+
+    let parallel = run(rpc_sim().parallel(Threads::Specific(2)).build());
+    println!("Parallel (answered, timed out, finished at): {parallel:?}");
+    // Concurrent clients may interleave differently, but outcomes are the same
+    assert_eq!((simple.0, simple.1), (parallel.0, parallel.1));
+}
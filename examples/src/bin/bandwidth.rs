@@ -30,7 +30,10 @@ fn run_unbounded() -> usize {
         .add_pool::<Receiver>("Receivers", 1)
         .vnic_bandwidth(BandwidthConfig::Unbounded)
         .within_pool_latency("Senders", Distributions::Uniform(Jiffies(10), Jiffies(10)))
-        .within_pool_latency("Receivers", Distributions::Uniform(Jiffies(10), Jiffies(10)))
+        .within_pool_latency(
+            "Receivers",
+            Distributions::Uniform(Jiffies(10), Jiffies(10)),
+        )
         .between_pool_latency(
             "Senders",
             "Receivers",
@@ -64,7 +67,10 @@ fn run_bounded() -> usize {
         // Very low bandwidth: 1 byte per jiffy (messages will queue up)
         .vnic_bandwidth(BandwidthConfig::Bounded(1))
         .within_pool_latency("Senders", Distributions::Uniform(Jiffies(10), Jiffies(10)))
-        .within_pool_latency("Receivers", Distributions::Uniform(Jiffies(10), Jiffies(10)))
+        .within_pool_latency(
+            "Receivers",
+            Distributions::Uniform(Jiffies(10), Jiffies(10)),
+        )
         .between_pool_latency(
             "Senders",
             "Receivers",
@@ -76,7 +82,7 @@ fn run_bounded() -> usize {
         .build();
 
     let start = Instant::now();
-    sim.run_full_budget();
+    let report = sim.run_full_budget();
     let elapsed = start.elapsed();
 
     let sent = kv::get::<usize>("messages_sent");
@@ -85,6 +91,14 @@ fn run_bounded() -> usize {
         "  Elapsed: {:?}, sent: {}, received: {}",
         elapsed, sent, received
     );
+    println!("  Peak bandwidth queue depth: {}", report.peak_queue_depth);
+
+    let traffic = report
+        .traffic("Senders", "Receivers")
+        .expect("Both pools exist");
+    assert_eq!(traffic.delivered, received);
+    assert_eq!(traffic.bytes, received * 1000);
+    assert!(report.peak_queue_depth > 0);
 
     received
 }
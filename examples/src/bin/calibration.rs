@@ -0,0 +1,62 @@
+use dscale::*;
+use examples::datacenters::{HOPS, Replica};
+
+const REPLICAS: usize = 8;
+
+fn datacenters() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool_with("East", REPLICAS, Replica::new)
+        .add_pool_with("West", REPLICAS, Replica::new)
+        .within_pool_latency("East", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .within_pool_latency("West", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .between_pool_latency(
+            "East",
+            "West",
+            Distributions::Uniform(Jiffies(100), Jiffies(100)),
+        )
+        .time_budget(Jiffies(1_000_000))
+        .seed(7)
+}
+
+fn tokens(sim: &mut Box<dyn SimulationRunner>) -> usize {
+    let mut tokens = 0;
+    sim.run_until(&mut |view| {
+        tokens = (0..2 * REPLICAS)
+            .map(|rank| view.get::<Replica>(rank).unwrap().tokens)
+            .sum();
+        true
+    });
+    tokens
+}
+
+fn main() {
+    let report = datacenters().simple().build().run_full_budget();
+    assert!(report.parallel.is_none());
+
+    let mut sim = datacenters().parallel(Threads::Specific(2)).build();
+    let report = sim.run_full_budget();
+    let stats = report.parallel.expect("Parallel runs report diagnostics");
+    println!("Parallel diagnostics: {stats:?}");
+    assert_eq!(stats.threads, 2);
+    assert_eq!(stats.thread_busy.len(), 2);
+    assert!(stats.windows > 0 && stats.windows <= report.status.steps());
+    assert!(stats.parallelism >= 1.0);
+    assert!(stats.window_utilization > 0.0 && stats.window_utilization <= 1.0);
+    assert!(stats.coordinator_blocked <= report.wall_time);
+    assert!(
+        stats
+            .thread_busy
+            .iter()
+            .all(|&busy| (0.0..=1.0).contains(&busy))
+    );
+
+    // Any mode calibration picks runs the same simulation
+    let mut sim = datacenters().calibrate(Threads::Specific(4), 2_000).build();
+    let report = sim.run_full_budget();
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+    assert_eq!(tokens(&mut sim), HOPS * 2);
+    match report.parallel {
+        Some(stats) => println!("Calibration picked {} threads", stats.threads),
+        None => println!("Calibration picked single-threaded execution"),
+    }
+}
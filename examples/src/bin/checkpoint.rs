@@ -0,0 +1,104 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use dscale::{global::kv, *};
+use examples::couriers::{Courier, HOPS, Parcel};
+use examples::reconfiguration::{Leader, Node};
+
+const COURIERS: usize = 8;
+
+fn couriers(queue: EventQueue) -> Box<dyn SimulationRunner> {
+    // Random latencies and limited bandwidth, so the checkpoint holds
+    // generator states and messages waiting for their NIC
+    SimulationBuilder::default()
+        .add_pool::<Courier>("Couriers", COURIERS)
+        .within_pool_latency("Couriers", Distributions::Uniform(Jiffies(1), Jiffies(6)))
+        .vnic_bandwidth(BandwidthConfig::Bounded(16))
+        .event_queue(queue)
+        .checkpoint_message::<Parcel>("Parcel")
+        .time_budget(Jiffies(1_000_000))
+        .seed(5)
+        .simple()
+        .build()
+}
+
+// (parcels, hops) of every courier, the number of handled parcels, traffic and end time
+fn finish(sim: &mut dyn SimulationRunner) -> (Vec<(usize, usize)>, usize, TrafficStats, Jiffies) {
+    let report = sim.run_full_budget();
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+
+    let mut couriers = Vec::new();
+    sim.run_until(&mut |view| {
+        couriers = (0..COURIERS)
+            .map(|rank| {
+                let courier = view.get::<Courier>(rank).unwrap();
+                (courier.parcels, courier.hops)
+            })
+            .collect();
+        true
+    });
+    let traffic = report.total_traffic();
+    (couriers, kv::get::<usize>("handled"), traffic, now())
+}
+
+fn main() {
+    // Without a checkpoint, to see that taking one does not change the run
+    let mut sim = couriers(EventQueue::BinaryHeap);
+    kv::set::<usize>("handled", 0);
+    let uninterrupted = finish(&mut *sim);
+    drop(sim);
+
+    let mut sim = couriers(EventQueue::BinaryHeap);
+    kv::set::<usize>("handled", 0);
+    let report = sim.run_sub_budget(Jiffies(3_000));
+    assert!(matches!(report.status, RunStatus::Completed { .. }));
+    let checkpoint = sim.checkpoint();
+    let at = now();
+    let continued = finish(&mut *sim);
+    drop(sim);
+
+    // A fresh simulation resumes where the checkpoint was taken,
+    // also with the other event queue
+    let mut sim = couriers(EventQueue::Calendar);
+    sim.restore(&checkpoint);
+    assert_eq!(now(), at);
+    let restored = finish(&mut *sim);
+
+    // Traffic of the report is counted from the last run only
+    let (couriers, handled, _, end) = &uninterrupted;
+    assert_eq!(
+        (couriers, handled, end),
+        (&continued.0, &continued.1, &continued.3)
+    );
+    assert_eq!(continued, restored);
+    assert_eq!(restored.1, COURIERS * (HOPS + 1));
+    println!(
+        "Checkpoint of {} bytes at {at}, both runs ended at {} with {} parcels handled",
+        checkpoint.len(),
+        restored.3,
+        restored.1
+    );
+
+    // A simulation built the same way could not restore pools changed since the build.
+    // Release builds abort on panics, so this is only checked where they unwind.
+    if cfg!(panic = "unwind") {
+        changed_pools();
+    }
+}
+
+fn changed_pools() {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Leader>("Leader", 1)
+        .add_pool::<Node>("Nodes", 2)
+        .within_pool_latency("Leader", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .within_pool_latency("Nodes", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .between_pool_latency(
+            "Leader",
+            "Nodes",
+            Distributions::Uniform(Jiffies(1), Jiffies(1)),
+        )
+        .time_budget(Jiffies(1_000))
+        .simple()
+        .build();
+    sim.run_steps(1);
+    assert!(catch_unwind(AssertUnwindSafe(|| sim.checkpoint())).is_err());
+}
@@ -0,0 +1,106 @@
+use dscale::*;
+use examples::{
+    context_token_ring::ContextTokenHolder,
+    token_ring::{Token, TokenHolder},
+};
+
+fn unit_test_with_mock() {
+    let mut holder = ContextTokenHolder::default();
+    let mut mock = MockCtx::new(2, 3);
+
+    holder.on_start(&mut mock.ctx());
+    assert!(!holder.holds);
+    assert!(mock.timers().is_empty());
+
+    holder.on_message(&mut mock.ctx(), 1, MessagePtr(std::sync::Arc::new(Token)));
+    assert!(holder.holds);
+    assert_eq!(mock.timers(), &[(0, Jiffies(5))]);
+
+    mock.set_now(Jiffies(5));
+    holder.on_timer(&mut mock.ctx(), 0);
+    assert!(!holder.holds);
+    let sent = mock.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, Recipient::Rank(0));
+    assert!(sent[0].message.is::<Token>());
+}
+
+fn membership_with_mock() {
+    let mut mock = MockCtx::new(0, 3).with_pool("Ring", 0..3);
+    let mut ctx = mock.ctx();
+    let spawned = ctx.spawn::<WithContext<ContextTokenHolder>>("Ring");
+    ctx.create_pool("Leaders");
+    ctx.join_pool("Leaders", spawned);
+    ctx.leave_pool("Ring", 0);
+    ctx.exit();
+
+    assert_eq!(spawned, 3);
+    assert_eq!(
+        mock.changes(),
+        &[
+            MembershipChange::Spawned {
+                pool: "Ring".to_string(),
+                rank: 3
+            },
+            MembershipChange::Created("Leaders".to_string()),
+            MembershipChange::Joined {
+                pool: "Leaders".to_string(),
+                rank: 3
+            },
+            MembershipChange::Left {
+                pool: "Ring".to_string(),
+                rank: 0
+            },
+            MembershipChange::Exited,
+        ]
+    );
+    // Recorded only, the mocked pools stay as configured
+    assert_eq!(&*mock.ctx().list_pool("Ring"), &[0, 1, 2]);
+}
+
+fn ring<P: ProcessHandle + Default + Send>() -> Box<dyn SimulationRunner> {
+    SimulationBuilder::default()
+        .add_pool::<P>("Ring", 5)
+        .within_pool_latency("Ring", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .time_budget(Jiffies(10_000))
+        .seed(3)
+        .build()
+}
+
+/// Runs the whole budget and reports how often every process passed the token.
+fn passes<P: ProcessHandle>(
+    sim: &mut Box<dyn SimulationRunner>,
+    passes: impl Fn(&P) -> usize,
+) -> Vec<usize> {
+    let mut result = Vec::new();
+    sim.run_until(&mut |view| {
+        result = view.iter::<P>().map(|(_, p)| passes(p)).collect();
+        false
+    });
+    result
+}
+
+fn main() {
+    unit_test_with_mock();
+    membership_with_mock();
+
+    // Both flavors behave identically under the same seed
+    let expected = passes::<TokenHolder>(&mut ring::<TokenHolder>(), |p| p.passes);
+    let actual = passes::<WithContext<ContextTokenHolder>>(
+        &mut ring::<WithContext<ContextTokenHolder>>(),
+        |p| p.passes,
+    );
+
+    println!("Passes: {actual:?}");
+    assert!(expected.iter().all(|&p| p > 100));
+    assert_eq!(expected, actual);
+
+    // The wrapper forwards the snapshot of the process, so it can be forked
+    let mut sim = ring::<WithContext<ContextTokenHolder>>();
+    sim.run_sub_budget(Jiffies(5_000));
+    let mut fork = sim.fork();
+    let forked = passes::<WithContext<ContextTokenHolder>>(&mut fork, |p| p.passes);
+    assert!(forked.iter().all(|&p| p > 0));
+    let continued = passes::<WithContext<ContextTokenHolder>>(&mut sim, |p| p.passes);
+    assert_eq!(continued, actual);
+}
@@ -0,0 +1,56 @@
+use dscale::*;
+use examples::elastic::{Coordinator, Worker};
+
+fn elastic() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Coordinator>("Coordinator", 1)
+        .add_pool::<Worker>("Workers", 1)
+        .within_pool_latency(
+            "Coordinator",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )
+        .within_pool_latency("Workers", Distributions::Uniform(Jiffies(1), Jiffies(5)))
+        .between_pool_latency(
+            "Coordinator",
+            "Workers",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )
+        .allow_spawn_into("Workers")
+        .time_budget(Jiffies(2_000))
+        .seed(9)
+}
+
+fn check(mut sim: Box<dyn SimulationRunner>) {
+    let report = sim.run_full_budget();
+
+    let mut replies = Vec::new();
+    let mut workers = Vec::new();
+    sim.run_until(&mut |view| {
+        let coordinator = view.get::<Coordinator>(0).unwrap();
+        replies = coordinator.replies.clone();
+        assert_eq!(coordinator.spawned, Vec::<Rank>::new());
+        assert_eq!(coordinator.retired, vec![5, 4, 3, 2]);
+        workers = view
+            .iter::<Worker>()
+            .map(|(rank, w)| (rank, w.index))
+            .collect();
+        true
+    });
+    println!("Replies per round: {replies:?}");
+
+    // The pool grows and shrinks with spawned and exited workers
+    assert_eq!(replies, vec![2, 3, 4, 5, 4, 3, 2, 1, 0]);
+    // Only the initial worker is left
+    assert_eq!(workers, vec![(1, 0)]);
+
+    // The job sent to an exited worker is dropped
+    let traffic = report.traffic("Coordinator", "Workers").unwrap();
+    println!("Coordinator -> Workers: {traffic:?}");
+    assert_eq!(traffic.dropped, 1);
+    assert_eq!(traffic.sent, traffic.delivered + traffic.dropped);
+}
+
+fn main() {
+    check(elastic().simple().build());
+    check(elastic().parallel(Threads::Specific(2)).build());
+}
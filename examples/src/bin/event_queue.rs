@@ -0,0 +1,101 @@
+use dscale::{global::kv, *};
+use examples::{
+    bandwidth::{Receiver, Sender},
+    broadcast::BroadcastProcess,
+    hold::{Holder, IN_FLIGHT},
+};
+
+const PROCESSES: usize = 10_000;
+
+fn hold(queue: EventQueue) -> Box<dyn SimulationRunner> {
+    SimulationBuilder::default()
+        .add_pool::<Holder>("Holders", PROCESSES)
+        .within_pool_latency(
+            "Holders",
+            Distributions::Uniform(Jiffies(1), Jiffies(2_000)),
+        )
+        .time_budget(Jiffies(3_000))
+        .seed(11)
+        .event_queue(queue)
+        .build()
+}
+
+fn run(queue: EventQueue) -> RunReport {
+    let report = hold(queue).run_full_budget();
+    println!(
+        "{queue:?}: {} steps in {:?}, {:.0} steps/sec",
+        report.steps(),
+        report.wall_time,
+        report.steps_per_sec()
+    );
+    report
+}
+
+// Timers and messages waiting for bandwidth go through the event queue too
+fn bandwidth(queue: EventQueue) -> RunReport {
+    kv::set::<usize>("messages_sent", 0);
+    kv::set::<usize>("messages_received", 0);
+    SimulationBuilder::default()
+        .add_pool::<Sender>("Senders", 1)
+        .add_pool::<Receiver>("Receivers", 1)
+        .vnic_bandwidth(BandwidthConfig::Bounded(500))
+        .within_pool_latency("Senders", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .within_pool_latency("Receivers", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .between_pool_latency(
+            "Senders",
+            "Receivers",
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )
+        .time_budget(Jiffies(5_000))
+        .seed(7)
+        .event_queue(queue)
+        .build()
+        .run_full_budget()
+}
+
+// Broadcasts overlap, so fan-outs are split while others are due at the same jiffies
+fn broadcasts(queue: EventQueue) -> Vec<TraceEvent> {
+    kv::set::<usize>("broadcast_received", 0);
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<BroadcastProcess>("BroadcastPool", 50)
+        .within_pool_latency(
+            "BroadcastPool",
+            Distributions::Uniform(Jiffies(1), Jiffies(300)),
+        )
+        .time_budget(Jiffies(20_000))
+        .seed(3)
+        .event_queue(queue)
+        .record_timeline()
+        .build();
+    sim.run_full_budget();
+    sim.timeline().expect("Recorded").events().to_vec()
+}
+
+fn main() {
+    // Same-jiffy events are handled in scheduling order by both, so the runs are identical
+    let heap = bandwidth(EventQueue::BinaryHeap);
+    let calendar = bandwidth(EventQueue::Calendar);
+    assert_eq!(heap.steps(), calendar.steps());
+    assert_eq!(heap.timers_fired, calendar.timers_fired);
+    assert_eq!(heap.total_traffic(), calendar.total_traffic());
+    assert_eq!(
+        broadcasts(EventQueue::BinaryHeap),
+        broadcasts(EventQueue::Calendar)
+    );
+
+    println!("{} messages in flight", PROCESSES * IN_FLIGHT);
+    let heap = run(EventQueue::BinaryHeap);
+    let calendar = run(EventQueue::Calendar);
+    assert_eq!(heap.steps(), calendar.steps());
+    assert_eq!(heap.time, calendar.time);
+    assert_eq!(heap.total_traffic(), calendar.total_traffic());
+
+    let speedup = calendar.steps_per_sec() / heap.steps_per_sec();
+    println!("\nSpeedup: {speedup:.2}x");
+
+    // Runs on a single core share it with everything else, too noisy to compare
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+    if cores > 1 {
+        assert!(speedup > 1.0)
+    }
+}
@@ -0,0 +1,50 @@
+use dscale::*;
+use examples::heartbeats::Heartbeater;
+
+fn ring() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool_with("Ring", 4, Heartbeater::in_ring)
+        .within_pool_latency("Ring", Distributions::Uniform(Jiffies(1), Jiffies(5)))
+        .time_budget(Jiffies(10_000))
+        .seed(11)
+}
+
+/// Incarnation, heartbeats sent, peers and period of the node.
+fn state(sim: &mut Box<dyn SimulationRunner>, rank: Rank) -> (usize, usize, Vec<Rank>, Jiffies) {
+    let mut state = None;
+    sim.run_until(&mut |view| {
+        let node = view.get::<Heartbeater>(rank).unwrap();
+        state = Some((node.incarnation, node.sent, node.peers.clone(), node.period));
+        true
+    });
+    state.unwrap()
+}
+
+fn check(mut sim: Box<dyn SimulationRunner>) {
+    sim.run_sub_budget(Jiffies(1000));
+
+    // Constructor arguments come from the rank and the pool
+    let (_, sent_p0, peers, period) = state(&mut sim, 0);
+    assert_eq!((peers, period), (vec![3, 1], Jiffies(10)));
+    assert!((99..=100).contains(&sent_p0));
+    let (incarnation, sent, peers, period) = state(&mut sim, 1);
+    assert_eq!((incarnation, peers, period), (0, vec![0, 2], Jiffies(20)));
+    assert!((49..=50).contains(&sent));
+
+    // Restarted node is rebuilt by the same factory and loses its state
+    sim.restart(1);
+    assert_eq!(state(&mut sim, 1), (1, 0, vec![0, 2], Jiffies(20)));
+
+    // Timers of the previous incarnation never fire on the new one,
+    // otherwise it would heartbeat twice as often
+    sim.run_sub_budget(Jiffies(1000));
+    let (incarnation, sent, _, _) = state(&mut sim, 1);
+    println!("P1 after restart: incarnation {incarnation}, sent {sent}");
+    assert_eq!(incarnation, 1);
+    assert!((49..=50).contains(&sent));
+}
+
+fn main() {
+    check(ring().simple().build());
+    check(ring().parallel(Threads::Specific(2)).build());
+}
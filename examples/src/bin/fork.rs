@@ -0,0 +1,71 @@
+use std::time::Instant;
+
+use dscale::{global::kv, *};
+use examples::couriers::{Courier, HOPS};
+
+const COURIERS: usize = 8;
+
+fn couriers() -> Box<dyn SimulationRunner> {
+    SimulationBuilder::default()
+        .add_pool::<Courier>("Couriers", COURIERS)
+        .within_pool_latency("Couriers", Distributions::Uniform(Jiffies(1), Jiffies(6)))
+        .time_budget(Jiffies(1_000_000))
+        .seed(5)
+        .fork_value::<Vec<Jiffies>>()
+        .simple()
+        .build()
+}
+
+// Handled parcels and the time the last one was handled
+fn finish(sim: &mut dyn SimulationRunner) -> (usize, Jiffies) {
+    let report = sim.run_full_budget();
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+    (kv::get::<usize>("handled"), now())
+}
+
+fn main() {
+    let mut unforked = couriers();
+    kv::set::<usize>("handled", 0);
+    let expected = finish(&mut *unforked);
+    drop(unforked);
+    assert_eq!(expected.0, COURIERS * (HOPS + 1));
+
+    let mut sim = couriers();
+    kv::set::<usize>("handled", 0);
+    kv::set("forks", Vec::<Jiffies>::new());
+    // Not registered with fork_value, so forks are taken without it
+    let started = Instant::now();
+    kv::set("started", started);
+    sim.run_sub_budget(Jiffies(3_000));
+    // The simulation stays active after a fork, so the key-value store is its own
+    let mut relaxed = sim.fork();
+    kv::modify::<Vec<Jiffies>>("forks", |forks| forks.push(now()));
+    let mut crashed = sim.fork();
+    kv::modify::<Vec<Jiffies>>("forks", |forks| forks.push(now()));
+    // What if courier 3 crashes now: it drops the parcels it holds and starts a new one
+    crashed.restart(3);
+
+    // Forks run interleaved with the simulation and with each other
+    relaxed.run_sub_budget(Jiffies(1_000));
+    sim.run_sub_budget(Jiffies(1_000));
+    crashed.run_sub_budget(Jiffies(1_000));
+    // Forks copy the key-value store as it was when they were taken
+    let relaxed = finish(&mut *relaxed);
+    assert!(kv::get::<Vec<Jiffies>>("forks").is_empty());
+    let crashed = finish(&mut *crashed);
+    assert_eq!(kv::get::<Vec<Jiffies>>("forks"), [Jiffies(3_000)]);
+    let continued = finish(&mut *sim);
+    assert_eq!(kv::get::<Vec<Jiffies>>("forks"), [Jiffies(3_000); 2]);
+    assert_eq!(kv::get::<Instant>("started"), started);
+
+    // The simulation continues as if it was not forked
+    assert_eq!(continued, expected);
+    // Forks sample other latencies, but every parcel makes it
+    assert_eq!(relaxed.0, expected.0);
+    assert_ne!(relaxed.1, expected.1);
+    assert_ne!(crashed.0, expected.0);
+    println!(
+        "Unforked: {} parcels handled by {}, other latencies: by {}, crash of courier 3: {} parcels handled by {}",
+        expected.0, expected.1, relaxed.1, crashed.0, crashed.1
+    );
+}
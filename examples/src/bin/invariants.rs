@@ -0,0 +1,79 @@
+use dscale::*;
+use examples::token_ring::TokenHolder;
+
+fn base_sim() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<TokenHolder>("Ring", 10)
+        .within_pool_latency("Ring", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .time_budget(Jiffies(10_000))
+        .seed(7)
+        .invariant("single token", |view| {
+            view.iter::<TokenHolder>().filter(|(_, p)| p.holds).count() <= 1
+        })
+        .property(
+            "token keeps being held",
+            Property::always_eventually(Jiffies(20), |view| {
+                view.iter::<TokenHolder>().any(|(_, p)| p.holds)
+            }),
+        )
+}
+
+fn main() {
+    let mut sim = base_sim().simple().build();
+    let status = sim.run_full_budget().status;
+    println!("Simple: {:?}", status);
+    assert!(matches!(status, RunStatus::BudgetExhausted { .. }));
+    drop(sim);
+
+    let mut sim = base_sim()
+        .check_invariants_every(10)
+        .parallel(Threads::Specific(2))
+        .build();
+    let status = sim.run_full_budget().status;
+    println!("Scalable: {:?}", status);
+    assert!(matches!(status, RunStatus::BudgetExhausted { .. }));
+    drop(sim);
+
+    let mut sim = base_sim()
+        .invariant("bounded passes", |view| {
+            view.iter::<TokenHolder>().all(|(_, p)| p.passes <= 3)
+        })
+        .simple()
+        .build();
+    let status = sim.run_full_budget().status;
+    println!("Bounded passes: {:?}", status);
+    let RunStatus::InvariantViolated { steps, violation } = status else {
+        panic!("Expected violation, got {status:?}");
+    };
+    assert_eq!(violation.property, "bounded passes");
+    // Both count the steps starting the processes
+    assert_eq!(violation.step, steps);
+    assert_eq!(violation.seed, 7);
+
+    // Halted simulation keeps reporting the same violation
+    assert_eq!(
+        sim.run_steps(10).status,
+        RunStatus::InvariantViolated {
+            steps: 0,
+            violation
+        }
+    );
+    drop(sim);
+
+    let mut sim = base_sim()
+        .property(
+            "everyone passed quickly",
+            Property::eventually(Jiffies(100), |view| {
+                view.iter::<TokenHolder>().all(|(_, p)| p.passes >= 1)
+            }),
+        )
+        .simple()
+        .build();
+    let status = sim.run_full_budget().status;
+    println!("Everyone passed quickly: {:?}", status);
+    let RunStatus::LivenessViolated { violation, .. } = status else {
+        panic!("Expected liveness violation, got {status:?}");
+    };
+    assert_eq!(violation.property, "everyone passed quickly");
+    assert!(violation.time > Jiffies(100));
+}
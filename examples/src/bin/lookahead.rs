@@ -0,0 +1,58 @@
+use std::time::Instant;
+
+use dscale::*;
+use examples::datacenters::{HOPS, Replica};
+
+const REPLICAS: usize = 8;
+
+fn datacenters() -> SimulationBuilder {
+    // Within a datacenter the lookahead is a single jiffy,
+    // between the two it is a hundred
+    SimulationBuilder::default()
+        .add_pool_with("East", REPLICAS, Replica::new)
+        .add_pool_with("West", REPLICAS, Replica::new)
+        .within_pool_latency("East", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .within_pool_latency("West", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .between_pool_latency(
+            "East",
+            "West",
+            Distributions::Uniform(Jiffies(100), Jiffies(100)),
+        )
+        .time_budget(Jiffies(1_000_000))
+        .seed(7)
+}
+
+// (tokens, digests, last remote token count) of every replica
+fn run(mut sim: Box<dyn SimulationRunner>) -> Vec<(usize, usize, usize)> {
+    let started = Instant::now();
+    let report = sim.run_full_budget();
+    println!("{} steps in {:?}", report.steps(), started.elapsed());
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+
+    let mut replicas = Vec::new();
+    sim.run_until(&mut |view| {
+        replicas = (0..2 * REPLICAS)
+            .map(|rank| {
+                let replica = view.get::<Replica>(rank).unwrap();
+                (replica.tokens, replica.digests, replica.remote_tokens)
+            })
+            .collect();
+        true
+    });
+    replicas
+}
+
+fn main() {
+    let simple = run(datacenters().simple().build());
+    let parallel = run(datacenters().parallel(Threads::Specific(2)).build());
+
+    // Latencies are fixed, so conservative synchronization must not change anything
+    assert_eq!(simple, parallel);
+    assert!(
+        simple
+            .iter()
+            .all(|&(tokens, digests, _)| tokens == HOPS / REPLICAS
+                && digests == HOPS / REPLICAS / 10)
+    );
+    println!("Replicas: {parallel:?}");
+}
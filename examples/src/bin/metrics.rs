@@ -0,0 +1,79 @@
+use dscale::{
+    global::metrics::{self, MetricValue},
+    *,
+};
+use examples::rpc_latency::{Client, Server};
+
+fn rpc_latency() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Client>("Clients", 4)
+        .add_pool::<Server>("Servers", 2)
+        .within_pool_latency("Clients", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .within_pool_latency("Servers", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .between_pool_latency(
+            "Clients",
+            "Servers",
+            Distributions::Uniform(Jiffies(10), Jiffies(20)),
+        )
+        .vnic_bandwidth(BandwidthConfig::Unbounded)
+        .sample_metrics_every(Jiffies(1000))
+        .time_budget(Jiffies(10_000))
+        .seed(7)
+}
+
+fn check(mut sim: Box<dyn SimulationRunner>) {
+    sim.run_full_budget();
+
+    let requests = metrics::counter("requests");
+    let served = metrics::counter("served");
+    let rtt = metrics::histogram("rtt");
+    println!(
+        "Requests: {requests}, served: {served}, rtt p50: {}, p99: {}, max: {}",
+        rtt.value_at_quantile(0.5),
+        rtt.value_at_quantile(0.99),
+        rtt.max()
+    );
+
+    // Every client has at most one request in flight
+    assert!(requests - served <= 4);
+    assert_eq!(rtt.count(), requests - 4);
+    // Two hops of 10..=20 jiffies plus one jiffy of delivery each
+    assert!(rtt.min() >= 22 && rtt.max() <= 42);
+    assert!((22..=42).contains(&rtt.value_at_quantile(0.5)));
+    assert_eq!(metrics::counter_in_pool("requests", "Clients"), requests);
+    assert_eq!(metrics::counter_in_pool("requests", "Servers"), 0);
+    assert_eq!(
+        metrics::histogram_in_pool("rtt", "Clients").count(),
+        rtt.count()
+    );
+    let per_server = metrics::counter_of("served", 4);
+    assert_eq!(
+        metrics::gauge_of("served_total", 4),
+        Some(per_server as f64)
+    );
+
+    // One sample per metric and process every 1000 jiffies
+    let samples = metrics::samples();
+    assert!(samples.iter().all(|s| s.time.0 % 1000 == 0));
+    let last_requests: u64 = samples
+        .iter()
+        .filter(|s| s.time == Jiffies(9000) && s.metric == "requests")
+        .map(|s| match s.value {
+            MetricValue::Counter(v) => v,
+            _ => unreachable!(),
+        })
+        .sum();
+    assert!(0 < last_requests && last_requests <= requests);
+
+    let mut csv = Vec::new();
+    metrics::write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("time,metric,rank,pool,stat,value\n"));
+    assert!(csv.contains(",rtt,0,Clients,p99,"));
+    assert!(csv.contains(",served,4,Servers,count,"));
+}
+
+fn main() {
+    check(rpc_latency().simple().build());
+    check(rpc_latency().parallel(Threads::Specific(2)).build());
+}
@@ -0,0 +1,56 @@
+use dscale::*;
+use examples::quorum::{Acceptor, Proposer, QUORUM, ROUNDS};
+
+fn quorum(use_multicast: bool) -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool_with("Proposer", 1, move |_, _| Proposer::new(use_multicast))
+        .add_pool::<Acceptor>("Acceptors", 7)
+        .within_pool_latency("Proposer", Distributions::Uniform(Jiffies(1), Jiffies(5)))
+        .within_pool_latency("Acceptors", Distributions::Uniform(Jiffies(1), Jiffies(5)))
+        .between_pool_latency(
+            "Proposer",
+            "Acceptors",
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )
+        .time_budget(Jiffies(1_000))
+        .seed(5)
+}
+
+fn promises(builder: SimulationBuilder) -> Vec<(usize, Rank, Jiffies)> {
+    let mut sim = builder.build();
+    let report = sim.run_full_budget();
+
+    let traffic = report.traffic("Proposer", "Acceptors").unwrap();
+    assert_eq!(traffic.sent, QUORUM * ROUNDS);
+    assert_eq!(traffic.delivered, QUORUM * ROUNDS);
+
+    let mut promises = Vec::new();
+    sim.run_until(&mut |view| {
+        promises = view.get::<Proposer>(0).unwrap().promises.clone();
+        true
+    });
+    promises
+}
+
+fn main() {
+    let multicast = promises(quorum(true).simple());
+    let unicast = promises(quorum(false).simple());
+    println!("Promises: {multicast:?}");
+
+    // Every acceptor of every quorum answers
+    assert_eq!(multicast.len(), QUORUM * ROUNDS);
+    // One multicast delivers exactly like a send_to per acceptor
+    assert_eq!(multicast, unicast);
+
+    // Delivery times depend on the runner, every quorum still answers
+    let answered = |promises: &[(usize, Rank, Jiffies)]| {
+        let mut answered: Vec<_> = promises
+            .iter()
+            .map(|&(round, from, _)| (round, from))
+            .collect();
+        answered.sort();
+        answered
+    };
+    let parallel = promises(quorum(true).parallel(Threads::Specific(2)));
+    assert_eq!(answered(&parallel), answered(&multicast));
+}
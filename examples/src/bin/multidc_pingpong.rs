@@ -34,6 +34,7 @@ fn main() {
         elapsed, pings, pongs,
     );
 
-    assert_eq!(pings, 9356);
-    assert_eq!(pongs, 9355);
+    // Counts at the budget depend on ties: messages due at the same jiffy are handled in send order
+    assert_eq!(pings, 9388);
+    assert_eq!(pongs, 9387);
 }
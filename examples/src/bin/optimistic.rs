@@ -0,0 +1,56 @@
+use std::time::Instant;
+
+use dscale::{global::kv, *};
+use examples::couriers::{Courier, HOPS};
+
+const COURIERS: usize = 16;
+
+fn couriers() -> SimulationBuilder {
+    // A jiffy of lookahead leaves the conservative runner nothing to run in parallel
+    SimulationBuilder::default()
+        .add_pool::<Courier>("Couriers", COURIERS)
+        .within_pool_latency("Couriers", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .time_budget(Jiffies(1_000_000))
+        .seed(11)
+}
+
+// (parcels, hops) of every courier and the number of handled parcels
+fn run(mut sim: Box<dyn SimulationRunner>) -> (Vec<(usize, usize)>, usize) {
+    kv::set::<usize>("handled", 0);
+    let started = Instant::now();
+    let report = sim.run_full_budget();
+    println!("{} steps in {:?}", report.steps(), started.elapsed());
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+
+    let mut couriers = Vec::new();
+    sim.run_until(&mut |view| {
+        couriers = (0..COURIERS)
+            .map(|rank| {
+                let courier = view.get::<Courier>(rank).unwrap();
+                (courier.parcels, courier.hops)
+            })
+            .collect();
+        true
+    });
+    (couriers, kv::get::<usize>("handled"))
+}
+
+fn main() {
+    let (simple, handled) = run(couriers().simple().build());
+    let (optimistic, speculated) = run(couriers()
+        .optimistic(Threads::Specific(2), Jiffies(50))
+        .build());
+
+    // Rolled back steps leave no trace in the state of the processes
+    assert_eq!(simple, optimistic);
+    assert_eq!(handled, COURIERS * (HOPS + 1));
+    assert_eq!(
+        optimistic
+            .iter()
+            .map(|&(parcels, _)| parcels)
+            .sum::<usize>(),
+        handled
+    );
+    assert!(speculated >= handled);
+    println!("Handled {handled} parcels, {speculated} with speculation");
+}
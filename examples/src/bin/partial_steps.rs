@@ -15,17 +15,17 @@ fn main() {
 
     let mut total_steps = 0;
     loop {
-        let status = sim.run_steps(1000);
-        total_steps += status.steps();
+        let report = sim.run_steps(1000);
+        total_steps += report.steps();
         println!(
             "status={:?}, passes={}, total_steps={}",
-            status,
+            report.status,
             kv::get::<usize>("passes"),
             total_steps,
         );
-        match status {
+        match report.status {
             RunStatus::Completed { .. } => {}
-            RunStatus::BudgetExhausted { .. } | RunStatus::NoMoreEvents { .. } => break,
+            _ => break,
         }
     }
 }
@@ -16,17 +16,17 @@ fn main() {
     let chunk = Jiffies(100_000);
     let mut total_steps = 0;
     loop {
-        let status = sim.run_sub_budget(chunk);
-        total_steps += status.steps();
+        let report = sim.run_sub_budget(chunk);
+        total_steps += report.steps();
         println!(
             "status={:?}, passes={}, total_steps={}",
-            status,
+            report.status,
             kv::get::<usize>("passes"),
             total_steps,
         );
-        match status {
+        match report.status {
             RunStatus::Completed { .. } => {}
-            RunStatus::BudgetExhausted { .. } | RunStatus::NoMoreEvents { .. } => break,
+            _ => break,
         }
     }
 }
@@ -0,0 +1,49 @@
+use dscale::*;
+use examples::reconfiguration::{Leader, Node};
+
+fn reconfiguration() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Leader>("Leader", 1)
+        .add_pool::<Node>("Nodes", 4)
+        .within_pool_latency("Leader", Distributions::Uniform(Jiffies(1), Jiffies(5)))
+        .within_pool_latency("Nodes", Distributions::Uniform(Jiffies(1), Jiffies(5)))
+        .between_pool_latency(
+            "Leader",
+            "Nodes",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )
+        .time_budget(Jiffies(1_000))
+        .seed(3)
+}
+
+fn check(mut sim: Box<dyn SimulationRunner>) {
+    sim.run_full_budget();
+    sim.run_until(&mut |view| {
+        let leader = view.get::<Leader>(0).unwrap();
+        println!("Membership per round: {:?}", leader.membership);
+        println!("Votes per round: {:?}", leader.votes);
+
+        // Changes are visible from the next step on
+        assert_eq!(
+            leader.membership,
+            vec![
+                (vec![1, 2], vec![3, 4]),
+                (vec![1, 2, 3], vec![4]),
+                (vec![1, 2, 3, 4], vec![]),
+                (vec![1, 2, 3], vec![4]),
+                (vec![1, 2, 3, 4], vec![]),
+            ]
+        );
+        let seen_at_start: Vec<_> = leader.membership.iter().map(|(v, _)| v.clone()).collect();
+        assert_eq!(leader.voters_after_change, seen_at_start);
+
+        // Every ballot reaches exactly the voters of its round
+        assert_eq!(leader.votes, vec![2, 3, 4, 3, 4]);
+        true
+    });
+}
+
+fn main() {
+    check(reconfiguration().simple().build());
+    check(reconfiguration().parallel(Threads::Specific(2)).build());
+}
@@ -0,0 +1,106 @@
+use dscale::{
+    global::{kv, logs},
+    *,
+};
+use examples::timers::LazyPingPong;
+
+fn lazy_ping_pong(log_lines: usize) -> Box<dyn SimulationRunner> {
+    kv::set::<usize>("heartbeats", 0);
+    kv::set::<usize>("pings_received", 0);
+    kv::set::<usize>("pongs_received", 0);
+    SimulationBuilder::default()
+        .add_pool::<LazyPingPong>("TimerDemoPool", 2)
+        .within_pool_latency(
+            "TimerDemoPool",
+            Distributions::Uniform(Jiffies(10), Jiffies(50)),
+        )
+        .capture_process_logs(log_lines)
+        .time_budget(Jiffies(10_000))
+        .seed(42)
+        .build()
+}
+
+fn main() {
+    // Run by the check of failed assertions below, in a process of its own
+    if std::env::var_os("DSCALE_FAILING_ASSERTION").is_some() {
+        let mut sim = lazy_ping_pong(1000);
+        sim.run_full_budget();
+        assert_eq!(kv::get::<usize>("pongs_received"), 0);
+    }
+
+    let mut sim = lazy_ping_pong(1000);
+    sim.run_full_budget();
+
+    // Captured even though release builds strip debug logs
+    let p1 = logs::of(1);
+    assert_eq!(p1[0].time, Jiffies(0));
+    assert_eq!(p1[0].text, "Starting timer demo process");
+    assert_eq!(
+        p1.iter()
+            .filter(|line| line.text == "Received Ping from Process 0")
+            .count(),
+        5
+    );
+    assert!(p1.iter().all(|line| line.rank == 1));
+    // Lines of the framework itself are not captured
+    assert!(p1.iter().all(|line| !line.text.contains("[Access]")));
+
+    // Arguments are evaluated once
+    let mut evaluated = 0;
+    debug_process!("Evaluated {}", {
+        evaluated += 1;
+        evaluated
+    });
+    assert_eq!(evaluated, 1);
+
+    let window = logs::between(0, Jiffies(1000), Jiffies(2000));
+    println!("P0 between 1000 and 2000:\n{window:#?}");
+    assert!(!window.is_empty());
+    assert!(
+        window
+            .iter()
+            .all(|line| Jiffies(1000) <= line.time && line.time <= Jiffies(2000))
+    );
+    assert!(
+        window
+            .iter()
+            .any(|line| line.text == "Heartbeat timer fired")
+    );
+
+    assert!(logs::dump(&[0]).starts_with("--- P0 log ---\n[0 jfs] DEBUG Starting"));
+    assert_ranks!([0, 1], kv::get::<usize>("pongs_received") == 5);
+    drop(sim);
+
+    // Only the most recent lines are kept
+    let mut sim = lazy_ping_pong(10);
+    sim.run_full_budget();
+    let p0 = logs::of(0);
+    assert_eq!(p0.len(), 10);
+    assert!(p0.last().unwrap().time >= Jiffies(9000));
+    drop(sim);
+
+    // Nothing is captured unless enabled
+    let mut sim = lazy_ping_pong(0);
+    sim.run_full_budget();
+    assert!(logs::of(0).is_empty());
+
+    // Arguments are not evaluated if the line is neither captured nor logged
+    if !log::log_enabled!(log::Level::Debug) {
+        let mut evaluated = 0;
+        debug_process!("Evaluated {}", {
+            evaluated += 1;
+            evaluated
+        });
+        assert_eq!(evaluated, 0);
+    }
+
+    // Any failed assertion prints the captured logs of every process
+    let failed = std::process::Command::new(std::env::current_exe().unwrap())
+        .env("DSCALE_FAILING_ASSERTION", "1")
+        .output()
+        .unwrap();
+    assert!(!failed.status.success());
+    let stderr = String::from_utf8_lossy(&failed.stderr);
+    assert!(stderr.contains("--- P0 log ---") && stderr.contains("--- P1 log ---"));
+    assert!(stderr.contains("Received Ping from Process 0"));
+}
@@ -0,0 +1,50 @@
+use dscale::*;
+use examples::rumors::{Gossiper, RETOLD};
+
+const GOSSIPERS: usize = 16;
+
+fn gossipers() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Gossiper>("Gossipers", GOSSIPERS)
+        .within_pool_latency("Gossipers", Distributions::Uniform(Jiffies(1), Jiffies(20)))
+        .time_budget(Jiffies(1_000_000))
+        .seed(23)
+}
+
+// Rumors every gossiper heard and told to every peer
+fn run(mut sim: Box<dyn SimulationRunner>) -> Vec<(usize, Vec<usize>)> {
+    let report = sim.run_full_budget();
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+
+    let mut gossipers = Vec::new();
+    sim.run_until(&mut |view| {
+        gossipers = (0..GOSSIPERS)
+            .map(|rank| {
+                let gossiper = view.get::<Gossiper>(rank).unwrap();
+                let told = (0..GOSSIPERS)
+                    .map(|peer| gossiper.told.get(&peer).copied().unwrap_or(0))
+                    .collect();
+                (gossiper.heard, told)
+            })
+            .collect();
+        true
+    });
+    gossipers
+}
+
+fn main() {
+    let simple = run(gossipers().simple().build());
+
+    // Generators of random choices and latencies roll back with the process
+    // that drew from them, so speculation does not change what is drawn
+    let optimistic = run(gossipers()
+        .optimistic(Threads::Specific(2), Jiffies(50))
+        .build());
+    assert_eq!(simple, optimistic);
+    let scalable = run(gossipers().parallel(Threads::Specific(2)).build());
+    assert_eq!(simple, scalable);
+
+    let told: usize = simple.iter().flat_map(|(_, told)| told).sum();
+    assert!(told > GOSSIPERS && told <= GOSSIPERS * RETOLD);
+    println!("{told} rumors retold, the same with every runner");
+}
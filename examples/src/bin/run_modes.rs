@@ -0,0 +1,157 @@
+use dscale::{global::kv, *};
+use examples::{timers::LazyPingPong, token_ring::TokenHolder};
+
+fn token_ring() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<TokenHolder>("Ring", 10)
+        .within_pool_latency("Ring", Distributions::Uniform(Jiffies(1), Jiffies(10)))
+        .time_budget(Jiffies(100_000))
+        .seed(3)
+}
+
+fn lazy_ping_pong() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<LazyPingPong>("TimerDemoPool", 2)
+        .within_pool_latency(
+            "TimerDemoPool",
+            Distributions::Uniform(Jiffies(10), Jiffies(50)),
+        )
+        .time_budget(Jiffies(1_000_000))
+        .seed(42)
+}
+
+/// Heartbeats every 100 jiffies and times out once, long after the last message.
+#[derive(Default)]
+struct Candidate {
+    heartbeat: TimerId,
+    election: TimerId,
+}
+
+impl ProcessHandle for Candidate {
+    fn on_start(&mut self) {
+        self.heartbeat = schedule_periodic_timer_after(Jiffies(100));
+        self.election = schedule_timer_after(Jiffies(5000));
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, id: TimerId) {
+        if id == self.election {
+            kv::set("elected", true);
+        } else {
+            self.heartbeat = schedule_periodic_timer_after(Jiffies(100));
+        }
+    }
+}
+
+/// Retries a request every 300 jiffies, re-arming the timeout from its own handler,
+/// until the tenth attempt gets through.
+#[derive(Default)]
+struct Retrier {
+    attempts: usize,
+}
+
+impl ProcessHandle for Retrier {
+    fn on_start(&mut self) {
+        schedule_timer_after(Jiffies(300));
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        self.attempts += 1;
+        kv::set("attempts", self.attempts);
+        if self.attempts < 10 {
+            schedule_timer_after(Jiffies(300));
+        }
+    }
+}
+
+fn retries() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Retrier>("Retriers", 1)
+        .within_pool_latency("Retriers", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .time_budget(Jiffies(100_000))
+        .seed(5)
+}
+
+fn election() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Candidate>("Candidates", 1)
+        .within_pool_latency("Candidates", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .time_budget(Jiffies(100_000))
+        .seed(5)
+}
+
+fn until_three_rounds(sim: &mut Box<dyn SimulationRunner>) {
+    let status = sim
+        .run_until(&mut |view| {
+            view.get::<TokenHolder>(0)
+                .is_some_and(|first| first.passes >= 3)
+        })
+        .status;
+    println!("Until three rounds: {:?}", status);
+    let RunStatus::PredicateMet { time, .. } = status else {
+        panic!("Expected predicate to be met, got {status:?}");
+    };
+    assert!(time < Jiffies(100_000));
+}
+
+fn until_quiescent(sim: &mut Box<dyn SimulationRunner>) {
+    kv::set::<usize>("heartbeats", 0);
+    kv::set::<usize>("pings_received", 0);
+    kv::set::<usize>("pongs_received", 0);
+
+    // Heartbeat timers keep firing forever, but pings stop after five rounds
+    let status = sim.run_until_quiescent(Jiffies(2000)).status;
+    println!("Until quiescent: {:?}", status);
+    assert!(matches!(status, RunStatus::Quiescent { .. }));
+    assert_eq!(kv::get::<usize>("pongs_received"), 5);
+}
+
+fn until_election(sim: &mut Box<dyn SimulationRunner>) {
+    kv::set("elected", false);
+
+    // The one-shot election timeout is not background activity like the heartbeats
+    let status = sim.run_until_quiescent(Jiffies(1000)).status;
+    println!("Until election: {:?}", status);
+    let RunStatus::Quiescent { time, .. } = status else {
+        panic!("Expected quiescence, got {status:?}");
+    };
+    assert!(time >= Jiffies(5000));
+    assert!(kv::get::<bool>("elected"));
+}
+
+fn until_retried(sim: &mut Box<dyn SimulationRunner>) {
+    kv::set::<usize>("attempts", 0);
+
+    // A timeout re-armed from its own handler is pending work, not background activity
+    let status = sim.run_until_quiescent(Jiffies(100)).status;
+    println!("Until retried: {:?}", status);
+    let RunStatus::Quiescent { time, .. } = status else {
+        panic!("Expected quiescence, got {status:?}");
+    };
+    assert!(time >= Jiffies(3000));
+    assert_eq!(kv::get::<usize>("attempts"), 10);
+}
+
+fn exact_steps(sim: &mut Box<dyn SimulationRunner>) {
+    // The first steps start every process of the ring
+    assert_eq!(sim.run_steps(10).steps(), 10);
+    for _ in 0..10 {
+        assert_eq!(sim.run_steps(7).steps(), 7);
+    }
+}
+
+fn main() {
+    until_three_rounds(&mut token_ring().simple().build());
+    until_three_rounds(&mut token_ring().parallel(Threads::Specific(2)).build());
+    until_quiescent(&mut lazy_ping_pong().simple().build());
+    until_quiescent(&mut lazy_ping_pong().parallel(Threads::Specific(2)).build());
+    until_election(&mut election().simple().build());
+    until_election(&mut election().parallel(Threads::Specific(2)).build());
+    until_retried(&mut retries().simple().build());
+    until_retried(&mut retries().parallel(Threads::Specific(2)).build());
+    exact_steps(&mut token_ring().simple().build());
+    exact_steps(&mut token_ring().parallel(Threads::Specific(2)).build());
+}
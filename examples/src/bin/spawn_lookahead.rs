@@ -0,0 +1,53 @@
+use dscale::*;
+use examples::nursery::{Child, Far, Origin, SPAWNS};
+
+const FAR: usize = 4;
+
+fn pools() -> SimulationBuilder {
+    let link = |latency| Distributions::Uniform(Jiffies(latency), Jiffies(latency));
+    // Far from the origin directly, but close through the nursery. Greetings arrive
+    // at odd jiffies, tokens at even ones, so no two steps of a process tie
+    SimulationBuilder::default()
+        .add_pool::<Origin>("Origin", 1)
+        .add_pool::<Child>("Nursery", 1)
+        .add_pool_with("Far", FAR, Far::new)
+        .within_pool_latency("Origin", link(1))
+        .within_pool_latency("Nursery", link(1))
+        .within_pool_latency("Far", link(1))
+        .between_pool_latency("Origin", "Nursery", link(100))
+        .between_pool_latency("Origin", "Far", link(300))
+        .between_pool_latency("Nursery", "Far", link(2))
+        .allow_spawn_into("Nursery")
+        .time_budget(Jiffies(1_000_000))
+        .seed(13)
+}
+
+// (tokens, greetings) of every far process
+fn run(mut sim: Box<dyn SimulationRunner>) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let report = sim.run_full_budget();
+    assert!(matches!(report.status, RunStatus::NoMoreEvents { .. }));
+
+    let mut far = Vec::new();
+    sim.run_until(&mut |view| {
+        far = (2..2 + FAR)
+            .map(|rank| {
+                let far = view.get::<Far>(rank).unwrap();
+                (far.tokens, far.hellos.clone())
+            })
+            .collect();
+        true
+    });
+    far
+}
+
+fn main() {
+    let simple = run(pools().simple().build());
+    let parallel = run(pools().parallel(Threads::Specific(2)).build());
+
+    // Children greet the far pool no later than the origin could, and the
+    // parallel runner keeps that pool from running ahead of them
+    assert_eq!(simple, parallel);
+    let greeted: usize = simple.iter().map(|(_, hellos)| hellos.len()).sum();
+    assert_eq!(greeted, SPAWNS);
+    println!("Far processes: {parallel:?}");
+}
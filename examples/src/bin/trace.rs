@@ -0,0 +1,173 @@
+use std::{borrow::Cow, fs::File, sync::Arc};
+
+use dscale::*;
+use examples::rpc_latency::{Client, Server};
+
+struct Labeled;
+
+impl Message for Labeled {
+    fn label(&self) -> Cow<'_, str> {
+        Cow::Borrowed("custom label")
+    }
+}
+
+struct Markup;
+
+impl Message for Markup {
+    fn label(&self) -> Cow<'_, str> {
+        Cow::Borrowed("Vote<Term>\ncast")
+    }
+}
+
+#[derive(Default)]
+struct Voter;
+
+impl ProcessHandle for Voter {
+    fn on_start(&mut self) {
+        send_to(rank(), Markup);
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+fn rpc_latency() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Client>("Clients", 2)
+        .add_pool::<Server>("Servers", 1)
+        .within_pool_latency("Clients", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .within_pool_latency("Servers", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .between_pool_latency(
+            "Clients",
+            "Servers",
+            Distributions::Uniform(Jiffies(10), Jiffies(20)),
+        )
+        .record_timeline()
+        .time_budget(Jiffies(1000))
+        .seed(7)
+}
+
+fn check(mut sim: Box<dyn SimulationRunner>) {
+    let report = sim.run_full_budget();
+    let timeline = sim.timeline().expect("Timeline is recorded");
+    let events = timeline.events();
+    assert!(report.steps() > 0);
+    assert_eq!(
+        events.iter().filter(|e| e.kind == TraceKind::Start).count(),
+        3
+    );
+    assert_eq!(timeline.pool_of(2), "Servers");
+
+    // Every message was handled after it was sent, by a process that was running at that time
+    for event in events {
+        if let TraceKind::Message {
+            source, sent_at, ..
+        } = event.kind
+        {
+            assert!(sent_at < event.time);
+            assert!(events.iter().any(|e| e.rank == source && e.time == sent_at));
+        }
+    }
+
+    let path = std::env::temp_dir().join("dscale_rpc_latency.json");
+    timeline
+        .write_chrome_trace(File::create(&path).unwrap())
+        .unwrap();
+    let trace = std::fs::read_to_string(&path).unwrap();
+    assert!(trace.contains("\"args\":{\"name\":\"P2 (Servers)\"}"));
+    assert!(trace.contains("{\"name\":\"Request\",\"cat\":\"step\""));
+    assert_eq!(
+        trace.matches("\"ph\":\"s\"").count(),
+        trace.matches("\"ph\":\"f\"").count()
+    );
+    println!("Wrote {} events to {}", events.len(), path.display());
+
+    let filter = ChartFilter::default()
+        .window(Jiffies(0), Jiffies(100))
+        .ranks([0, 2])
+        .messages(["Request", "Response"]);
+
+    let mut mermaid = Vec::new();
+    timeline.write_mermaid(&filter, &mut mermaid).unwrap();
+    let mermaid = String::from_utf8(mermaid).unwrap();
+    assert!(mermaid.starts_with("sequenceDiagram\n"));
+    assert!(mermaid.contains("participant P2 as P2 (Servers)"));
+    assert!(mermaid.contains("P0->>P2: Request (0.."));
+    assert!(!mermaid.contains("P1"));
+
+    // Processes that are not in the timeline are left out
+    let mut beyond = Vec::new();
+    timeline
+        .write_mermaid(&ChartFilter::default().ranks([2, 7]), &mut beyond)
+        .unwrap();
+    let beyond = String::from_utf8(beyond).unwrap();
+    assert!(beyond.contains("participant P2"));
+    assert!(!beyond.contains("P7"));
+    timeline
+        .write_svg(&ChartFilter::default().ranks([7]), std::io::sink())
+        .unwrap();
+
+    let mut plantuml = Vec::new();
+    timeline
+        .write_plantuml(&ChartFilter::default().messages(["Request"]), &mut plantuml)
+        .unwrap();
+    let plantuml = String::from_utf8(plantuml).unwrap();
+    assert!(plantuml.contains("P1 -> P2 : Request"));
+    assert!(!plantuml.contains("Response"));
+
+    let mut svg = Vec::new();
+    timeline.write_svg(&filter, &mut svg).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    assert_eq!(
+        svg.matches("marker-end").count(),
+        mermaid.matches("->>").count()
+    );
+    std::fs::write(std::env::temp_dir().join("dscale_rpc_latency.svg"), svg).unwrap();
+}
+
+fn labels() {
+    let message = MessagePtr(Arc::new(Labeled));
+    assert_eq!(format!("{message:?}"), "custom label");
+}
+
+fn escaped_labels() {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Voter>("Voters", 1)
+        .within_pool_latency("Voters", Distributions::Uniform(Jiffies(1), Jiffies(1)))
+        .record_timeline()
+        .time_budget(Jiffies(100))
+        .build();
+    sim.run_full_budget();
+    let timeline = sim.timeline().expect("Timeline is recorded");
+
+    let mut plantuml = Vec::new();
+    timeline
+        .write_plantuml(&ChartFilter::default(), &mut plantuml)
+        .unwrap();
+    let plantuml = String::from_utf8(plantuml).unwrap();
+    assert!(plantuml.contains("P0 -> P0 : Vote~<Term~> cast (0.."));
+}
+
+fn main() {
+    labels();
+    escaped_labels();
+    check(rpc_latency().simple().build());
+    check(rpc_latency().parallel(Threads::Specific(2)).build());
+
+    let mut untraced = SimulationBuilder::default()
+        .add_pool::<Client>("Clients", 1)
+        .add_pool::<Server>("Servers", 1)
+        .within_pool_latency("Clients", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .within_pool_latency("Servers", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .between_pool_latency(
+            "Clients",
+            "Servers",
+            Distributions::Uniform(Jiffies(10), Jiffies(20)),
+        )
+        .time_budget(Jiffies(100))
+        .build();
+    untraced.run_full_budget();
+    assert!(untraced.timeline().is_none());
+}
@@ -0,0 +1,76 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use dscale::*;
+
+// Protocol code instrumented with `tracing` gets simulated time, rank and pool attached for free.
+
+#[derive(Clone)]
+struct Hello;
+
+impl Message for Hello {}
+
+#[derive(Default)]
+struct Greeter;
+
+impl ProcessHandle for Greeter {
+    fn on_start(&mut self) {
+        if rank() == 0 {
+            tracing::info!("saying hello");
+            broadcast_within_pool("Greeters", Hello);
+        }
+    }
+
+    fn on_message(&mut self, from: Rank, _message: MessagePtr) {
+        tracing::info!(from, "got hello");
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn greeters() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<Greeter>("Greeters", 3)
+        .within_pool_latency("Greeters", Distributions::Uniform(Jiffies(5), Jiffies(5)))
+        .time_budget(Jiffies(100))
+}
+
+fn main() {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    tracing_subscriber::fmt()
+        .with_timer(dscale::SimTime)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .init();
+
+    greeters().simple().build().run_full_budget();
+    greeters()
+        .parallel(Threads::Specific(2))
+        .build()
+        .run_full_budget();
+
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    print!("{output}");
+
+    assert_eq!(output.matches("saying hello").count(), 2);
+    assert_eq!(output.matches("got hello").count(), 6);
+    assert!(output.contains("0 jfs  INFO step{sim_time=0 rank=0 pool=\"Greeters\"}"));
+    // Latency is 5, plus one jiffy of delivery
+    assert!(output.contains("6 jfs  INFO step{sim_time=6 rank=2 pool=\"Greeters\"}"));
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use dscale::{global::kv, *};
+use examples::{
+    multidc_pingpong::{Ping, PingProcess, Pong, PongProcess},
+    typed_pingpong::{PongerMsg, TypedPingProcess, TypedPongProcess},
+};
+
+fn pingpong<Pinger, Ponger>() -> (usize, usize)
+where
+    Pinger: ProcessHandle + Default + Send + 'static,
+    Ponger: ProcessHandle + Default + Send + 'static,
+{
+    let mut sim = simulation::<Pinger, Ponger>();
+    sim.run_full_budget();
+    counts()
+}
+
+fn simulation<Pinger, Ponger>() -> Box<dyn SimulationRunner>
+where
+    Pinger: ProcessHandle + Default + Send + 'static,
+    Ponger: ProcessHandle + Default + Send + 'static,
+{
+    let sim = SimulationBuilder::default()
+        .add_pool::<Pinger>("Pingers", 3)
+        .add_pool::<Ponger>("Pongers", 2)
+        .vnic_bandwidth(BandwidthConfig::Unbounded)
+        .within_pool_latency("Pingers", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .within_pool_latency("Pongers", Distributions::Uniform(Jiffies(0), Jiffies(10)))
+        .between_pool_latency(
+            "Pingers",
+            "Pongers",
+            Distributions::Uniform(Jiffies(10), Jiffies(20)),
+        )
+        .time_budget(Jiffies(100_000))
+        .seed(5)
+        .build();
+
+    kv::set::<usize>("pings", 0);
+    kv::set::<usize>("pongs", 0);
+    sim
+}
+
+fn counts() -> (usize, usize) {
+    (kv::get::<usize>("pings"), kv::get::<usize>("pongs"))
+}
+
+fn main() {
+    // Dispatch accepts members of the set only
+    assert!(matches!(
+        PongerMsg::from_message(&MessagePtr(Arc::new(Ping))),
+        Some(PongerMsg::Ping(_))
+    ));
+    assert!(PongerMsg::from_message(&MessagePtr(Arc::new(Pong))).is_none());
+
+    // Typed processes behave exactly like their untyped originals
+    let typed = pingpong::<Typed<TypedPingProcess>, Typed<TypedPongProcess>>();
+    println!("Typed (pings, pongs): {typed:?}");
+    assert_eq!(typed, pingpong::<PingProcess, PongProcess>());
+    assert_eq!(typed, (9388, 9387));
+
+    // The wrapper forwards the snapshot of the process, so it can be forked
+    let mut sim = simulation::<Typed<TypedPingProcess>, Typed<TypedPongProcess>>();
+    sim.run_sub_budget(Jiffies(50_000));
+    let mut fork = sim.fork();
+    fork.run_full_budget();
+    sim.run_full_budget();
+    assert_eq!(counts(), typed);
+
+    // Debug builds check that the target runs the process the send is typed for
+    if cfg!(debug_assertions) {
+        let untyped_pongers =
+            std::panic::catch_unwind(pingpong::<Typed<TypedPingProcess>, PongProcess>);
+        assert!(untyped_pongers.is_err());
+    }
+}
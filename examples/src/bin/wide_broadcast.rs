@@ -0,0 +1,46 @@
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use dscale::*;
+use examples::wide_broadcast::{DELIVERED, Gossiper, Unicaster};
+
+const NUM_PROCS: usize = 5_000;
+
+fn wide<P: ProcessHandle + Default + Send + 'static>() -> SimulationBuilder {
+    SimulationBuilder::default()
+        .add_pool::<P>("wide", NUM_PROCS)
+        .vnic_bandwidth(BandwidthConfig::Unbounded)
+        .within_pool_latency("wide", Distributions::Uniform(Jiffies(5), Jiffies(50)))
+        .time_budget(Jiffies(200))
+        .seed(7)
+}
+
+/// Runs the whole budget and returns the delivered messages and how many per second.
+fn measure(name: &str, mut sim: Box<dyn SimulationRunner>) -> (usize, f64) {
+    DELIVERED.store(0, Ordering::Relaxed);
+    let start = Instant::now();
+    sim.run_full_budget();
+    let elapsed = start.elapsed();
+    let delivered = DELIVERED.load(Ordering::Relaxed);
+
+    let throughput = delivered as f64 / elapsed.as_secs_f64();
+    println!("{name}: {elapsed:?}, deliveries: {delivered}");
+    println!("  deliveries/sec: {throughput:.2}");
+    assert!(delivered > 0);
+    (delivered, throughput)
+}
+
+fn main() {
+    // Sending to every target one by one is what broadcasts used to be expanded to
+    let (unicasts, unicast) = measure("Unicast", wide::<Unicaster>().simple().build());
+    let (broadcasts, broadcast) = measure("Broadcast", wide::<Gossiper>().simple().build());
+    assert_eq!(unicasts, broadcasts);
+    measure(
+        "Scalable broadcast",
+        wide::<Gossiper>().parallel(Threads::All).build(),
+    );
+
+    let speedup = broadcast / unicast;
+    println!("\nBroadcast speedup: {speedup:.2}x");
+    assert!(speedup > 1.0);
+}
@@ -0,0 +1,41 @@
+use dscale::*;
+
+use crate::token_ring::Token;
+
+// Same ring as in `token_ring`, written against an explicit context instead of the free functions.
+
+#[derive(Clone, Default)]
+pub struct ContextTokenHolder {
+    pub holds: bool,
+    pub passes: usize,
+}
+
+impl ContextTokenHolder {
+    fn take(&mut self, ctx: &mut Ctx) {
+        self.holds = true;
+        ctx.schedule_timer_after(Jiffies(5));
+    }
+}
+
+impl ContextProcess for ContextTokenHolder {
+    fn on_start(&mut self, ctx: &mut Ctx) {
+        if ctx.rank() == 0 {
+            self.take(ctx);
+        }
+    }
+
+    fn on_message(&mut self, ctx: &mut Ctx, _from: Rank, message: MessagePtr) {
+        let _ = message.as_type::<Token>();
+        self.take(ctx);
+    }
+
+    fn on_timer(&mut self, ctx: &mut Ctx, _id: TimerId) {
+        self.holds = false;
+        self.passes += 1;
+        ctx.send_to((ctx.rank() + 1) % ctx.process_number(), Token);
+    }
+
+    fn snapshot(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
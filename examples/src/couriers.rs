@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use dscale::{
+    checkpoint,
+    global::{configuration::process_number, kv},
+    *,
+};
+use serde::{Deserialize, Serialize};
+
+// Every courier starts a parcel. A courier holds each parcel it receives for a
+// few jiffies, then hands it on to one of the next three couriers, until the
+// parcel made `HOPS` hops. Where a parcel goes and how long it is held depend
+// only on the parcel and the courier, so every runner ends in the same state.
+
+pub const HOPS: usize = 2_000;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Parcel {
+    hops: usize,
+}
+
+impl Message for Parcel {
+    fn virtual_size(&self) -> usize {
+        64
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Courier {
+    holding: BTreeMap<TimerId, Parcel>,
+    last_step: Jiffies,
+    pub parcels: usize,
+    pub hops: usize,
+}
+
+impl Courier {
+    // Steps of a process happen in time order, also when they are rolled back
+    fn observe_time(&mut self) {
+        assert!(now() >= self.last_step, "P{} went back in time", rank());
+        self.last_step = now();
+    }
+}
+
+impl ProcessHandle for Courier {
+    fn on_start(&mut self) {
+        self.observe_time();
+        send_to(rank(), Parcel { hops: 0 });
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        self.observe_time();
+        // Also counts handlings that are rolled back later
+        kv::modify::<usize>("handled", |handled| *handled += 1);
+        let parcel = *message.as_type::<Parcel>();
+        self.parcels += 1;
+        self.hops += parcel.hops;
+        if parcel.hops < HOPS {
+            let delay = 1 + (rank() * 7 + parcel.hops) % 4;
+            self.holding
+                .insert(schedule_timer_after(Jiffies(delay)), parcel);
+        }
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        self.observe_time();
+        let parcel = self.holding.remove(&id).expect("Unknown timer");
+        let next = (rank() + 1 + parcel.hops % 3) % process_number();
+        send_to(
+            next,
+            Parcel {
+                hops: parcel.hops + 1,
+            },
+        );
+    }
+
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn save(&self) -> Option<Vec<u8>> {
+        Some(checkpoint::encode(self))
+    }
+
+    fn load(&mut self, state: &[u8]) {
+        *self = checkpoint::decode(state);
+    }
+}
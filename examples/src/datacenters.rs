@@ -0,0 +1,74 @@
+use dscale::*;
+
+// Two datacenters pass a token around their own fast ring until it made
+// `HOPS` hops. Every tenth token a replica sends a digest to its counterpart
+// in the other datacenter.
+
+pub const HOPS: usize = 20_000;
+
+pub struct Token(usize);
+
+impl Message for Token {}
+
+pub struct Digest(pub usize);
+
+impl Message for Digest {}
+
+pub struct Replica {
+    next: Rank,
+    remote: Rank,
+    first: bool,
+    last_step: Jiffies,
+    pub tokens: usize,
+    pub digests: usize,
+    pub remote_tokens: usize,
+}
+
+impl Replica {
+    /// Builds a replica of one of two equally sized pools added one after another.
+    pub fn new(rank: Rank, ctx: &FactoryCtx) -> Self {
+        let first = rank - ctx.index;
+        Self {
+            next: first + (ctx.index + 1) % ctx.pool_size,
+            remote: (rank + ctx.pool_size) % (2 * ctx.pool_size),
+            first: ctx.index == 0,
+            last_step: Jiffies(0),
+            tokens: 0,
+            digests: 0,
+            remote_tokens: 0,
+        }
+    }
+
+    // Steps of a process happen in time order, whatever the runner
+    fn observe_time(&mut self) {
+        assert!(now() >= self.last_step, "P{} went back in time", rank());
+        self.last_step = now();
+    }
+}
+
+impl ProcessHandle for Replica {
+    fn on_start(&mut self) {
+        self.observe_time();
+        if self.first {
+            send_to(self.next, Token(1));
+        }
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        self.observe_time();
+        if let Some(&Token(hops)) = message.try_as_type::<Token>() {
+            self.tokens += 1;
+            if self.tokens.is_multiple_of(10) {
+                send_to(self.remote, Digest(self.tokens));
+            }
+            if hops < HOPS {
+                send_to(self.next, Token(hops + 1));
+            }
+        } else {
+            self.digests += 1;
+            self.remote_tokens = message.as_type::<Digest>().0;
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
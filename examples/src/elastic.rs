@@ -0,0 +1,95 @@
+use dscale::*;
+
+// A coordinator grows the worker pool by one spawned worker per round, then retires them again.
+// Every round it broadcasts a job to the pool and counts the replies.
+
+#[derive(Clone)]
+pub struct Job {
+    round: usize,
+}
+#[derive(Clone)]
+pub struct Done {
+    round: usize,
+}
+#[derive(Clone)]
+pub struct Retire;
+
+impl Message for Job {}
+impl Message for Done {}
+impl Message for Retire {}
+
+const GROW_ROUNDS: usize = 4;
+
+#[derive(Default)]
+pub struct Coordinator {
+    pub spawned: Vec<Rank>,
+    pub retired: Vec<Rank>,
+    pub replies: Vec<usize>,
+    job_timer: Option<TimerId>,
+}
+
+impl Coordinator {
+    fn next_round(&mut self) {
+        let round = self.replies.len();
+        self.replies.push(0);
+        if round < GROW_ROUNDS {
+            let rank = spawn_with("Workers", |_, ctx| Worker {
+                index: ctx.index,
+                jobs: 0,
+            });
+            self.spawned.push(rank);
+        } else if let Some(rank) = self.spawned.pop() {
+            send_to(rank, Retire);
+            self.retired.push(rank);
+        } else {
+            // Everyone spawned is gone, this job is dropped
+            send_to(self.retired[0], Job { round });
+            return;
+        }
+        // Leave time for the pool to change before the job goes out
+        self.job_timer = Some(schedule_timer_after(Jiffies(50)));
+        schedule_timer_after(Jiffies(100));
+    }
+}
+
+impl ProcessHandle for Coordinator {
+    fn on_start(&mut self) {
+        schedule_timer_after(Jiffies(100));
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        let done = message.as_type::<Done>();
+        self.replies[done.round] += 1;
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        if self.job_timer == Some(id) {
+            let round = self.replies.len() - 1;
+            broadcast_within_pool("Workers", Job { round });
+        } else {
+            self.next_round();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Worker {
+    pub index: usize,
+    pub jobs: usize,
+}
+
+impl ProcessHandle for Worker {
+    fn on_start(&mut self) {}
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        if let Some(job) = message.try_as_type::<Job>() {
+            self.jobs += 1;
+            send_to(from, Done { round: job.round });
+        } else {
+            let _ = message.as_type::<Retire>();
+            exit();
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
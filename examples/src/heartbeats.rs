@@ -0,0 +1,51 @@
+use dscale::*;
+
+// Every node heartbeats the peers it was constructed with, at its own period.
+
+#[derive(Clone)]
+pub struct Heartbeat;
+
+impl Message for Heartbeat {}
+
+pub struct Heartbeater {
+    pub peers: Vec<Rank>,
+    pub period: Jiffies,
+    pub incarnation: usize,
+    pub sent: usize,
+    pub received: usize,
+}
+
+impl Heartbeater {
+    /// Builds a node of a ring that heartbeats both its neighbours.
+    pub fn in_ring(rank: Rank, ctx: &FactoryCtx) -> Self {
+        let first = rank - ctx.index;
+        let next = first + (ctx.index + 1) % ctx.pool_size;
+        let prev = first + (ctx.index + ctx.pool_size - 1) % ctx.pool_size;
+        Self {
+            peers: vec![prev, next],
+            period: Jiffies(10 * (ctx.index + 1)),
+            incarnation: ctx.incarnation,
+            sent: 0,
+            received: 0,
+        }
+    }
+}
+
+impl ProcessHandle for Heartbeater {
+    fn on_start(&mut self) {
+        schedule_periodic_timer_after(self.period);
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        let _ = message.as_type::<Heartbeat>();
+        self.received += 1;
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        for &peer in &self.peers {
+            send_to(peer, Heartbeat);
+        }
+        self.sent += 1;
+        schedule_periodic_timer_after(self.period);
+    }
+}
@@ -0,0 +1,27 @@
+use dscale::{global::configuration, *};
+
+// Hold model: every process keeps a fixed number of messages in flight and answers
+// each one it receives with a new one. Handlers do almost nothing, so the cost of
+// a run is dominated by the event queue, which always holds the same number of events.
+
+#[derive(Clone)]
+pub struct Token;
+
+impl Message for Token {}
+
+pub const IN_FLIGHT: usize = 200;
+
+#[derive(Default)]
+pub struct Holder;
+
+impl ProcessHandle for Holder {
+    fn on_start(&mut self) {
+        (0..IN_FLIGHT).for_each(|_| send_random(Token));
+    }
+
+    fn on_message(&mut self, from: Rank, _message: MessagePtr) {
+        send_to((from + rank() + 1) % configuration::process_number(), Token);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
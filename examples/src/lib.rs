@@ -1,8 +1,23 @@
 #![allow(non_snake_case)]
 
+pub mod async_rpc;
 pub mod bandwidth;
 pub mod broadcast;
+pub mod context_token_ring;
+pub mod couriers;
+pub mod datacenters;
+pub mod elastic;
+pub mod heartbeats;
 pub mod heavy_broadcast;
+pub mod hold;
 pub mod multidc_pingpong;
+pub mod nursery;
+pub mod quorum;
+pub mod reconfiguration;
 pub mod ring;
+pub mod rpc_latency;
+pub mod rumors;
 pub mod timers;
+pub mod token_ring;
+pub mod typed_pingpong;
+pub mod wide_broadcast;
@@ -0,0 +1,105 @@
+use std::{thread, time::Duration};
+
+use dscale::*;
+
+// An origin spawns a child into a nursery every few hundred jiffies. Each child
+// greets a process of a far pool, which passes a token around its fast ring
+// meanwhile. The origin is far from that pool but close to the nursery, so its
+// children reach the far pool much sooner than the origin itself could.
+
+pub const SPAWNS: usize = 50;
+pub const HOPS: usize = 20_000;
+
+pub struct Token(usize);
+
+impl Message for Token {}
+
+pub struct Hello(usize);
+
+impl Message for Hello {}
+
+#[derive(Default)]
+pub struct Origin {
+    spawned: usize,
+}
+
+impl ProcessHandle for Origin {
+    fn on_start(&mut self) {
+        schedule_timer_after(Jiffies(700));
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        // A slow step, so other pools run ahead as far as the runner lets them meanwhile
+        thread::sleep(Duration::from_millis(2));
+        let child = self.spawned;
+        spawn_with("Nursery", move |_, _| Child(Some(child)));
+        self.spawned += 1;
+        if self.spawned < SPAWNS {
+            schedule_timer_after(Jiffies(700));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Child(Option<usize>);
+
+impl ProcessHandle for Child {
+    fn on_start(&mut self) {
+        if let Some(child) = self.0 {
+            let far = list_pool("Far");
+            send_to(far[child % far.len()], Hello(child));
+        }
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+pub struct Far {
+    next: Rank,
+    first: bool,
+    last_step: Jiffies,
+    pub tokens: usize,
+    // Every child that greeted, with the tokens handled before
+    pub hellos: Vec<(usize, usize)>,
+}
+
+impl Far {
+    pub fn new(rank: Rank, ctx: &FactoryCtx) -> Self {
+        Self {
+            next: rank - ctx.index + (ctx.index + 1) % ctx.pool_size,
+            first: ctx.index == 0,
+            last_step: Jiffies(0),
+            tokens: 0,
+            hellos: Vec::new(),
+        }
+    }
+}
+
+impl ProcessHandle for Far {
+    fn on_start(&mut self) {
+        if self.first {
+            send_to(self.next, Token(1));
+        }
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        // Steps of a process happen in time order, whatever the runner
+        assert!(now() >= self.last_step, "P{} went back in time", rank());
+        self.last_step = now();
+        if let Some(&Token(hops)) = message.try_as_type::<Token>() {
+            self.tokens += 1;
+            if hops < HOPS {
+                send_to(self.next, Token(hops + 1));
+            }
+        } else {
+            let Hello(child) = *message.as_type::<Hello>();
+            self.hellos.push((child, self.tokens));
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,86 @@
+use dscale::*;
+
+// Every round a proposer sends a prepare to a quorum of acceptors and records the promises.
+// The quorum is reached either with one multicast or with a send_to per acceptor.
+
+#[derive(Clone)]
+pub struct Prepare {
+    round: usize,
+}
+#[derive(Clone)]
+pub struct Promise {
+    round: usize,
+}
+
+impl Message for Prepare {}
+impl Message for Promise {}
+
+pub const QUORUM: usize = 3;
+pub const ROUNDS: usize = 10;
+
+#[derive(Default)]
+pub struct Proposer {
+    pub use_multicast: bool,
+    round: usize,
+    /// (round, acceptor, arrival time) of every promise.
+    pub promises: Vec<(usize, Rank, Jiffies)>,
+}
+
+impl Proposer {
+    pub fn new(use_multicast: bool) -> Self {
+        Self {
+            use_multicast,
+            ..Default::default()
+        }
+    }
+}
+
+impl ProcessHandle for Proposer {
+    fn on_start(&mut self) {
+        schedule_timer_after(Jiffies(20));
+    }
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        let promise = message.as_type::<Promise>();
+        self.promises.push((promise.round, from, now()));
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        let acceptors = list_pool("Acceptors");
+        let quorum: Vec<Rank> = (0..QUORUM)
+            .map(|i| acceptors[(self.round + i) % acceptors.len()])
+            .collect();
+        let prepare = Prepare { round: self.round };
+        if self.use_multicast {
+            multicast(&quorum, prepare);
+        } else {
+            quorum
+                .iter()
+                .for_each(|&acceptor| send_to(acceptor, prepare.clone()));
+        }
+
+        self.round += 1;
+        if self.round < ROUNDS {
+            schedule_timer_after(Jiffies(20));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Acceptor;
+
+impl ProcessHandle for Acceptor {
+    fn on_start(&mut self) {}
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        let prepare = message.as_type::<Prepare>();
+        send_to(
+            from,
+            Promise {
+                round: prepare.round,
+            },
+        );
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,90 @@
+use dscale::*;
+
+// A leader keeps "voters" and "learners" pools. Every round it asks the voters for a vote,
+// then promotes a learner to voter, and once there are no learners left, demotes a voter.
+
+#[derive(Clone)]
+pub struct Ballot {
+    round: usize,
+}
+#[derive(Clone)]
+pub struct Vote {
+    round: usize,
+}
+
+impl Message for Ballot {}
+impl Message for Vote {}
+
+const ROUNDS: usize = 5;
+
+#[derive(Default)]
+pub struct Leader {
+    /// (voters, learners) as seen at the start of every round.
+    pub membership: Vec<(Vec<Rank>, Vec<Rank>)>,
+    /// Voters seen right after changing membership in the same step.
+    pub voters_after_change: Vec<Vec<Rank>>,
+    pub votes: Vec<usize>,
+}
+
+impl ProcessHandle for Leader {
+    fn on_start(&mut self) {
+        create_pool("voters");
+        create_pool("learners");
+        let nodes = list_pool("Nodes");
+        let (voters, learners) = nodes.split_at(2);
+        voters.iter().for_each(|&rank| join_pool("voters", rank));
+        learners
+            .iter()
+            .for_each(|&rank| join_pool("learners", rank));
+        schedule_timer_after(Jiffies(100));
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        let vote = message.as_type::<Vote>();
+        self.votes[vote.round] += 1;
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        let round = self.votes.len();
+        let voters = list_pool("voters");
+        let learners = list_pool("learners");
+        self.membership.push((voters.to_vec(), learners.to_vec()));
+        self.votes.push(0);
+        broadcast_within_pool("voters", Ballot { round });
+
+        if let Some(&learner) = learners.first() {
+            leave_pool("learners", learner);
+            join_pool("voters", learner);
+        } else if let Some(&voter) = voters.last() {
+            leave_pool("voters", voter);
+            join_pool("learners", voter);
+        }
+        self.voters_after_change.push(list_pool("voters").to_vec());
+
+        if round + 1 < ROUNDS {
+            schedule_timer_after(Jiffies(100));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Node;
+
+impl ProcessHandle for Node {
+    fn on_start(&mut self) {
+        // Created by the leader as well, which keeps the voters it added
+        create_pool("voters");
+    }
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        let ballot = message.as_type::<Ballot>();
+        send_to(
+            from,
+            Vote {
+                round: ballot.round,
+            },
+        );
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,64 @@
+use dscale::{global::metrics, *};
+
+// Clients in one data center keep a single request in flight to servers in another one.
+// Round trip times end up in a histogram of the metrics registry.
+
+#[derive(Clone)]
+pub struct Request {
+    sent_at: Jiffies,
+}
+#[derive(Clone)]
+pub struct Response {
+    sent_at: Jiffies,
+}
+
+impl Message for Request {}
+impl Message for Response {}
+
+#[derive(Default)]
+pub struct Client {}
+
+impl Client {
+    fn request(&self) {
+        send_random_from_pool("Servers", Request { sent_at: now() });
+        metrics::increment("requests", 1);
+    }
+}
+
+impl ProcessHandle for Client {
+    fn on_start(&mut self) {
+        self.request();
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        let response = message.as_type::<Response>();
+        metrics::record("rtt", (now() - response.sent_at).0 as u64);
+        self.request();
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+#[derive(Default)]
+pub struct Server {
+    served: usize,
+}
+
+impl ProcessHandle for Server {
+    fn on_start(&mut self) {}
+
+    fn on_message(&mut self, from: Rank, message: MessagePtr) {
+        let request = message.as_type::<Request>();
+        send_to(
+            from,
+            Response {
+                sent_at: request.sent_at,
+            },
+        );
+        self.served += 1;
+        metrics::increment("served", 1);
+        metrics::set_gauge("served_total", self.served as f64);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use dscale::*;
+
+// Every gossiper tells a rumor to a random peer. A gossiper passes on each of
+// the first `RETOLD` rumors it hears to another random peer. Peers and latencies
+// are drawn at random, but rumors are all alike, so it does not matter in which
+// order a gossiper hears the ones arriving at the same time.
+
+pub const RETOLD: usize = 300;
+
+#[derive(Clone, Copy)]
+pub struct Rumor;
+
+impl Message for Rumor {}
+
+#[derive(Clone, Default)]
+pub struct Gossiper {
+    pub heard: usize,
+    // Rumors told to every peer
+    pub told: BTreeMap<Rank, usize>,
+}
+
+impl ProcessHandle for Gossiper {
+    fn on_start(&mut self) {
+        send_random(Rumor);
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {
+        self.heard += 1;
+        if self.heard <= RETOLD {
+            let peer = choose_from_pool("Gossipers");
+            *self.told.entry(peer).or_default() += 1;
+            send_to(peer, Rumor);
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+
+    fn snapshot(&self) -> Option<Box<dyn ProcessHandle + Send>> {
+        Some(Box::new(self.clone()))
+    }
+}
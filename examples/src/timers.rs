@@ -8,26 +8,18 @@ pub enum LazyPingPongMessage {
 
 impl Message for LazyPingPongMessage {}
 
+#[derive(Default)]
 pub struct LazyPingPong {
     heartbeat_timer: usize,
     ping_count: usize,
 }
 
-impl Default for LazyPingPong {
-    fn default() -> Self {
-        Self {
-            heartbeat_timer: 0,
-            ping_count: 0,
-        }
-    }
-}
-
 impl ProcessHandle for LazyPingPong {
     fn on_start(&mut self) {
         debug_process!("Starting timer demo process");
 
         // Schedule a heartbeat timer to fire every 1000 jiffies
-        let timer_id = schedule_timer_after(Jiffies(1000));
+        let timer_id = schedule_periodic_timer_after(Jiffies(1000));
         self.heartbeat_timer = timer_id;
         debug_process!(
             "Scheduled heartbeat timer {} to fire in 1000 jiffies",
@@ -75,7 +67,7 @@ impl ProcessHandle for LazyPingPong {
             kv::modify::<usize>("heartbeats", |count| *count += 1);
 
             // Reschedule the heartbeat timer for continuous operation
-            let new_timer_id = schedule_timer_after(Jiffies(1000));
+            let new_timer_id = schedule_periodic_timer_after(Jiffies(1000));
             self.heartbeat_timer = new_timer_id;
             return;
         }
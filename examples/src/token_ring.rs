@@ -0,0 +1,40 @@
+use dscale::{global::configuration::process_number, *};
+
+// A single token travels around the ring. Each process holds it for a while before passing it on.
+
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Token;
+
+impl Message for Token {}
+
+#[derive(Default)]
+pub struct TokenHolder {
+    pub holds: bool,
+    pub passes: usize,
+}
+
+impl TokenHolder {
+    fn take(&mut self) {
+        self.holds = true;
+        schedule_timer_after(Jiffies(5));
+    }
+}
+
+impl ProcessHandle for TokenHolder {
+    fn on_start(&mut self) {
+        if rank() == 0 {
+            self.take();
+        }
+    }
+
+    fn on_message(&mut self, _from: Rank, message: MessagePtr) {
+        let _ = message.as_type::<Token>();
+        self.take();
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        self.holds = false;
+        self.passes += 1;
+        send_to((rank() + 1) % process_number(), Token);
+    }
+}
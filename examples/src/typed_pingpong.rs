@@ -0,0 +1,72 @@
+use dscale::{global::kv, *};
+
+use crate::multidc_pingpong::{Ping, Pong};
+
+// Same demo as `multidc_pingpong`, with messages dispatched into per-process enums.
+// Sending a `Ping` to a `TypedPingProcess` would not compile.
+
+message_set! {
+    pub enum PingerMsg {
+        Pong(Pong),
+    }
+}
+
+message_set! {
+    pub enum PongerMsg {
+        Ping(Ping),
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TypedPingProcess {}
+
+impl TypedPingProcess {
+    fn ping(&self) {
+        send_typed::<TypedPongProcess, _>(choose_from_pool("Pongers"), Ping);
+        kv::modify::<usize>("pings", |p| *p += 1);
+    }
+}
+
+impl TypedProcess for TypedPingProcess {
+    type Msg = PingerMsg;
+
+    fn on_start(&mut self) {
+        self.ping();
+    }
+
+    fn on_message(&mut self, _from: Rank, message: PingerMsg) {
+        match message {
+            PingerMsg::Pong(_) => self.ping(),
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+
+    fn snapshot(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TypedPongProcess {}
+
+impl TypedProcess for TypedPongProcess {
+    type Msg = PongerMsg;
+
+    fn on_start(&mut self) {}
+
+    fn on_message(&mut self, _from: Rank, message: PongerMsg) {
+        match message {
+            PongerMsg::Ping(_) => {
+                send_typed::<TypedPingProcess, _>(choose_from_pool("Pingers"), Pong);
+                kv::modify::<usize>("pongs", |p| *p += 1);
+            }
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+
+    fn snapshot(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
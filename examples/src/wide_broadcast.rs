@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use dscale::*;
+
+pub static DELIVERED: AtomicUsize = AtomicUsize::new(0);
+
+/// Every hundredth process broadcasts a beat to the whole pool every 10 jiffies.
+/// Handlers do next to nothing, so the run is dominated by the network queues.
+pub const BROADCASTER_EVERY: usize = 100;
+
+#[derive(Clone)]
+pub struct Beat;
+
+impl Message for Beat {}
+
+#[derive(Default)]
+pub struct Gossiper {}
+
+impl ProcessHandle for Gossiper {
+    fn on_start(&mut self) {
+        if rank().is_multiple_of(BROADCASTER_EVERY) {
+            schedule_timer_after(Jiffies(10));
+        }
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {
+        DELIVERED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        broadcast(Beat);
+        schedule_timer_after(Jiffies(10));
+    }
+}
+
+/// Like [`Gossiper`], but sends the beat to every process one by one,
+/// the way broadcasts used to be expanded into a message per target.
+#[derive(Default)]
+pub struct Unicaster {}
+
+impl ProcessHandle for Unicaster {
+    fn on_start(&mut self) {
+        if rank().is_multiple_of(BROADCASTER_EVERY) {
+            schedule_timer_after(Jiffies(10));
+        }
+    }
+
+    fn on_message(&mut self, _from: Rank, _message: MessagePtr) {
+        DELIVERED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        list_pool(GLOBAL_POOL)
+            .iter()
+            .for_each(|&target| send_to(target, Beat));
+        schedule_timer_after(Jiffies(10));
+    }
+}